@@ -2,8 +2,10 @@ import Group17
 
 
 def main():
-    Group17.onnx_make_inference("models/mnist-8.onnx", "mnist_data_0.pb", "mnist_output_0.pb",
-                                ["Input3", "Parameter193"])
+    # The inputs can be numpy arrays or serialized TensorProto files, the expected output is optional
+    outputs = Group17.onnx_make_inference("models/mnist-8.onnx", {"Input3": "mnist_data_0.pb"}, "mnist_output_0.pb")
+    for name, value in outputs.items():
+        print(name, value.shape, value.argmax())
 
 
 if __name__ == "__main__":
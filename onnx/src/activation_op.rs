@@ -0,0 +1,220 @@
+use half::f16;
+use ndarray::{Array, ArrayD, IxDyn};
+use num_traits::Float;
+use crate::binary_op::broadcast_with;
+use crate::tensor::Tensor;
+
+//OPSET VERSION = 20
+//Element-wise activation functions: the output has the shape and the element type of the input (f32, f64 or f16)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Activation {
+  /* x if x >= 0, alpha * x otherwise */
+  LeakyRelu { alpha: f32 },
+  /* x if x > 0, alpha * (e^x - 1) otherwise */
+  Elu { alpha: f32 },
+  /* gamma * x if x > 0, gamma * alpha * (e^x - 1) otherwise */
+  Selu { alpha: f32, gamma: f32 },
+  /* 1 / (1 + e^-x) */
+  Sigmoid,
+  /* max(0, min(1, alpha * x + beta)) */
+  HardSigmoid { alpha: f32, beta: f32 },
+  /* x * max(0, min(1, x / 6 + 1 / 2)) */
+  HardSwish,
+  Tanh,
+  /* ln(1 + e^x) */
+  Softplus,
+  /* x / (1 + |x|) */
+  Softsign,
+  /* x * P(X <= x) with X normal: 0.5 * x * (1 + erf(x / sqrt(2))), or its tanh approximation */
+  Gelu { tanh_approximation: bool },
+  /* x * tanh(softplus(x)) */
+  Mish,
+  /* x if x > alpha, 0 otherwise */
+  ThresholdedRelu { alpha: f32 },
+}
+
+/*
+This function computes an activation function on a single value
+  -It takes 2 parameters:
+    ~ activation: function to compute
+    ~ x: value
+*/
+fn activate<F: Float>(activation: Activation, x: F) -> F {
+  let zero = F::zero();
+  let one = F::one();
+  let constant = |c: f64| F::from(c).unwrap();
+  let softplus = |x: F| if x > zero { x + (-x).exp().ln_1p() } else { x.exp().ln_1p() };
+  match activation {
+    Activation::LeakyRelu { alpha } => if x < zero { constant(alpha as f64) * x } else { x },
+    Activation::Elu { alpha } => if x > zero { x } else { constant(alpha as f64) * x.exp_m1() },
+    Activation::Selu { alpha, gamma } => {
+      let gamma = constant(gamma as f64);
+      if x > zero { gamma * x } else { gamma * constant(alpha as f64) * x.exp_m1() }
+    }
+    /* Written so that the exponential never overflows */
+    Activation::Sigmoid => if x >= zero { one / (one + (-x).exp()) } else { x.exp() / (one + x.exp()) },
+    Activation::HardSigmoid { alpha, beta } => (constant(alpha as f64) * x + constant(beta as f64)).max(zero).min(one),
+    Activation::HardSwish => x * (x / constant(6.) + constant(0.5)).max(zero).min(one),
+    Activation::Tanh => x.tanh(),
+    Activation::Softplus => softplus(x),
+    Activation::Softsign => x / (one + x.abs()),
+    Activation::Gelu { tanh_approximation: false } => constant(0.5) * x * (one + constant(erf((x / constant(std::f64::consts::SQRT_2)).to_f64().unwrap()))),
+    Activation::Gelu { tanh_approximation: true } => {
+      let inner = constant((2. / std::f64::consts::PI).sqrt()) * (x + constant(0.044715) * x.powi(3));
+      constant(0.5) * x * (one + inner.tanh())
+    }
+    Activation::Mish => x * softplus(x).tanh(),
+    Activation::ThresholdedRelu { alpha } => if x > constant(alpha as f64) { x } else { zero },
+  }
+}
+
+/*
+This function computes the error function: with its Maclaurin series near 0, with the approximation 7.1.26 of
+Abramowitz and Stegun (error < 1.5e-7) elsewhere
+*/
+pub(crate) fn erf(x: f64) -> f64 {
+  if x.abs() < 0.5 {
+    /* 2 / sqrt(pi) * sum of (-1)^n x^(2n + 1) / (n! (2n + 1)) */
+    let mut power = x;
+    let mut sum = x;
+    for n in 1..10 {
+      power *= -x * x / n as f64;
+      sum += power / (2 * n + 1) as f64;
+    }
+    return sum * std::f64::consts::FRAC_2_SQRT_PI;
+  }
+  let t = 1. / (1. + 0.3275911 * x.abs());
+  let polynomial = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+  let y = 1. - polynomial * (-x * x).exp();
+  if x < 0. { -y } else { y }
+}
+
+/*
+This function executes an activation function
+  -It takes 2 parameters:
+    ~ activation: function to compute
+    ~ x: input, with a floating point element type
+  -It returns the result, with the shape and the element type of the input, or an error if the input isn't floating point
+*/
+pub fn activation(activation: Activation, x: &Tensor) -> Result<Tensor, String> {
+  match x {
+    Tensor::F32(x) => Ok(Tensor::F32(x.mapv(|v| activate(activation, v)))),
+    Tensor::F64(x) => Ok(Tensor::F64(x.mapv(|v| activate(activation, v)))),
+    Tensor::F16(x) => Ok(Tensor::F16(x.mapv(|v| f16::from_f32(activate(activation, v.to_f32()))))),
+    other => Err(format!("{:?} is not defined for {:?}", activation, other.data_type()))
+  }
+}
+
+/*
+This function executes the PRelu: x if x >= 0, slope * x otherwise
+  -It takes 2 parameters:
+    ~ x: input
+    ~ slope: slope, with the element type of the input, broadcast to the input (numpy style). When it can't be, a
+    slope with one value for each channel (axis 1) is applied to the whole channel
+  -It returns the result, with the shape of the input, or an error if the slope can't be broadcast
+*/
+pub fn prelu(x: &Tensor, slope: &Tensor) -> Result<Tensor, String> {
+  let slope_shape = slope_shape(x.shape(), slope.shape())?;
+  match (x, slope) {
+    (Tensor::F32(x), Tensor::F32(s)) => prelu_with(x, s, &slope_shape, 0.).map(Tensor::F32),
+    (Tensor::F64(x), Tensor::F64(s)) => prelu_with(x, s, &slope_shape, 0.).map(Tensor::F64),
+    (Tensor::F16(x), Tensor::F16(s)) => prelu_with(x, s, &slope_shape, f16::ZERO).map(Tensor::F16),
+    (Tensor::I64(x), Tensor::I64(s)) => prelu_with(x, s, &slope_shape, 0).map(Tensor::I64),
+    (Tensor::I32(x), Tensor::I32(s)) => prelu_with(x, s, &slope_shape, 0).map(Tensor::I32),
+    _ => Err(format!("PRelu is not defined between {:?} and {:?}", x.data_type(), slope.data_type()))
+  }
+}
+
+fn prelu_with<T>(x: &ArrayD<T>, slope: &ArrayD<T>, slope_shape: &[usize], zero: T) -> Result<ArrayD<T>, String>
+  where T: Copy + PartialOrd + std::ops::Mul<Output = T>
+{
+  let slope = slope.to_shape(IxDyn(slope_shape)).unwrap().into_owned();
+  broadcast_with(x, &slope, |v, s| if v < zero { v * s } else { v })
+}
+
+/* Shape with which the slope is broadcast to the input: its own one if it is unidirectionally broadcastable,
+(C, 1, ..., 1) if it has one value for each channel */
+fn slope_shape(x: &[usize], slope: &[usize]) -> Result<Vec<usize>, String> {
+  let unidirectional = slope.len() <= x.len() && slope.iter().rev().zip(x.iter().rev()).all(|(&s, &d)| s == d || s == 1);
+  if unidirectional {
+    return Ok(slope.to_vec());
+  }
+  let len: usize = slope.iter().product();
+  if x.len() >= 2 && len == x[1] {
+    let mut shape = vec![1; x.len() - 1];
+    shape[0] = len;
+    return Ok(shape);
+  }
+  Err(format!("slope {:?} cannot be broadcast to the input {:?}", slope, x))
+}
+
+/*
+This function executes the Clip: every value is limited to [min, max]
+  -It takes 3 parameters:
+    ~ x: input (floating point or integer)
+    ~ min: lower bound, if present
+    ~ max: upper bound, if present
+  -It returns the result, with the shape and the element type of the input
+*/
+pub fn clip(x: &Tensor, min: Option<f64>, max: Option<f64>) -> Result<Tensor, String> {
+  let min = min.unwrap_or(f64::NEG_INFINITY);
+  let max = max.unwrap_or(f64::INFINITY);
+  /* Integer bounds are rounded toward the inside of the interval */
+  let bounded = |v: f64| v.max(min).min(max);
+  let int_bounded = |v: f64| v.max(min.ceil()).min(max.floor());
+  Ok(match x {
+    Tensor::F32(x) => Tensor::F32(x.mapv(|v| bounded(v as f64) as f32)),
+    Tensor::F64(x) => Tensor::F64(x.mapv(bounded)),
+    Tensor::F16(x) => Tensor::F16(x.mapv(|v| f16::from_f64(bounded(v.to_f64())))),
+    /* i64 values aren't converted, so that the ones beyond 2^53 keep their precision */
+    Tensor::I64(x) => Tensor::I64(x.mapv(|v| if (v as f64) < min { min.ceil() as i64 } else if (v as f64) > max { max.floor() as i64 } else { v })),
+    Tensor::I32(x) => Tensor::I32(x.mapv(|v| int_bounded(v as f64) as i32)),
+    Tensor::U8(x) => Tensor::U8(x.mapv(|v| int_bounded(v as f64) as u8)),
+    Tensor::I8(x) => Tensor::I8(x.mapv(|v| int_bounded(v as f64) as i8)),
+    Tensor::Bool(_) => return Err("Clip is not defined for BOOL".to_string()),
+  })
+}
+
+#[allow(dead_code)]
+pub fn test_activation() {
+  let x = Tensor::F32(Array::from_shape_vec(5, vec![-2., -0.5, 0., 1., 3.]).unwrap().into_dyn());
+  let cases = [
+    (Activation::LeakyRelu { alpha: 0.1 }, "[-0.2, -0.05, 0, 1, 3]"),
+    (Activation::Elu { alpha: 1. }, "[-0.8647, -0.3935, 0, 1, 3]"),
+    (Activation::Selu { alpha: 1.6732632, gamma: 1.050701 }, "[-1.5202, -0.6918, 0, 1.0507, 3.1521]"),
+    (Activation::Sigmoid, "[0.1192, 0.3775, 0.5, 0.7311, 0.9526]"),
+    (Activation::HardSigmoid { alpha: 0.2, beta: 0.5 }, "[0.1, 0.4, 0.5, 0.7, 1]"),
+    (Activation::HardSwish, "[-0.3333, -0.2083, 0, 0.6667, 3]"),
+    (Activation::Tanh, "[-0.9640, -0.4621, 0, 0.7616, 0.9951]"),
+    (Activation::Softplus, "[0.1269, 0.4741, 0.6931, 1.3133, 3.0486]"),
+    (Activation::Softsign, "[-0.6667, -0.3333, 0, 0.5, 0.75]"),
+    (Activation::Gelu { tanh_approximation: false }, "[-0.0455, -0.1543, 0, 0.8413, 2.9960]"),
+    (Activation::Gelu { tanh_approximation: true }, "[-0.0454, -0.1543, 0, 0.8412, 2.9964]"),
+    (Activation::Mish, "[-0.2525, -0.2207, 0, 0.8651, 2.9865]"),
+    (Activation::ThresholdedRelu { alpha: 1. }, "[0, 0, 0, 0, 3]"),
+  ];
+  for (function, expected) in cases {
+    println!("{:?}: {:?}", function, activation(function, &x));
+    println!("expected: {}", expected);
+  }
+
+  // Clip on floats and on integers (with a single bound)
+  println!("clip: {:?}", clip(&x, Some(-1.), Some(2.)));
+  println!("expected: [-1, -0.5, 0, 1, 2]");
+  let integers = Tensor::I64(Array::from_shape_vec(3, vec![-5, 0, 7]).unwrap().into_dyn());
+  println!("clip int: {:?}", clip(&integers, None, Some(5.)));
+  println!("expected: [-5, 0, 5]");
+
+  // PRelu with a slope (C, 1, 1) broadcast to (N, C, H, W), and with a slope (C) applied to each channel
+  let x = Tensor::F32(Array::from_shape_vec((1, 2, 2, 2), vec![-1., 2., -3., 4., -5., 6., -7., 8.]).unwrap().into_dyn());
+  let slope = Tensor::F32(Array::from_shape_vec((2, 1, 1), vec![0.1, 0.5]).unwrap().into_dyn());
+  println!("prelu: {:?}", prelu(&x, &slope));
+  println!("expected: [[[[-0.1, 2], [-0.3, 4]], [[-2.5, 6], [-3.5, 8]]]]");
+  let x = Tensor::F32(Array::from_shape_vec((1, 2, 3), vec![-1., -2., -3., -4., -5., -6.]).unwrap().into_dyn());
+  let slope = Tensor::F32(Array::from_shape_vec(2, vec![0.1, 0.5]).unwrap().into_dyn());
+  println!("prelu per channel: {:?}", prelu(&x, &slope));
+  println!("expected: [[[-0.1, -0.2, -0.3], [-2, -2.5, -3]]]");
+
+  // Activations are defined only for floating point inputs
+  println!("sigmoid int: {:?}", activation(Activation::Sigmoid, &integers));
+}
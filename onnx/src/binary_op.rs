@@ -0,0 +1,204 @@
+use std::cell::Cell;
+use half::f16;
+use ndarray::{Array, ArrayD, IxDyn, Zip};
+use crate::tensor::{broadcast_shapes, Tensor};
+
+//OPSET VERSION = 13
+//Element-wise operations between two tensors of the same element type, with multidirectional (numpy) broadcasting:
+//the shapes are aligned on the last dimension and every dimension of size 1 is repeated (i.e. [N,C,H,W] + [C,1,1])
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+  Add,
+  Sub,
+  Mul,
+  /* Integer division truncates toward zero */
+  Div,
+  /* The exponent can have a different element type: it is converted to the type of the base */
+  Pow,
+  Max,
+  Min,
+  /* fmod = false: the result has the sign of the divisor (integers only), fmod = true: the sign of the dividend */
+  Mod { fmod: bool },
+}
+
+/* Element type on which the binary operations are defined */
+trait Element: Copy + Default {
+  fn apply(op: BinaryOp, x: Self, y: Self) -> Result<Self, &'static str>;
+}
+
+macro_rules! impl_float_element {
+  ($($elem:ty),*) => {
+    $(
+      impl Element for $elem {
+        fn apply(op: BinaryOp, x: Self, y: Self) -> Result<Self, &'static str> {
+          Ok(match op {
+            BinaryOp::Add => x + y,
+            BinaryOp::Sub => x - y,
+            BinaryOp::Mul => x * y,
+            BinaryOp::Div => x / y,
+            BinaryOp::Pow => x.powf(y),
+            /* NaN is propagated */
+            BinaryOp::Max => if x.is_nan() || x > y { x } else { y },
+            BinaryOp::Min => if x.is_nan() || x < y { x } else { y },
+            BinaryOp::Mod { fmod: true } => x % y,
+            BinaryOp::Mod { fmod: false } => return Err("fmod must be 1 for floating point inputs"),
+          })
+        }
+      }
+    )*
+  };
+}
+
+macro_rules! impl_int_element {
+  ($($elem:ty),*) => {
+    $(
+      impl Element for $elem {
+        #[allow(unused_comparisons)]
+        fn apply(op: BinaryOp, x: Self, y: Self) -> Result<Self, &'static str> {
+          Ok(match op {
+            BinaryOp::Add => x.wrapping_add(y),
+            BinaryOp::Sub => x.wrapping_sub(y),
+            BinaryOp::Mul => x.wrapping_mul(y),
+            BinaryOp::Div => x.checked_div(y).ok_or("integer division by zero")?,
+            BinaryOp::Pow => (x as f64).powf(y as f64) as $elem,
+            BinaryOp::Max => x.max(y),
+            BinaryOp::Min => x.min(y),
+            BinaryOp::Mod { fmod } => {
+              let remainder = x.checked_rem(y).ok_or("integer division by zero")?;
+              if !fmod && remainder != 0 && (remainder < 0) != (y < 0) { remainder.wrapping_add(y) } else { remainder }
+            }
+          })
+        }
+      }
+    )*
+  };
+}
+
+impl_float_element!(f32, f64);
+impl_int_element!(i64, i32, u8, i8);
+
+impl Element for f16 {
+  fn apply(op: BinaryOp, x: Self, y: Self) -> Result<Self, &'static str> {
+    f32::apply(op, x.to_f32(), y.to_f32()).map(f16::from_f32)
+  }
+}
+
+/*
+This function applies a function to the elements of two arrays broadcast to the same shape
+  -It takes 3 parameters:
+    ~ a: first operand
+    ~ b: second operand
+    ~ f: function computing an element of the result from the elements of the operands
+  -It returns the result, with the broadcast shape, or an error if the shapes aren't compatible
+*/
+pub fn broadcast_with<A: Copy, B: Copy, C>(a: &ArrayD<A>, b: &ArrayD<B>, f: impl Fn(A, B) -> C) -> Result<ArrayD<C>, String> {
+  let shape = match broadcast_shapes(a.shape(), b.shape()) {
+    Some(shape) => shape,
+    None => return Err(format!("cannot broadcast {:?} with {:?}", a.shape(), b.shape()))
+  };
+  let a = a.broadcast(IxDyn(&shape)).unwrap();
+  let b = b.broadcast(IxDyn(&shape)).unwrap();
+  Ok(Zip::from(a).and(b).map_collect(|&x, &y| f(x, y)))
+}
+
+/*
+This function executes a binary operation
+  -It takes 3 parameters:
+    ~ op: operation
+    ~ a: first operand
+    ~ b: second operand, with the same element type of the first one (except for the exponent of Pow)
+  -It returns the result, with the broadcast shape and the element type of the operands, or an error if the shapes
+  or the element types aren't compatible, or if an integer is divided by zero
+*/
+pub fn binary(op: BinaryOp, a: &Tensor, b: &Tensor) -> Result<Tensor, String> {
+  let converted;
+  let b = if op == BinaryOp::Pow && a.data_type() != b.data_type() {
+    converted = exponent_like(a, b);
+    &converted
+  } else {
+    b
+  };
+
+  match (a, b) {
+    (Tensor::F32(x), Tensor::F32(y)) => apply(op, x, y).map(Tensor::F32),
+    (Tensor::F64(x), Tensor::F64(y)) => apply(op, x, y).map(Tensor::F64),
+    (Tensor::I64(x), Tensor::I64(y)) => apply(op, x, y).map(Tensor::I64),
+    (Tensor::I32(x), Tensor::I32(y)) => apply(op, x, y).map(Tensor::I32),
+    (Tensor::U8(x), Tensor::U8(y)) => apply(op, x, y).map(Tensor::U8),
+    (Tensor::I8(x), Tensor::I8(y)) => apply(op, x, y).map(Tensor::I8),
+    (Tensor::F16(x), Tensor::F16(y)) => apply(op, x, y).map(Tensor::F16),
+    _ => Err(format!("{:?} is not defined between {:?} and {:?}", op, a.data_type(), b.data_type()))
+  }
+}
+
+fn apply<T: Element>(op: BinaryOp, a: &ArrayD<T>, b: &ArrayD<T>) -> Result<ArrayD<T>, String> {
+  /* The first error found by an element stops the operation once the result is built */
+  let error: Cell<Option<&'static str>> = Cell::new(None);
+  let result = broadcast_with(a, b, |x, y| T::apply(op, x, y).unwrap_or_else(|e| {
+    error.set(Some(e));
+    T::default()
+  }))?;
+  match error.get() {
+    Some(e) => Err(e.to_string()),
+    None => Ok(result)
+  }
+}
+
+/* Converts the exponent of Pow to the element type of the base */
+fn exponent_like(base: &Tensor, exponent: &Tensor) -> Tensor {
+  match base {
+    Tensor::F32(_) => Tensor::F32(exponent.to_f32()),
+    Tensor::F64(_) => Tensor::F64(match exponent {
+      Tensor::I64(e) => e.mapv(|v| v as f64),
+      Tensor::I32(e) => e.mapv(|v| v as f64),
+      other => other.to_f32().mapv(|v| v as f64)
+    }),
+    Tensor::F16(_) => Tensor::F16(exponent.to_f32().mapv(f16::from_f32)),
+    Tensor::I64(_) => Tensor::I64(exponent.to_i64()),
+    Tensor::I32(_) => Tensor::I32(exponent.to_i64().mapv(|v| v as i32)),
+    Tensor::U8(_) => Tensor::U8(exponent.to_i64().mapv(|v| v as u8)),
+    Tensor::I8(_) => Tensor::I8(exponent.to_i64().mapv(|v| v as i8)),
+    Tensor::Bool(_) => exponent.clone(),
+  }
+}
+
+#[allow(dead_code)]
+pub fn test_binary() {
+  // [N,C,H,W] + [C,1,1]: a different value is added to every channel
+  let x = Tensor::F32(Array::from_shape_vec((1, 2, 2, 2), vec![1., 2., 3., 4., 5., 6., 7., 8.]).unwrap().into_dyn());
+  let bias = Tensor::F32(Array::from_shape_vec((2, 1, 1), vec![10., 100.]).unwrap().into_dyn());
+  println!("add: {:?}", binary(BinaryOp::Add, &x, &bias));
+  println!("expected: [[[[11, 12], [13, 14]], [[105, 106], [107, 108]]]]");
+
+  // Both operands broadcast: (3, 1) - (2) = (3, 2)
+  let column = Tensor::I64(Array::from_shape_vec((3, 1), vec![1, 2, 3]).unwrap().into_dyn());
+  let row = Tensor::I64(Array::from_shape_vec(2, vec![10, 20]).unwrap().into_dyn());
+  println!("sub: {:?}", binary(BinaryOp::Sub, &column, &row));
+  println!("expected: [[-9, -19], [-8, -18], [-7, -17]]");
+
+  // Integer division truncates, Mod follows the sign of the divisor (fmod = 0) or of the dividend (fmod = 1)
+  let dividend = Tensor::I32(Array::from_shape_vec(4, vec![7, -7, 7, -7]).unwrap().into_dyn());
+  let divisor = Tensor::I32(Array::from_shape_vec(4, vec![3, 3, -3, -3]).unwrap().into_dyn());
+  println!("div: {:?}", binary(BinaryOp::Div, &dividend, &divisor));
+  println!("expected: [2, -2, -2, 2]");
+  println!("mod: {:?}", binary(BinaryOp::Mod { fmod: false }, &dividend, &divisor));
+  println!("expected: [1, 2, -2, -1]");
+  println!("fmod: {:?}", binary(BinaryOp::Mod { fmod: true }, &dividend, &divisor));
+  println!("expected: [1, -1, 1, -1]");
+
+  // Pow with an integer exponent on a float base, Max and Min with a scalar
+  let base = Tensor::F32(Array::from_shape_vec(3, vec![1., 2., 3.]).unwrap().into_dyn());
+  let exponent = Tensor::I64(ArrayD::from_elem(IxDyn(&[]), 2));
+  println!("pow: {:?}", binary(BinaryOp::Pow, &base, &exponent));
+  println!("expected: [1, 4, 9]");
+  let two = Tensor::F32(ArrayD::from_elem(IxDyn(&[]), 2.));
+  println!("max: {:?}", binary(BinaryOp::Max, &base, &two));
+  println!("expected: [2, 2, 3]");
+  println!("min: {:?}", binary(BinaryOp::Min, &base, &two));
+  println!("expected: [1, 2, 2]");
+
+  // Errors: incompatible shapes, different element types, division by zero
+  println!("mul wrong shapes: {:?}", binary(BinaryOp::Mul, &x, &base));
+  println!("mul wrong types: {:?}", binary(BinaryOp::Mul, &base, &row));
+  println!("div by zero: {:?}", binary(BinaryOp::Div, &row, &Tensor::I64(ArrayD::from_elem(IxDyn(&[]), 0))));
+}
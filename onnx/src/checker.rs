@@ -0,0 +1,278 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use crate::inference_error::node_name;
+use crate::onnx_structure::{ModelProto, NodeProto};
+use crate::op_registry::{normalize_domain, DEFAULT_DOMAIN};
+
+/* Rules of the ONNX IR verified by check_model */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+  /* The model has no graph */
+  MissingGraph,
+  /* A node input isn't a graph input, an initializer or the output of an earlier node */
+  UndefinedInput,
+  /* The same name is assigned by more than one node (or by a node and a graph input/initializer) */
+  DuplicateOutput,
+  /* The model has no opset import, or a node uses a domain without opset import */
+  MissingOpsetImport,
+  /* An attribute required by the operation isn't set */
+  MissingAttribute,
+  /* Two initializers have the same name */
+  DuplicateInitializer,
+  /* The nodes depend on each other in a cycle */
+  Cycle,
+}
+
+/* Violation found by check_model. node is the name of the node involved, if any */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+  pub rule: Rule,
+  pub node: Option<String>,
+  pub message: String,
+}
+
+impl fmt::Display for Violation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.node {
+      Some(node) => write!(f, "{:?} in node '{}': {}", self.rule, node, self.message),
+      None => write!(f, "{:?}: {}", self.rule, self.message),
+    }
+  }
+}
+
+/*
+This function validates the model against the rules of the ONNX IR, without executing it
+  -It takes 1 parameter:
+    ~ model: the onnx model
+  -It returns all the violations found (not only the first one), so a third party model can be rejected with a complete report
+*/
+pub fn check_model(model: &ModelProto) -> Result<(), Vec<Violation>> {
+  let mut violations = Vec::new();
+
+  check_opset_imports(model, &mut violations);
+
+  let graph = match model.graph.as_ref() {
+    Some(graph) => graph,
+    None => {
+      violations.push(Violation { rule: Rule::MissingGraph, node: None, message: "model without graph".to_string() });
+      return Err(violations);
+    }
+  };
+
+  /* Initializers */
+  let mut defined: HashSet<&str> = HashSet::new();
+  for init in &graph.initializer {
+    let name = init.name.as_deref().unwrap_or_default();
+    if !defined.insert(name) {
+      violations.push(Violation { rule: Rule::DuplicateInitializer, node: None, message: format!("initializer '{}' is defined more than once", name) });
+    }
+  }
+  /* Graph inputs may repeat the initializers (they are the default value of the input) */
+  for inp in &graph.input {
+    defined.insert(inp.name.as_deref().unwrap_or_default());
+  }
+
+  /* The required attributes depend on the opset imported for the standard operators (the latest one if it isn't imported) */
+  let opset_version = model.opset_import.iter()
+    .filter(|opset| normalize_domain(opset.domain.as_deref().unwrap_or_default()) == DEFAULT_DOMAIN)
+    .filter_map(|opset| opset.version)
+    .max()
+    .unwrap_or(i64::MAX);
+
+  /* Node inputs and outputs, in the order of the graph */
+  let producers: HashMap<&str, usize> = graph.node.iter().enumerate()
+    .flat_map(|(position, node)| node.output.iter().map(move |output| (output.as_str(), position)))
+    .collect();
+  for (position, node) in graph.node.iter().enumerate() {
+    for input in &node.input {
+      if input.is_empty() || defined.contains(input.as_str()) {
+        continue;
+      }
+      let message = match producers.get(input.as_str()) {
+        Some(&producer) if producer >= position => format!("input '{}' is produced by node '{}' that comes later in the graph", input, node_name(&graph.node[producer])),
+        _ => format!("input '{}' is not a graph input, an initializer or the output of a node", input),
+      };
+      violations.push(Violation { rule: Rule::UndefinedInput, node: Some(node_name(node)), message });
+    }
+
+    for output in &node.output {
+      if output.is_empty() {
+        continue;
+      }
+      if !defined.insert(output.as_str()) {
+        violations.push(Violation { rule: Rule::DuplicateOutput, node: Some(node_name(node)), message: format!("output '{}' is already defined", output) });
+      }
+    }
+
+    /* The operators of the custom domains are only known by their kernels */
+    let standard = normalize_domain(node.domain.as_deref().unwrap_or_default()) == DEFAULT_DOMAIN;
+    for attribute in required_attributes(node.op_type.as_deref().unwrap_or_default(), opset_version).iter().filter(|_| standard) {
+      if !node.attribute.iter().any(|attr| attr.name.as_deref() == Some(*attribute)) {
+        violations.push(Violation { rule: Rule::MissingAttribute, node: Some(node_name(node)), message: format!("required attribute '{}' of {} is not set", attribute, node.op_type.as_deref().unwrap_or_default()) });
+      }
+    }
+  }
+
+  check_cycles(&graph.node, &mut violations);
+
+  if violations.is_empty() {
+    Ok(())
+  } else {
+    Err(violations)
+  }
+}
+
+/*
+This function checks that the model imports an opset for the default domain and for every domain used by its nodes
+*/
+fn check_opset_imports(model: &ModelProto, violations: &mut Vec<Violation>) {
+  if model.opset_import.is_empty() {
+    violations.push(Violation { rule: Rule::MissingOpsetImport, node: None, message: "the model has no opset import".to_string() });
+    return;
+  }
+
+  let imported: HashSet<&str> = model.opset_import.iter().map(|opset| normalize_domain(opset.domain.as_deref().unwrap_or_default())).collect();
+  if let Some(graph) = model.graph.as_ref() {
+    for node in &graph.node {
+      let domain = normalize_domain(node.domain.as_deref().unwrap_or_default());
+      if !imported.contains(domain) {
+        violations.push(Violation { rule: Rule::MissingOpsetImport, node: Some(node_name(node)), message: format!("domain '{}' is not imported by the model", domain) });
+      }
+    }
+  }
+}
+
+/*
+This function finds the cycles among the nodes (a node depends on the nodes producing its inputs).
+Every node is visited once: the violation lists the nodes of each cycle found.
+*/
+fn check_cycles(nodes: &[NodeProto], violations: &mut Vec<Violation>) {
+  let mut producers: HashMap<&str, usize> = HashMap::new();
+  for (position, node) in nodes.iter().enumerate() {
+    for output in &node.output {
+      if !output.is_empty() {
+        producers.entry(output.as_str()).or_insert(position);
+      }
+    }
+  }
+  let dependencies: Vec<Vec<usize>> = nodes.iter()
+    .map(|node| node.input.iter().filter_map(|input| producers.get(input.as_str()).copied()).collect())
+    .collect();
+
+  /* 0 = not visited, 1 = on the current path, 2 = done */
+  let mut state = vec![0u8; nodes.len()];
+  for start in 0..nodes.len() {
+    if state[start] != 0 {
+      continue;
+    }
+    /* Iterative depth first search: (node, next dependency to visit) */
+    let mut path: Vec<(usize, usize)> = vec![(start, 0)];
+    state[start] = 1;
+    while let Some(&(position, next)) = path.last() {
+      if next < dependencies[position].len() {
+        let dependency = dependencies[position][next];
+        path.last_mut().unwrap().1 += 1;
+        match state[dependency] {
+          0 => {
+            state[dependency] = 1;
+            path.push((dependency, 0));
+          }
+          1 => {
+            let cycle_start = path.iter().position(|&(p, _)| p == dependency).unwrap();
+            let names: Vec<String> = path[cycle_start..].iter().map(|&(p, _)| node_name(&nodes[p])).collect();
+            violations.push(Violation { rule: Rule::Cycle, node: Some(node_name(&nodes[dependency])), message: format!("the nodes {:?} depend on each other", names) });
+          }
+          _ => {}
+        }
+      } else {
+        state[position] = 2;
+        path.pop();
+      }
+    }
+  }
+}
+
+/*
+This function gives the attributes that the ONNX specification marks as required for an operation
+  -It takes 2 parameters:
+    ~ op_type: name of the operation
+    ~ opset_version: opset version of the default domain imported by the model
+  -It returns the names of the required attributes (some are required only in some versions, i.e. before becoming inputs)
+*/
+pub(crate) fn required_attributes(op_type: &str, opset_version: i64) -> &'static [&'static str] {
+  match (op_type, opset_version) {
+    ("MaxPool" | "AveragePool" | "LpPool", _) => &["kernel_shape"],
+    ("Concat", version) if version >= 4 => &["axis"],
+    ("Cast", _) => &["to"],
+    ("GroupNormalization", _) => &["num_groups"],
+    ("Slice", version) if version < 10 => &["starts", "ends"],
+    ("Unsqueeze", version) if version < 13 => &["axes"],
+    ("Pad", version) if version < 11 => &["pads"],
+    _ => &[],
+  }
+}
+
+#[allow(dead_code)]
+pub fn test_check_model() {
+  use crate::onnx_structure::tensor_proto::DataType;
+  use crate::test_graph::{int64_initializer, int_attribute, model, node, value_info};
+
+  let x = || vec![value_info("x", DataType::FLOAT, None)];
+  let report = |name: &str, model: &ModelProto| match check_model(model) {
+    Ok(()) => println!("{}: Ok", name),
+    Err(violations) => {
+      println!("{}: {} violation(s)", name, violations.len());
+      for violation in violations {
+        println!("  {}", violation);
+      }
+    }
+  };
+
+  report("valid", &model(vec![node("Relu", &["x"], &["y"], vec![])], x(), vec![], vec![], 13));
+  println!("expected: Ok");
+
+  report("undefined input", &model(vec![node("Add", &["x", "missing"], &["y"], vec![])], x(), vec![], vec![], 13));
+  println!("expected: UndefinedInput in node 'y' (missing)");
+
+  report("use before definition", &model(vec![node("Relu", &["h"], &["y"], vec![]), node("Relu", &["x"], &["h"], vec![])], x(), vec![], vec![], 13));
+  println!("expected: UndefinedInput in node 'y' (h is produced by a later node)");
+
+  report("duplicate output", &model(vec![node("Relu", &["x"], &["y"], vec![]), node("Neg", &["x"], &["y"], vec![])], x(), vec![], vec![], 13));
+  println!("expected: DuplicateOutput in node 'y'");
+
+  let initializers = vec![int64_initializer("c", &[1], &[1]), int64_initializer("c", &[1], &[2])];
+  report("duplicate initializer", &model(vec![node("Identity", &["c"], &["y"], vec![])], vec![], vec![], initializers, 13));
+  println!("expected: DuplicateInitializer (c)");
+
+  let mut no_opset = model(vec![node("Relu", &["x"], &["y"], vec![])], x(), vec![], vec![], 13);
+  no_opset.opset_import.clear();
+  report("no opset import", &no_opset);
+  let mut custom = node("MyRelu", &["x"], &["y"], vec![]);
+  custom.domain = Some("com.example".to_string());
+  report("custom domain without opset import", &model(vec![custom], x(), vec![], vec![], 13));
+  println!("expected: MissingOpsetImport for both");
+
+  // axis is required by Concat since opset 4
+  let concat = |opset_version: i64| model(vec![node("Concat", &["x", "x"], &["y"], vec![])], x(), vec![], vec![], opset_version);
+  report("concat opset 1", &concat(1));
+  println!("expected: Ok");
+  report("concat opset 4", &concat(4));
+  println!("expected: MissingAttribute in node 'y' (axis)");
+  report("concat opset 4 with axis", &model(vec![node("Concat", &["x", "x"], &["y"], vec![int_attribute("axis", 0)])], x(), vec![], vec![], 4));
+  println!("expected: Ok");
+
+  report("cycle", &model(vec![node("Relu", &["b"], &["a"], vec![]), node("Relu", &["a"], &["b"], vec![])], vec![], vec![], vec![], 13));
+  println!("expected: UndefinedInput in node 'a' (b is produced later) and Cycle");
+
+  // All the violations of a model are collected in one report
+  let mut broken = model(
+    vec![node("Concat", &["x", "missing"], &["y"], vec![]), node("Relu", &["x"], &["y"], vec![])],
+    x(),
+    vec![],
+    vec![int64_initializer("c", &[1], &[1]), int64_initializer("c", &[1], &[2])],
+    13,
+  );
+  broken.opset_import.clear();
+  report("everything", &broken);
+  println!("expected: MissingOpsetImport, DuplicateInitializer, UndefinedInput, MissingAttribute (axis), DuplicateOutput");
+}
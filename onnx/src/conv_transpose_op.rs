@@ -0,0 +1,265 @@
+use ndarray::*;
+
+use crate::convolution_op::{add_bias, Padding};
+
+// Rust implementation of a transposed convolution (a.k.a. deconvolution) layer with any number of spatial axes.
+// The weight matrix (aka kernel) has the ONNX layout (in that order):
+// channels(input channels), feature maps/groups(output channels), kernel d1, ..., kernel dn
+pub struct ConvTransposeLayer {
+  pub(in crate) kernel: ArrayD<f32>,
+  pub(in crate) bias: Option<Array1<f32>>,
+  pub(in crate) auto_pad: Padding,
+  pub(in crate) dilations: Vec<usize>,
+  pub(in crate) group: usize,
+  pub(in crate) output_padding: Vec<usize>,
+  pub(in crate) output_shape: Option<Vec<usize>>,
+  pub(in crate) pads: Vec<usize>,
+  pub(in crate) strides: Vec<usize>,
+}
+
+impl ConvTransposeLayer {
+  // Creates new transposed convolution layer.
+  #[allow(clippy::too_many_arguments)]
+  pub(crate) fn new(
+    kernel: ArrayD<f32>,
+    bias: Option<Array1<f32>>,
+    auto_pad: Padding,
+    dilations: Vec<usize>,
+    group: usize,
+    output_padding: Vec<usize>,
+    output_shape: Option<Vec<usize>>,
+    pads: Vec<usize>,
+    strides: Vec<usize>,
+  ) -> ConvTransposeLayer {
+    ConvTransposeLayer { kernel, bias, auto_pad, dilations, group, output_padding, output_shape, pads, strides }
+  }
+
+  /// Size of the output along a spatial axis and padding removed at its beginning (negative when the output_shape
+  /// asks for more values than the full transposed convolution produces)
+  ///
+  /// The full output is stride * (input - 1) + output_padding + (kernel - 1) * dilation + 1, then:
+  ///  - output_shape: the difference with the full output is the total padding, split as SAME_UPPER
+  ///    (smaller half at the beginning) or otherwise (bigger half at the beginning)
+  ///  - "SAME_UPPER"/"SAME_LOWER": the output is input * stride, the total padding is split the same way
+  ///  - "NOTSET": pads is removed, "VALID": nothing is removed
+  pub fn output_geometry(&self, input: usize, axis: usize) -> Result<(usize, i64), String> {
+    let n = self.kernel.ndim() - 2;
+    let kernel = self.kernel.shape()[2 + axis] as i64;
+    let stride = self.strides[axis] as i64;
+    let full = stride * (input as i64 - 1) + self.output_padding[axis] as i64 + (kernel - 1) * self.dilations[axis] as i64 + 1;
+    let split = |total: i64| if self.auto_pad == Padding::SameUpper { total.div_euclid(2) } else { total - total.div_euclid(2) };
+
+    let (output, begin) = match (&self.output_shape, self.auto_pad) {
+      (Some(shape), _) => (shape[axis] as i64, split(full - shape[axis] as i64)),
+      (None, Padding::SameUpper) | (None, Padding::SameLower) => (input as i64 * stride, split(full - input as i64 * stride)),
+      (None, Padding::Valid) => (full, 0),
+      (None, Padding::NotSet) => (full - (self.pads[axis] + self.pads[axis + n]) as i64, self.pads[axis] as i64),
+    };
+    if output <= 0 {
+      return Err(format!("pads {:?} remove the whole output along axis {}", self.pads, axis + 2));
+    }
+    Ok((output as usize, begin))
+  }
+
+  /// Analog to conv_transpose1d/conv_transpose2d/conv_transpose3d, depending on the rank of the kernel.
+  pub fn conv_transpose(&self, image: &ArrayD<f32>) -> Result<ArrayD<f32>, String> {
+    let channels = self.kernel.shape()[0];
+    if image.ndim() != self.kernel.ndim() || image.shape()[1] != channels || !channels.is_multiple_of(self.group) {
+      return Err(format!("input {:?} and weights {:?} are not compatible with group {}", image.shape(), self.kernel.shape(), self.group));
+    }
+    let mut output_size = Vec::with_capacity(image.ndim() - 2);
+    let mut begin = Vec::with_capacity(image.ndim() - 2);
+    for (axis, &input) in image.shape()[2..].iter().enumerate() {
+      let (size, pad) = self.output_geometry(input, axis)?;
+      output_size.push(size);
+      begin.push(pad);
+    }
+
+    let output = conv_transpose(image.view(), self.kernel.view(), self.group, &self.strides, &self.dilations, &output_size, &begin);
+    Ok(add_bias(&output, self.bias.as_ref()))
+  }
+}
+
+/// OPSET VERSION: 11
+/// Performs a transposed convolution: every input value is multiplied by the whole kernel and the product is
+/// added to the output window that the value would have been computed from in the convolution (scattered
+/// with strides and dilations). The values falling in the padding are discarded.
+///
+/// Input:
+///
+///  - im(batch size, channels, d1, ..., dn): ArrayViewD.
+///  - kernel_weights(C=#Channels, M=#FeatureMaps/group, k1, ..., kn): ArrayViewD.
+///  - group: usize. Number of groups (C/group input channels are mapped to M output channels for each group)
+///  - strides: Moving offset over each output axis.
+///  - dilations: Dilation over kernel a.k.a. w filter
+///  - output_size: (d1', ..., dn') of the output
+///  - begin: padding removed at the beginning of each axis
+///
+/// Returns:
+/// -----------------------------------------------
+/// - out: Output data, of shape (B, M, d1', ..., dn')
+pub fn conv_transpose(
+  im: ArrayViewD<f32>,
+  kernel_weights: ArrayViewD<f32>,
+  group: usize,
+  strides: &[usize],
+  dilations: &[usize],
+  output_size: &[usize],
+  begin: &[i64],
+) -> ArrayD<f32> {
+  let (batch, channels) = (im.shape()[0], im.shape()[1]);
+  let maps = kernel_weights.shape()[1];
+  let channels_per_group = channels / group;
+  let mut output_shape = vec![batch, maps * group];
+  output_shape.extend(output_size);
+  let mut output = ArrayD::zeros(IxDyn(&output_shape));
+
+  /* Output position of the input position i combined with the kernel position k along an axis, None if it falls in the padding */
+  let position = |i: usize, k: usize, axis: usize| {
+    let p = (i * strides[axis] + k * dilations[axis]) as i64 - begin[axis];
+    if p >= 0 && (p as usize) < output_size[axis] { Some(p as usize) } else { None }
+  };
+
+  let mut out_position = vec![0usize; output_size.len()];
+  for b in 0..batch {
+    for c in 0..channels {
+      let g = c / channels_per_group;
+      let image = im.index_axis(Axis(0), b);
+      let image = image.index_axis(Axis(0), c);
+      for m in 0..maps {
+        let kernel = kernel_weights.index_axis(Axis(0), c);
+        let kernel = kernel.index_axis(Axis(0), m);
+        let mut out = output.index_axis_mut(Axis(0), b);
+        let mut out = out.index_axis_mut(Axis(0), g * maps + m);
+        for (i, &value) in image.indexed_iter() {
+          'kernel: for (k, &weight) in kernel.indexed_iter() {
+            for (axis, p) in out_position.iter_mut().enumerate() {
+              match position(i[axis], k[axis], axis) {
+                Some(o) => *p = o,
+                None => continue 'kernel
+              }
+            }
+            out[&out_position[..]] += value * weight;
+          }
+        }
+      }
+    }
+  }
+
+  output
+}
+
+#[allow(dead_code)]
+fn test_conv_transpose_1_channel_in_2_channels_out() {
+  // Input has shape (batch_size, channels, height, width)
+  let input = Array::from_shape_vec(
+    (1, 1, 3, 3),
+    vec![0., 1., 2.,
+         3., 4., 5.,
+         6., 7., 8.]
+  )
+    .unwrap()
+    .into_dyn();
+
+  // Kernel has shape (channels in, channels out, height, width)
+  let kernel = Array::ones((1, 2, 3, 3)).into_dyn();
+
+  let conv_layer = ConvTransposeLayer::new(kernel.clone(), None, Padding::NotSet, vec![1, 1], 1, vec![0, 0], None, vec![0, 0, 0, 0], vec![1, 1]);
+  println!("test_conv_transpose_1_channel_in_2_channels_out: {:?}", conv_layer.conv_transpose(&input));
+  println!("expected (both channels): [[0, 1, 3, 3, 2], [3, 8, 15, 12, 7], [9, 21, 36, 27, 15], [9, 20, 33, 24, 13], [6, 13, 21, 15, 8]]");
+
+  // Strides (3, 2) and pads (1, 2, 1, 2)
+  let conv_layer = ConvTransposeLayer::new(kernel.clone(), None, Padding::NotSet, vec![1, 1], 1, vec![0, 0], None, vec![1, 2, 1, 2], vec![3, 2]);
+  println!("test_conv_transpose pads: {:?}", conv_layer.conv_transpose(&input));
+  println!("expected (both channels): [[1, 1, 3], [1, 1, 3], [7, 4, 9], [7, 4, 9], [7, 4, 9], [13, 7, 15], [13, 7, 15]]");
+
+  // Strides (3, 2) and output_padding (1, 1), the same of output_shape (10, 8)
+  let conv_layer = ConvTransposeLayer::new(kernel.clone(), None, Padding::NotSet, vec![1, 1], 1, vec![1, 1], None, vec![0, 0, 0, 0], vec![3, 2]);
+  println!("test_conv_transpose output_padding: {:?}", conv_layer.conv_transpose(&input));
+  let conv_layer = ConvTransposeLayer::new(kernel, None, Padding::NotSet, vec![1, 1], 1, vec![0, 0], Some(vec![10, 8]), vec![0, 0, 0, 0], vec![3, 2]);
+  println!("test_conv_transpose output_shape: {:?}", conv_layer.conv_transpose(&input));
+  println!("expected (both channels): [[0, 0, 1, 1, 3, 2, 2, 0], [0, 0, 1, 1, 3, 2, 2, 0], [0, 0, 1, 1, 3, 2, 2, 0],
+    [3, 3, 7, 4, 9, 5, 5, 0], [3, 3, 7, 4, 9, 5, 5, 0], [3, 3, 7, 4, 9, 5, 5, 0],
+    [6, 6, 13, 7, 15, 8, 8, 0], [6, 6, 13, 7, 15, 8, 8, 0], [6, 6, 13, 7, 15, 8, 8, 0], [0, 0, 0, 0, 0, 0, 0, 0]]");
+}
+
+#[allow(dead_code)]
+fn test_conv_transpose_dilations() {
+  // Input has shape (batch_size, channels, height, width)
+  let input = Array::from_shape_vec(
+    (1, 1, 3, 3),
+    vec![3., 8., 1.,
+         9., 5., 7.,
+         3., 2., 6.]
+  )
+    .unwrap()
+    .into_dyn();
+
+  // Kernel has shape (channels in, channels out, height, width)
+  let kernel = Array::from_shape_vec(
+    (1, 1, 2, 2),
+    vec![7., 2.,
+         1., 9.]
+  )
+    .unwrap()
+    .into_dyn();
+
+  let conv_layer = ConvTransposeLayer::new(kernel, None, Padding::NotSet, vec![2, 2], 1, vec![0, 0], None, vec![0, 0, 0, 0], vec![1, 1]);
+  println!("test_conv_transpose_dilations: {:?}", conv_layer.conv_transpose(&input));
+  println!("expected: [[21, 56, 13, 16, 2], [63, 35, 67, 10, 14], [24, 22, 76, 76, 21], [9, 5, 88, 45, 63], [3, 2, 33, 18, 54]]");
+}
+
+#[allow(dead_code)]
+fn test_conv_transpose_2_channels_out_2_channels_in() {
+  // Input has shape (batch_size, channels, height, width)
+  let input = Array::from_shape_vec(
+    (1, 2, 2, 2),
+    vec![1., 2.,
+         3., 4.,
+         5., 6.,
+         7., 8.]
+  )
+    .unwrap()
+    .into_dyn();
+
+  // Kernel has shape (channels in, channels out, height, width): with group 2 each channel has its own 1x1 kernel
+  let kernel = Array::from_shape_vec((2, 1, 1, 1), vec![2., 3.]).unwrap().into_dyn();
+  let bias: Array1<f32> = array![1., -1.];
+
+  let conv_layer = ConvTransposeLayer::new(kernel, Some(bias), Padding::NotSet, vec![1, 1], 2, vec![0, 0], None, vec![0, 0, 0, 0], vec![1, 1]);
+  println!("test_conv_transpose_2_channels_out_2_channels_in: {:?}", conv_layer.conv_transpose(&input));
+  println!("expected: [[[[3, 5], [7, 9]], [[14, 17], [20, 23]]]]");
+
+  // Kernel (2, 1, 2, 2) without groups: both input channels are summed in the only output channel
+  let kernel = Array::from_shape_vec((2, 1, 2, 2), vec![1., 0., 0., 1., 0., 1., 1., 0.]).unwrap().into_dyn();
+  let conv_layer = ConvTransposeLayer::new(kernel, None, Padding::SameUpper, vec![1, 1], 1, vec![0, 0], None, vec![0, 0, 0, 0], vec![2, 2]);
+  println!("test_conv_transpose SAME_UPPER: {:?}", conv_layer.conv_transpose(&input));
+  println!("expected: [[[[1, 5, 2, 6], [5, 1, 6, 2], [3, 7, 4, 8], [7, 3, 8, 4]]]]");
+}
+
+#[allow(dead_code)]
+fn test_conv_transpose_1d() {
+  // Input has shape (batch_size, channels, length), kernel (channels in, channels out, length)
+  let input = Array::from_shape_vec((1, 1, 3), vec![1., 2., 3.]).unwrap().into_dyn();
+  let kernel = Array::from_shape_vec((1, 1, 2), vec![1., 1.]).unwrap().into_dyn();
+
+  let conv_layer = ConvTransposeLayer::new(kernel.clone(), None, Padding::NotSet, vec![1], 1, vec![0], None, vec![0, 0], vec![1]);
+  println!("test_conv_transpose_1d: {:?}", conv_layer.conv_transpose(&input));
+  println!("expected: [[[1, 3, 5, 3]]]");
+
+  // Stride 2: the windows don't overlap
+  let conv_layer = ConvTransposeLayer::new(kernel, None, Padding::NotSet, vec![1], 1, vec![0], None, vec![0, 0], vec![2]);
+  println!("test_conv_transpose_1d strides: {:?}", conv_layer.conv_transpose(&input));
+  println!("expected: [[[1, 1, 2, 2, 3, 3]]]");
+}
+
+#[allow(dead_code)]
+pub fn test_conv_transpose() {
+  test_conv_transpose_1_channel_in_2_channels_out();
+  println!("\n\n");
+  test_conv_transpose_dilations();
+  println!("\n\n");
+  test_conv_transpose_2_channels_out_2_channels_in();
+  println!("\n\n");
+  test_conv_transpose_1d();
+}
@@ -1,8 +1,6 @@
 use ndarray::*;
 use num_traits::Float;
 
-pub type DataRepresentation<F> = Array4<F>;
-
 // Padding (specific way of adding zeros to the input matrix) kind used in the convolution.
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum Padding {
@@ -16,26 +14,26 @@ pub enum Padding {
   Valid,
 }
 
-// Rust implementation of a convolutional layer.
-// The weight matrix (aka kernel) shall have dimension (in that order)
-// channels/groups(input channels), feature maps(output channels), kernel width, kernel height,
+// Rust implementation of a convolutional layer with any number of spatial axes (1-D, 2-D, 3-D, ...).
+// The weight matrix (aka kernel) has the ONNX layout (in that order):
+// feature maps(output channels), channels/groups(input channels), kernel d1, ..., kernel dn
 pub struct ConvolutionLayer<F: Float> {
-  pub(in crate) kernel: Array4<F>,
+  pub(in crate) kernel: ArrayD<F>,
   pub(in crate) bias: Option<Array1<F>>,
   pub(in crate) auto_pad: Padding,
-  pub(in crate) dilations: Option<Array2<i32>>,
+  pub(in crate) dilations: Option<Array1<i32>>,
   pub(in crate) group: Option<i32>,
   pub(in crate) pads: Array1<F>,
   pub(in crate) strides: Array1<F>,
 }
 
-impl<F: 'static + Float + std::ops::AddAssign + std::default::Default + std::convert::From<F> + std::ops::AddAssign<f32>> ConvolutionLayer<F> where f32: From<F> {
+impl<F: 'static + Float + std::ops::AddAssign> ConvolutionLayer<F> where f32: From<F> {
   // Creates new convolution layer.
   pub(crate) fn new(
-    kernel: Array4<F>,
+    kernel: ArrayD<F>,
     bias: Option<Array1<F>>,
     auto_pad: Padding,
-    dilations: Option<Array2<i32>>,
+    dilations: Option<Array1<i32>>,
     group: Option<i32>,
     pads: Array1<F>,
     strides: Array1<F>,
@@ -43,31 +41,11 @@ impl<F: 'static + Float + std::ops::AddAssign + std::default::Default + std::con
     ConvolutionLayer { kernel, bias, auto_pad, dilations, group, pads, strides }
   }
 
-  /// Creates new convolution layer. The weights are given in ONNX Tensorflow layout:
-  /// feature maps(output channels), channels/groups(input channels), kernel height, kernel width
-  /// converted into:
-  /// channels/groups(input channels), feature maps(output channels), kernel width, kernel height,
-  pub fn new_onnx_tensor_flow(
-    kernel: Array4<F>,
-    bias: Option<Array1<F>>,
-    auto_pad: Padding,
-    dilations: Option<Array2<i32>>,
-    group: Option<i32>,
-    pads: Array1<F>,
-    strides: Array1<F>,
-  ) -> ConvolutionLayer<F> {
-    let permuted_view = kernel.view().permuted_axes([1, 0, 3, 2]);
-    // Hack to fix the memory layout, permuted axes makes a
-    // col major array / non-contiguous array from kernel
-    let permuted_array: Array4<F> = Array::from_shape_vec(permuted_view.dim(), permuted_view.iter().copied().collect()).unwrap();
-    ConvolutionLayer::new(permuted_array, bias, auto_pad, dilations, group, pads, strides)
-  }
-
-  /// Analog to conv2d.
-  pub fn convolve(&self, image: &DataRepresentation<F>) -> DataRepresentation<F> {
-    conv2d(
-      &self.kernel,
-      image,
+  /// Analog to conv1d/conv2d/conv3d, depending on the rank of the kernel.
+  pub fn convolve(&self, image: &ArrayD<F>) -> ArrayD<F> {
+    conv(
+      image.view(),
+      self.kernel.view(),
       self.bias.as_ref(),
       self.auto_pad,
       self.dilations.as_ref(),
@@ -76,538 +54,196 @@ impl<F: 'static + Float + std::ops::AddAssign + std::default::Default + std::con
       &self.strides,
     )
   }
+
+  /// Spatial dimensions of the output for an image with the given spatial dimensions,
+  /// None if the kernel (with its dilations) is bigger than the padded image
+  pub fn output_size(&self, input: &[usize]) -> Option<Vec<usize>> {
+    let kernel = &self.kernel.shape()[2..];
+    conv_geometry(input, kernel, self.auto_pad, &dilations_or_ones(self.dilations.as_ref(), kernel.len()), &self.pads, &self.strides)
+      .map(|(output, _, _)| output)
+  }
 }
 
-/// OPSET VERSION: 8
+/// OPSET VERSION: 11
 /// Performs a convolution on the given image data using this layers parameters.
-/// We always convolve on flattened images and expect the input array in im2col
-/// style format.
+/// The (padded) images are unrolled in im2col style: a column for each output position holding the
+/// window of every input channel of a group, so that the convolution of a group is the matrix product
+/// of its kernels (one per row) and of the columns.
 ///
 /// Read more here:
 /// - <https://leonardoaraujosantos.gitbook.io/artificial-inteligence/machine_learning/deep_learning/convolution_layer/making_faster>
 ///
 /// Input:
 ///
-///  - im2d(batch size, channels, height, width): Array4.
-///  - kernel_weights(F=#Filters(channels/groups), C=#ChannelsOut(feature maps), width, height): Array4. (Feature Maps->#output volume)
+///  - im(batch size, channels, d1, ..., dn): ArrayViewD.
+///  - kernel_weights(M=#FeatureMaps, C=#Channels/group, k1, ..., kn): ArrayViewD. (Feature Maps->#output volume)
 ///  - bias: Array1. (Bias, is added to each channel (after having adding each Hadamard Product))
 ///  - auto_pad: ["NOTSET"->pads has meant not to be None (manually specified padding),
-///               "SAME_UPPER"->padding equally split between axis(if odd number, extra padding added to bottom),
-///               "SAME_LOWER"->padding equally split between axis(if odd number, extra padding added to top,
-///               "VALID"->no padding
-///               ]
-///  - dilations: Array1. (Dilation over kernel a.k.a. w filter)
+///    "SAME_UPPER"->padding equally split between axis(if odd number, extra padding added at the end),
+///    "SAME_LOWER"->padding equally split between axis(if odd number, extra padding added at the beginning),
+///    "VALID"->no padding
+///    ]
+///  - dilations: Array1. (Dilation over kernel a.k.a. w filter, one for each spatial axis)
 ///  - group: i32. Number of groups
-///  - kernel_shape: Array1. If not None means the shape of kernel a.k.a. w filter. Since it's not required from the standard, it's inferred from kernel_weights
-///  - pads: Array1. Manual padding specified accordingly to auto_pad
+///  - pads: Array1. Manual padding specified accordingly to auto_pad (the beginning of each axis, then the end of each axis)
 ///  - strides: Array1. Moving offset over each x input axis.
 /// Returns:
 /// -----------------------------------------------
-/// - out: Output data, of shape (B, F, H', W')
-#[allow(unused_assignments)]
-#[allow(unused_variables)]
-pub fn conv2d<'a, T, V, F: 'static + Float + std::ops::AddAssign + std::default::Default + std::ops::AddAssign<f32>>(
-  kernel_weights: T,
-  im2d: T,
+/// - out: Output data, of shape (B, M, d1', ..., dn')
+#[allow(clippy::too_many_arguments)]
+pub fn conv<F: 'static + Float + std::ops::AddAssign>(
+  im: ArrayViewD<F>,
+  kernel_weights: ArrayViewD<F>,
   bias: Option<&Array1<F>>,
   auto_pad: Padding,
-  dilations: Option<&Array2<i32>>,
+  dilations: Option<&Array1<i32>>,
   group: Option<i32>,
-  pads: V, //Option<&Array1<F>>
-  strides: V, //Option<&Array1<F>>
-) -> DataRepresentation<F>
-  where
-  // This trait bound ensures that kernel and im2d can be passed as owned array or view.
-  // AsArray just ensures that im2d can be converted to an array view via ".into()".
-  // Read more here: https://docs.rs/ndarray/0.12.1/ndarray/trait.AsArray.html
-    T: AsArray<'a, F, Ix4>,
-    V: AsArray<'a, F, Ix1>, f32: From<F>
-{
-  // Initialisations
-  let im2d_arr: ArrayView4<F> = im2d.into();
-  let kernel_weights_arr: ArrayView4<F> = kernel_weights.into();
-  let strides_arr: ArrayView1<F> = strides.into();
-  let pads_arr: ArrayView1<F> = pads.into();
-  let im_col: Array2<F>; // output of fn: im2col_ref()
-  let ker_col: Array2<F>;
-  let new_im_height: usize;
-  let new_im_width: usize;
-  let weight_shape = kernel_weights_arr.shape();
-
-  assert!(im2d_arr.shape()[1] == (weight_shape[0] * group.unwrap() as usize) && weight_shape[1] % group.unwrap() as usize == 0);
-
-  let mut num_filters = weight_shape[0];
-  match group {
-    Some(g) => num_filters = num_filters / g as usize,
-    None => {}
-  }
-  let num_channels_out = weight_shape[1];
-  let kernel_height = weight_shape[3];
-  let kernel_width = weight_shape[2];
-  let mut pads_height_start: usize = 0;
-  let mut pads_height_end: usize = 0;
-  let mut pads_width_start: usize = 0;
-  let mut pads_width_end: usize = 0;
-  if auto_pad == Padding::NotSet {
-    let pads_height_start_as_f = *pads_arr.get(0).unwrap();
-    let pads_height_start_as_f32: f32 = pads_height_start_as_f.into();
-    pads_height_start = pads_height_start_as_f32 as usize;
-    let pads_height_end_as_f = *pads_arr.get(2).unwrap();
-    let pads_height_end_as_f32: f32 = pads_height_end_as_f.into();
-    pads_height_end = pads_height_end_as_f32 as usize;
-    let pads_width_start_as_f = *pads_arr.get(1).unwrap();
-    let pads_width_start_as_f32: f32 = pads_width_start_as_f.into();
-    pads_width_start = pads_width_start_as_f32 as usize;
-    let pads_width_end_as_f = *pads_arr.get(3).unwrap();
-    let pads_width_end_as_f32: f32 = pads_width_end_as_f.into();
-    pads_width_end = pads_width_end_as_f32 as usize;
-  }
-
-  let im_batch_size = im2d_arr.len_of(Axis(0));
-  let im_channel = im2d_arr.len_of(Axis(1));
-  let im_height = im2d_arr.len_of(Axis(2));
-  let im_width = im2d_arr.len_of(Axis(3));
-  let im_height_stride_as_f = *strides_arr.get(0).unwrap();
-  let im_height_stride_as_f32: f32 = im_height_stride_as_f.into();
-  let im_height_stride = im_height_stride_as_f32 as usize;
-  let im_width_stride_as_f = *strides_arr.get(1).unwrap();
-  let im_width_stride_as_f32: f32 = im_width_stride_as_f.into();
-  let im_width_stride = im_width_stride_as_f32 as usize;
-
-  // Calculate output shapes H', W' for two types of Padding
-  match auto_pad {
-    Padding::SameLower => {
-      // H' = (H / stride).ceil()
-      // W' = (W / stride).ceil()
-      let new_im_height_float = (im_height as f32 / im_height_stride as f32).ceil();
-      let new_im_width_float = (im_width as f32 / im_width_stride as f32).ceil();
-
-      new_im_height = new_im_height_float as usize;
-      new_im_width = new_im_width_float as usize;
-    }
-    Padding::SameUpper => {
-      // H' = (H / stride).ceil()
-      // W' = (W / stride).ceil()
-      let new_im_height_float = (im_height as f32 / im_height_stride as f32).ceil();
-      let new_im_width_float = (im_width as f32 / im_width_stride as f32).ceil();
-
-      new_im_height = new_im_height_float as usize;
-      new_im_width = new_im_width_float as usize;
-    }
-    Padding::NotSet => {
-      // H' = {[H - HH + (2*padding)] / stride}+ 1
-      // W' = {[W - WW + (2*padding)] / stride} + 1
-      new_im_height = ((im_height - kernel_height + (pads_height_start + pads_height_end)) / im_height_stride) + 1;
-      new_im_width = ((im_width - kernel_width + (pads_width_start + pads_width_end)) / im_width_stride) + 1;
-    }
-    Padding::Valid => {
-      // H' =  ((H - HH) / stride_height) + 1
-      // W' =  ((W - WW) / stride_width) + 1
-      new_im_height = ((im_height - kernel_height) / im_height_stride) + 1;
-      new_im_width = ((im_width - kernel_width) / im_width_stride) + 1;
-    }
-  };
-
-  ker_col = ker2col_ref(
-    kernel_weights_arr,
-    kernel_height,
-    kernel_width,
-    num_channels_out,
-    num_filters
-  );
-
-  if auto_pad != Padding::Valid {
-    let mut pad_num_h = 0;
-    let mut pad_num_w = 0;
-    let mut pad_top = 0;
-    let mut pad_bottom = 0;
-    let mut pad_left = 0;
-    let mut pad_right = 0;
-    if auto_pad == Padding::SameUpper || auto_pad == Padding::SameLower {
-      (pad_num_h, pad_num_w, pad_top, pad_bottom, pad_left, pad_right) = get_padding_size(im_height, im_width, im_height_stride, im_width_stride, kernel_height, kernel_width);
-    } else if auto_pad == Padding::NotSet {
-      pad_top = pads_height_start;
-      pad_bottom = pads_height_end;
-      pad_left = pads_width_start;
-      pad_right = pads_width_end;
-      pad_num_h = pads_height_start + pads_height_end;
-      pad_num_w = pads_width_start + pads_width_end;
-    }
-    let mut im2d_arr_pad: Array4<F> = Array4::zeros((
-      im_batch_size,
-      im_channel,
-      im_height + pad_num_h,
-      im_width + pad_num_w,
-    ));
-    let pad_bottom_int = (im_height + pad_num_h) - pad_bottom;
-    let pad_right_int = (im_width + pad_num_w) - pad_right;
-    // https://github.com/rust-ndarray/ndarray/issues/823
-    im2d_arr_pad
-      .slice_mut(s![.., .., pad_top..pad_bottom_int, pad_left..pad_right_int])
-      .assign(&im2d_arr);
-
-    let im_height_pad = im2d_arr_pad.len_of(Axis(2));
-    let im_width_pad = im2d_arr_pad.len_of(Axis(3));
-
-    im_col = im2col_ref(
-      im2d_arr_pad.view(),
-      kernel_height,
-      kernel_width,
-      im_height_pad,
-      im_width_pad,
-      im_channel,
-      im_height_stride,
-      im_width_stride,
-      dilations,
-    );
-  } else {
-    im_col = im2col_ref(
-      im2d_arr,
-      kernel_height,
-      kernel_width,
-      im_height,
-      im_width,
-      im_channel,
-      im_height_stride,
-      im_width_stride,
-      dilations,
-    );
+  pads: &Array1<F>,
+  strides: &Array1<F>,
+) -> ArrayD<F> where f32: From<F> {
+  let kernel = &kernel_weights.shape()[2..];
+  let n = kernel.len();
+  let dilations = dilations_or_ones(dilations, n);
+  let strides_arr: Vec<usize> = strides.iter().map(|&s| to_usize(s)).collect();
+  let (output_size, pads_begin, pads_end) = conv_geometry(&im.shape()[2..], kernel, auto_pad, &dilations, pads, strides).unwrap();
+
+  let group = group.unwrap_or(1) as usize;
+  let (im_batch_size, im_channel) = (im.shape()[0], im.shape()[1]);
+  let num_channels_out = kernel_weights.shape()[0];
+  let channels_per_group = im_channel / group;
+  let maps_per_group = num_channels_out / group;
+  assert!(kernel_weights.shape()[1] == channels_per_group && num_channels_out.is_multiple_of(group));
+
+  // Padded copy of the images
+  let mut padded_shape = im.shape().to_vec();
+  for axis in 0..n {
+    padded_shape[axis + 2] += pads_begin[axis] + pads_end[axis];
   }
-
-  /*
-  println!("ker col:");
-  for row in ker_col.rows() {
-    for &elem in row.iter() {
-      print!("{:?}, ", f32::from(elem));
-    }
-    println!("");
-  }*/
-
-  let mut output = Array4::zeros((im_batch_size, num_channels_out, new_im_height, new_im_width));
-
-  let mut image_height = 0usize;
-  let mut image_width = 0usize;
-  let mut displacement = 0;
-  //println!("num_channels_out: {}, num_channels_in: {}, image_as_row[{}, {}], kernel_as_row: [{}, {}]", num_channels_out, num_filters, im_col.len_of(Axis(0)), im_col.len_of(Axis(1)), ker_col.len_of(Axis(0)), ker_col.len_of(Axis(1)));
-  for num_channel_output in 0..num_channels_out{
-    image_height = 0usize;
-    image_width = 0usize;
-    let image_start = 0usize;
-    let mut image_end = im_col.len_of(Axis(0));
-    let kernel_start = num_channel_output;
-    let kernel_end= num_channel_output + 1;
-    let image_idx_start = 0usize;
-    let mut image_idx_end = 1usize;
-    if im_channel > 1{
-      image_end = 1;
-      image_idx_end = (im_col.len_of(Axis(0))/im_channel)+image_idx_start;
-    }
-    //println!("image_start: {}, image_end: {}, kernel_start: {}, kernel_end: {}, image_idx_start: {}, image_idx_end: {}", image_start, image_end, kernel_start, kernel_end, image_idx_start, image_idx_end);
-
-    for aus_img_idx in image_idx_start..image_idx_end{
-      for channel_in in 0..im_channel {
-        for row in image_start..image_end {
-          for n_filter_input in kernel_start..kernel_end {
-            let mut im_col_idx = row;
-            let mut im_ker_idx = n_filter_input;
-            if im_channel > 1 {
-              im_col_idx = ((channel_in * im_col.len_of(Axis(0))) / im_channel)+aus_img_idx;
-              im_ker_idx = channel_in + (im_channel * num_channel_output);
-              if im_col_idx >= im_col.len_of(Axis(0)){
-                im_col_idx = im_col_idx - im_col.len_of(Axis(0));
-              }
-              //println!("im_col_idx:{}", im_col_idx);
-            }
-
-            let im_row = im_col.slice(s![im_col_idx, ..]);
-            let ker_row = ker_col.slice(s![im_ker_idx, ..]);
-
-            /*
-            if num_channel_output == 1 && image_height == 0 && image_width == 0 {
-              println!("image row:");
-              for row in im_row.rows() {
-                for &elem in row.iter() {
-                  print!("{:?}, ", f32::from(elem));
-                }
-                println!("");
-              }
-              println!("ker row:");
-              for row in ker_row.rows() {
-                for &elem in row.iter() {
-                  print!("{:?}, ", f32::from(elem));
-                }
-                println!("");
-              }
-            }
-            */
-
-            assert_eq!(im_row.len_of(Axis(0)), ker_row.len_of(Axis(0)));
-
-            let mut row_mul = Array1::zeros(im_row.len());
-            for idx in 0..im_row.len() {
-              row_mul[[idx]] = f32::from(im_row[[idx]]) * f32::from(ker_row[[idx]]);
-            }
-
-            /*
-            if num_channel_output == 1 && image_height == 0 && image_width == 0 {
-              println!("row mul:");
-              for row in row_mul.rows() {
-                for &elem in row.iter() {
-                  print!("{:?}, ", elem);
-                }
-                println!("");
-              }
-
-              println!("output[{},{},{},{}] = {}", 0, num_channel_output, image_height, image_width, row_mul.sum());
-            }
-            */
-
-            output[[0, num_channel_output, image_height, image_width]] += row_mul.sum();
-
-          }
-          if im_channel <= 1 {
-            if image_width + 1 < new_im_width {
-              image_width += 1;
-            } else {
-              image_height += 1;
-              image_width = 0;
-            }
+  let mut im_pad = ArrayD::<F>::zeros(IxDyn(&padded_shape));
+  im_pad
+    .slice_each_axis_mut(|ax| match ax.axis.index() {
+      a if a < 2 => Slice::from(..),
+      a => Slice::from(pads_begin[a - 2]..pads_begin[a - 2] + im.shape()[a]),
+    })
+    .assign(&im);
+  let im_strides: Vec<usize> = im_pad.strides().iter().map(|&s| s as usize).collect();
+  let im_data = im_pad.as_slice().unwrap();
+
+  // Offset of each element of a window from its first element, and of the first element of each window
+  let kernel_offsets: Vec<usize> = indices(kernel).into_iter()
+    .map(|k| (0..n).map(|axis| k[axis] * dilations[axis] * im_strides[axis + 2]).sum())
+    .collect();
+  let output_offsets: Vec<usize> = indices(&output_size[..]).into_iter()
+    .map(|o| (0..n).map(|axis| o[axis] * strides_arr[axis] * im_strides[axis + 2]).sum())
+    .collect();
+
+  // Kernels of each group, one for each row
+  let window = kernel_offsets.len();
+  let ker_col = kernel_weights.as_standard_layout().into_owned().into_shape((num_channels_out, channels_per_group * window)).unwrap();
+
+  let mut output = Array3::<F>::zeros((im_batch_size, num_channels_out, output_offsets.len()));
+  for b in 0..im_batch_size {
+    for g in 0..group {
+      let mut im_col = Array2::<F>::zeros((channels_per_group * window, output_offsets.len()));
+      for channel in 0..channels_per_group {
+        let channel_start = b * im_strides[0] + (g * channels_per_group + channel) * im_strides[1];
+        for (k, kernel_offset) in kernel_offsets.iter().enumerate() {
+          let mut row = im_col.row_mut(channel * window + k);
+          for (value, output_offset) in row.iter_mut().zip(&output_offsets) {
+            *value = im_data[channel_start + kernel_offset + output_offset];
           }
         }
       }
-      if im_channel > 1 {
-        if image_width + 1 < new_im_width {
-          image_width += 1;
-        } else {
-          image_height += 1;
-          image_width = 0;
-        }
-      }
+      let maps = s![g * maps_per_group..(g + 1) * maps_per_group, ..];
+      output.slice_mut(s![b, g * maps_per_group..(g + 1) * maps_per_group, ..]).assign(&ker_col.slice(maps).dot(&im_col));
     }
-
-    displacement+=1;
   }
 
-  /*
-  println!("output:");
-  for row in output.rows() {
-    for &elem in row.iter() {
-      print!("{:?}, ", f32::from(elem));
-    }
-    println!("");
-  }
-  */
+  let mut output_shape = vec![im_batch_size, num_channels_out];
+  output_shape.extend(&output_size);
+  add_bias(&output.into_shape(IxDyn(&output_shape)).unwrap(), bias)
+}
 
-  add_bias(&output, bias)
+/// Output size, padding at the beginning and padding at the end of each spatial axis of a convolution.
+/// None if the kernel (with its dilations) is bigger than the padded image
+fn conv_geometry<F: Float>(
+  input: &[usize],
+  kernel: &[usize],
+  auto_pad: Padding,
+  dilations: &[usize],
+  pads: &Array1<F>,
+  strides: &Array1<F>,
+) -> Option<(Vec<usize>, Vec<usize>, Vec<usize>)> where f32: From<F> {
+  let n = input.len();
+  let mut output = Vec::with_capacity(n);
+  let mut pads_begin = Vec::with_capacity(n);
+  let mut pads_end = Vec::with_capacity(n);
+  for axis in 0..n {
+    let stride = to_usize(strides[axis]);
+    // Size of the kernel, dilations included
+    let effective_kernel = (kernel[axis] - 1) * dilations[axis] + 1;
+    let (begin, end) = match auto_pad {
+      Padding::NotSet if pads.len() == 2 * n => (to_usize(pads[axis]), to_usize(pads[axis + n])),
+      Padding::NotSet | Padding::Valid => (0, 0),
+      Padding::SameUpper => {
+        let (_, small, big) = get_padding_size(input[axis], stride, effective_kernel);
+        (small, big)
+      }
+      Padding::SameLower => {
+        let (_, small, big) = get_padding_size(input[axis], stride, effective_kernel);
+        (big, small)
+      }
+    };
+    // D' = {[D - K + padding] / stride} + 1
+    let span = (input[axis] + begin + end).checked_sub(effective_kernel)?;
+    output.push(span / stride + 1);
+    pads_begin.push(begin);
+    pads_end.push(end);
+  }
+  Some((output, pads_begin, pads_end))
 }
 
-pub(in crate) fn get_padding_size(
-  input_h: usize,
-  input_w: usize,
-  stride_h: usize,
-  stride_w: usize,
-  kernel_h: usize,
-  kernel_w: usize,
-) -> (usize, usize, usize, usize, usize, usize) {
-  let pad_along_height: usize;
-  let pad_along_width: usize;
-  let idx_0: usize = 0;
-
-  if input_h % stride_h == idx_0 {
-    pad_along_height = (kernel_h - stride_h).max(idx_0);
-  } else {
-    pad_along_height = (kernel_h - (input_h % stride_h)).max(idx_0);
-  };
-  if input_w % stride_w == idx_0 {
-    pad_along_width = (kernel_w - stride_w).max(idx_0);
+/// Padding added along an axis by SAME_UPPER and SAME_LOWER, so that the output has ceil(input / stride) elements.
+/// It returns the total padding and its smaller and bigger halves (SAME_UPPER adds the bigger one at the end,
+/// SAME_LOWER at the beginning)
+pub(in crate) fn get_padding_size(input: usize, stride: usize, kernel: usize) -> (usize, usize, usize) {
+  let pad_along = if input.is_multiple_of(stride) {
+    kernel.saturating_sub(stride)
   } else {
-    pad_along_width = (kernel_w - (input_w % stride_w)).max(idx_0);
+    kernel.saturating_sub(input % stride)
   };
 
-  let pad_top = pad_along_height / 2;
-  let pad_bottom = pad_along_height - pad_top;
-  let pad_left = pad_along_width / 2;
-  let pad_right = pad_along_width - pad_left;
-
-  // yes top/bottom and right/left are swapped. No, I don't know
-  // why this change makes it conform to the pytorch implementation.
-  (
-    pad_along_height,
-    pad_along_width,
-    pad_bottom,
-    pad_top,
-    pad_right,
-    pad_left,
-  )
+  let pad_small = pad_along / 2;
+  let pad_big = pad_along - pad_small;
+  (pad_along, pad_small, pad_big)
 }
 
-#[allow(unused_assignments)]
-pub(in crate) fn im2col_ref<'a, T, F: 'a + Float + std::default::Default>(
-  im_arr: T,
-  ker_height: usize,
-  ker_width: usize,
-  im_height: usize,
-  im_width: usize,
-  im_channel: usize,
-  stride_h: usize,
-  stride_w: usize,
-  dilations: Option<&Array2<i32>>,
-) -> Array2<F>
-  where
-  // Args:
-  //   im_arr: image matrix to be translated into columns, (C,H,W)
-  //   ker_height: filter height (hh)
-  //   ker_width: filter width (ww)
-  //   im_height: image height
-  //   im_width: image width
-  //
-  // Returns:
-  //   col: (new_h*new_w,hh*ww*C) matrix, each column is a cube that will convolve with a filter
-  //         new_h = (H-hh) // stride + 1, new_w = (W-ww) // stride + 1
-    T: AsArray<'a, F, Ix4>
-{
-  let mut cols_img: Array2<F> = Default::default();
-  let mut cont = 0_usize;
+fn dilations_or_ones(dilations: Option<&Array1<i32>>, n: usize) -> Vec<usize> {
   match dilations {
-    Some(dilations) => {
-      let dilation_h = dilations[[0, 0]] as usize;
-      let dilation_w = dilations[[0, 1]] as usize;
-      if dilation_h > 1 || dilation_w > 1 {
-        let im2d_arr: ArrayView4<F> = im_arr.into();
-        let new_h = ((im_height - dilation_h * (ker_height - 1) - 1) / stride_h) + 1;
-        let new_w = ((im_width - dilation_w * (ker_width - 1) - 1) / stride_w) + 1;
-        cols_img = Array2::zeros((new_h * new_w, im_channel * ker_height * ker_width));
-        for i in 1..new_h + 1 {
-          for j in 1..new_w + 1 {
-            let h_start = (i - 1) * stride_h;
-            let h_end = ((((i - 1) * stride_h + ker_height) - (i - 1) * stride_h) * dilation_h) + (i - 1) * stride_h;
-            let w_start = (j - 1) * stride_w;
-            let w_end = ((((j - 1) * stride_w + ker_width) - (j - 1) * stride_w) * dilation_w) + (j - 1) * stride_w;
-            let patch = im2d_arr.slice(s![
-                  ..,
-                  ..,
-                  h_start..h_end; dilation_h,
-                  w_start..w_end; dilation_w
-              ]);
-            let patchrow_unwrap: Array1<F> = Array::from_iter(patch.map(|a| *a));
-
-            cols_img.row_mut(cont).assign(&patchrow_unwrap);
-            cont += 1;
-          }
-        }
-      } else {
-        let im2d_arr: ArrayView4<F> = im_arr.into();
-        let new_h = ((im_height - ker_height) / stride_h) + 1;
-        let new_w = ((im_width - ker_width) / stride_w) + 1;
-        cols_img = Array2::zeros((new_h * new_w * im_channel, ker_height * ker_width));
-
-        for k in 0..im_channel {
-          for i in 1..new_h + 1 {
-            for j in 1..new_w + 1 {
-              let patch = im2d_arr.slice(s![
-                  0,
-                  k,
-                  (i - 1) * stride_h..((i - 1) * stride_h + ker_height),
-                  (j - 1) * stride_w..((j - 1) * stride_w + ker_width),
-              ]);
-              let patchrow_unwrap: Array1<F> = Array::from_iter(patch.map(|a| *a));
-
-              cols_img.row_mut(cont).assign(&patchrow_unwrap);
-              cont += 1;
-            }
-          }
-        }
-      }
-    }
-    None => {
-      let im2d_arr: ArrayView4<F> = im_arr.into();
-      let new_h = ((im_height - ker_height) / stride_h) + 1;
-      let new_w = ((im_width - ker_width) / stride_w) + 1;
-      cols_img = Array2::zeros((new_h * new_w * im_channel, ker_height * ker_width));
-
-      for k in 0..im_channel {
-        for i in 1..new_h + 1 {
-          for j in 1..new_w + 1 {
-            let patch = im2d_arr.slice(s![
-                  0,
-                  k,
-                  (i - 1) * stride_h..((i - 1) * stride_h + ker_height),
-                  (j - 1) * stride_w..((j - 1) * stride_w + ker_width),
-              ]);
-            let patchrow_unwrap: Array1<F> = Array::from_iter(patch.map(|a| *a));
-
-            cols_img.row_mut(cont).assign(&patchrow_unwrap);
-            cont += 1;
-          }
-        }
-      }
-    }
-  };
-
-  cols_img
-}
-
-#[allow(unused_assignments)]
-pub(in crate) fn ker2col_ref<'a, T, F: 'a + Float + std::default::Default>(
-  im_arr: T,
-  ker_height: usize,
-  ker_width: usize,
-  num_channels: usize,
-  num_filters: usize) -> Array2<F> where T: AsArray<'a, F, Ix4>, f32: From<F>
-{
-  let mut cols_img: Array2<F> = Default::default();
-  let im2d_arr: ArrayView4<F> = im_arr.into();
-  cols_img = Array2::zeros((num_channels * num_filters, ker_height * ker_width));
-  let mut cont = 0usize;
-
-  for k in 0..num_channels {
-    for w in 0..num_filters{
-      let mut channel_kernel = Array1::zeros(ker_height * ker_width);
-      let mut kernel_arr: Vec<F> = vec![];
-      for i in 1..ker_height + 1 {
-        for j in 1..ker_width + 1 {
-          let patch = im2d_arr.slice(s![
-                w,
-                k,
-                j - 1,
-                i - 1
-            ]);
-          for p in patch.iter() {
-            kernel_arr.push(*p);
-          }
-        }
-      }
-      let patchrow_unwrap = Array::from_shape_vec(ker_height * ker_width, kernel_arr).unwrap();
-      channel_kernel.assign(&patchrow_unwrap);
-      cols_img.row_mut(cont).assign(&channel_kernel);
-      cont += 1;
-    }
+    Some(d) => d.iter().map(|&d| d as usize).collect(),
+    None => vec![1; n]
   }
+}
 
-  cols_img
+fn to_usize<F: Float>(value: F) -> usize where f32: From<F> {
+  f32::from(value) as usize
 }
 
-pub(in crate) fn add_bias<F>(x: &Array4<F>, bias: Option<&Array1<F>>) -> Array4<F>
+/// Adds to each channel (axis 1) of x its bias
+pub(in crate) fn add_bias<F>(x: &ArrayD<F>, bias: Option<&Array1<F>>) -> ArrayD<F>
   where
     F: 'static + Float + std::ops::AddAssign,
 {
+  let mut output = x.clone();
   if let Some(bias_array) = bias {
     assert_eq!(bias_array.shape()[0], x.shape()[1], "Bias array has the wrong shape {:?} for vec of shape {:?}", bias_array.shape(), x.shape());
-    // Yes this is really necessary. Broadcasting with ndarray-rust
-    // starts at the right side of the shape, so we have to add
-    // the axes by hand (else it thinks that it should compare the
-    // output width and the bias channels).
-    (x + &bias_array
-      .clone()
-      .insert_axis(Axis(1))
-      .insert_axis(Axis(2))
-      .broadcast(x.shape())
-      .unwrap())
-      .into_dimensionality()
-      .unwrap()
-  } else {
-    x.clone()
+    for mut image in output.axis_iter_mut(Axis(0)) {
+      for (mut channel, &b) in image.axis_iter_mut(Axis(0)).zip(bias_array) {
+        channel.mapv_inplace(|v| v + b);
+      }
+    }
   }
+  output
 }
 
 #[allow(dead_code)]
@@ -623,7 +259,7 @@ fn test_convolution_1_channels_out_1_channels_in() {
   )
     .unwrap();
 
-  // Kernel has shape (channels in, channels out, height, width)
+  // Kernel has shape (channels out, channels in, height, width)
   let kernel: Array4<f32> = Array::from_shape_vec(
     (1, 1, 5, 2),
     vec![1., 2.,
@@ -638,8 +274,8 @@ fn test_convolution_1_channels_out_1_channels_in() {
   let pads: Array1<f32> = array![0., 0., 0., 0.];
 
   let conv_layer =
-    ConvolutionLayer::new_onnx_tensor_flow(kernel.clone(), None, Padding::NotSet, None, Some(1), pads, strides);
-  let output_layer: Array4<f32> = conv_layer.convolve(&input);
+    ConvolutionLayer::new(kernel.into_dyn(), None, Padding::NotSet, None, Some(1), pads, strides);
+  let output_layer = conv_layer.convolve(&input.into_dyn());
 
   println!("test_convolution_1_channels_out_1_channels_in: {:?}", output_layer);
 }
@@ -662,7 +298,7 @@ fn test_convolution_2_channels_out_2_channels_in() {
   )
     .unwrap();
 
-  // Kernel has shape (channels in, channels out, height, width)
+  // Kernel has shape (channels out, channels in, height, width)
   let kernel: Array4<f32> = Array::from_shape_vec(
     (2, 2, 3, 4),
     vec![1., 1., 1., 1.,
@@ -684,8 +320,8 @@ fn test_convolution_2_channels_out_2_channels_in() {
   let pads: Array1<f32> = array![0., 0., 0., 0.];
 
   let conv_layer =
-    ConvolutionLayer::new_onnx_tensor_flow(kernel.clone(), None, Padding::NotSet, None, Some(1), pads, strides);
-  let output_layer: Array4<f32> = conv_layer.convolve(&input);
+    ConvolutionLayer::new(kernel.into_dyn(), None, Padding::NotSet, None, Some(1), pads, strides);
+  let output_layer = conv_layer.convolve(&input.into_dyn());
 
   println!("test_convolution_2_channels_out_2_channels_in: {:?}", output_layer);
 }
@@ -699,7 +335,7 @@ fn test_convolution_1_channel_out_2_channel_in(){
   )
     .unwrap();
 
-  // Kernel has shape (channels in, channels out, height, width)
+  // Kernel has shape (channels out, channels in, height, width)
   let kernel: Array4<f32> = Array::from_shape_vec(
     (2, 1, 3, 4),
     vec![1., 2., 3., 4., 5., 6., 7., 8., 9., 10., 11., 12., 13., 14., 15., 16., 17., 18., 19., 20., 21., 22., 23., 24.],
@@ -710,12 +346,57 @@ fn test_convolution_1_channel_out_2_channel_in(){
   let pads: Array1<f32> = array![0., 0., 0., 0.];
 
   let conv_layer =
-    ConvolutionLayer::new_onnx_tensor_flow(kernel.clone(), None, Padding::NotSet, None, Some(1), pads, strides);
-  let output_layer: Array4<f32> = conv_layer.convolve(&input);
+    ConvolutionLayer::new(kernel.into_dyn(), None, Padding::NotSet, None, Some(1), pads, strides);
+  let output_layer = conv_layer.convolve(&input.into_dyn());
 
   println!("test_convolution_1_channel_out_2_channel_in: {:?}", output_layer);
 }
 
+#[allow(dead_code)]
+fn test_convolution_1d() {
+  // Input has shape (batch_size, channels, length)
+  let input = Array::from_shape_vec((1, 1, 5), vec![1., 2., 3., 4., 5.]).unwrap().into_dyn();
+
+  // Kernel has shape (channels out, channels in, length)
+  let kernel = Array::from_shape_vec((1, 1, 3), vec![1., 0., -1.]).unwrap().into_dyn();
+
+  // Padded input [0, 1, 2, 3, 4, 5, 0], windows starting at 0, 2, 4
+  let conv_layer = ConvolutionLayer::new(kernel.clone(), None, Padding::NotSet, None, Some(1), array![1., 1.], array![2.]);
+  println!("test_convolution_1d: {:?}", conv_layer.convolve(&input));
+  println!("expected: [[[-2, -2, 4]]]");
+
+  // Dilations 2: the only window is made of the elements 0, 2 and 4
+  let conv_layer = ConvolutionLayer::new(kernel, None, Padding::Valid, Some(array![2]), Some(1), array![], array![1.]);
+  println!("test_convolution_1d dilations: {:?}", conv_layer.convolve(&input));
+  println!("expected: [[[-4]]]");
+}
+
+#[allow(dead_code)]
+fn test_convolution_3d() {
+  // Input has shape (batch_size, channels, depth, height, width)
+  let input = Array::from_shape_vec((1, 1, 2, 2, 2), vec![1., 2., 3., 4., 5., 6., 7., 8.]).unwrap().into_dyn();
+
+  // Kernel has shape (channels out, channels in, depth, height, width): the sum and the difference of the corners
+  let kernel = Array::from_shape_vec(
+    (2, 1, 2, 2, 2),
+    vec![1., 1., 1., 1., 1., 1., 1., 1.,
+         1., 0., 0., 0., 0., 0., 0., -1.]
+  )
+    .unwrap()
+    .into_dyn();
+
+  let conv_layer = ConvolutionLayer::new(kernel, Some(array![1., 0.]), Padding::NotSet, None, Some(1), array![], array![1., 1., 1.]);
+  println!("test_convolution_3d: {:?}", conv_layer.convolve(&input));
+  println!("expected: [[[[[37]]], [[[-7]]]]]");
+
+  // Group 2: each channel has its own kernel
+  let input = Array::from_shape_vec((1, 2, 1, 1, 2), vec![1., 2., 3., 4.]).unwrap().into_dyn();
+  let kernel = Array::from_shape_vec((2, 1, 1, 1, 2), vec![1., 1., 1., -1.]).unwrap().into_dyn();
+  let conv_layer = ConvolutionLayer::new(kernel, None, Padding::Valid, None, Some(2), array![], array![1., 1., 1.]);
+  println!("test_convolution_3d group: {:?}", conv_layer.convolve(&input));
+  println!("expected: [[[[[3]]], [[[-1]]]]]");
+}
+
 #[allow(dead_code)]
 pub fn test_convolution(){
   test_convolution_1_channels_out_1_channels_in();
@@ -723,4 +404,8 @@ pub fn test_convolution(){
   test_convolution_1_channel_out_2_channel_in();
   println!("\n\n");
   test_convolution_2_channels_out_2_channels_in();
+  println!("\n\n");
+  test_convolution_1d();
+  println!("\n\n");
+  test_convolution_3d();
 }
\ No newline at end of file
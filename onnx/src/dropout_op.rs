@@ -1,14 +1,15 @@
-use ndarray::{Array4, Array};
+use ndarray::{ArrayD, Array};
+use num_traits::Float;
 use rand::{Rng, SeedableRng};
 
-///VERSION 7 of Dropout Operation
-pub fn dropout(
-  x: Array4<f32>,
+///VERSION 7 of Dropout Operation. The output has the element type of the input
+pub fn dropout<F: Float>(
+  x: ArrayD<F>,
   ratio: Option<f32>,
   seed: Option<u64>,
   training_mode: bool,
   return_mask: bool,
-) -> (Array4<f32>, Option<Array4<bool>>) {
+) -> (ArrayD<F>, Option<ArrayD<bool>>) {
   let mut drop_probability = 0.5;
   match ratio {
     Some(drop) => drop_probability = drop,
@@ -17,7 +18,7 @@ pub fn dropout(
 
   if drop_probability == 0.0 || !training_mode {
     if return_mask {
-      return (x.clone(), Some(Array4::from_elem(x.raw_dim(), true)))
+      return (x.clone(), Some(ArrayD::from_elem(x.raw_dim(), true)))
     }
     return (x, None);
   }
@@ -27,11 +28,11 @@ pub fn dropout(
     None => rand::rngs::SmallRng::from_entropy(),
   };
 
-  let mask = Array4::from_shape_fn(x.raw_dim(), |_| rng.gen::<f32>() >= drop_probability);
-  let scale = 1.0 / (1.0 - drop_probability);
+  let mask = ArrayD::from_shape_fn(x.raw_dim(), |_| rng.gen::<f32>() >= drop_probability);
+  let scale = F::from(1.0 / (1.0 - drop_probability)).unwrap();
 
-  let mask_num = mask.map(|&x| return if x == true { 1. } else { 0. });
-  let masked_x = &x * &mask_num * scale;
+  let mask_num = mask.map(|&x| if x { scale } else { F::zero() });
+  let masked_x = &x * &mask_num;
 
   if return_mask {
     (masked_x, Some(mask))
@@ -52,7 +53,12 @@ pub fn test_dropout(){
   println!("Input{:?}", input);
 
   let _ratio = 0.5; //default ratio
-  let output = dropout(input, None, None, false, false);
+  let output = dropout(input.into_dyn(), None, None, false, false);
 
   println!("Output: {:?}", output);
+
+  // Training mode with a seed: the kept values are scaled by 1 / (1 - ratio) in the element type of the input
+  let input = Array::from_shape_vec(4, vec![1f64, 2., 3., 4.]).unwrap().into_dyn();
+  println!("training: {:?}", dropout(input, Some(0.5), Some(0), true, true));
+  println!("expected: f64 values, each 0 where the mask is false and doubled where it is true");
 }
\ No newline at end of file
@@ -0,0 +1,122 @@
+use half::f16;
+use ndarray::{Array, Array2, ArrayD, IxDyn, LinalgScalar};
+use ndarray::prelude::*;
+use num_traits::AsPrimitive;
+use crate::tensor::Tensor;
+
+/* Runs gemm_with on the arrays held by a variant (C has already been checked to hold the same variant of A) */
+macro_rules! gemm_variant {
+  ($variant:ident, $a:expr, $b:expr, $c:expr, $($args:expr),*) => {{
+    let c = match $c {
+      Some(Tensor::$variant(c)) => Some(c),
+      _ => None
+    };
+    Tensor::$variant(gemm_with($a, $b, c, $($args),*)?.into_dyn())
+  }};
+}
+
+//OPSET VERSION = 11
+//Y = alpha * A' * B' + beta * C, where A' and B' are A and B transposed if trans_a/trans_b are set.
+//C is broadcast to the shape (M, N) of the product (unidirectional broadcasting): it can be a scalar, (N), (M, 1), (1, N) or (M, N).
+//A, B and C must have the same element type (float, double, float16, int32 or int64), which is kept by the result
+pub fn gemm(a: &Tensor, b: &Tensor, c: Option<&Tensor>, alpha: f32, beta: f32, trans_a: bool, trans_b: bool) -> Result<Tensor, String> {
+  if let Some(c) = c.filter(|c| c.data_type() != a.data_type()) {
+    return Err(format!("C is {:?}, expected {:?}", c.data_type(), a.data_type()));
+  }
+  let (alpha, beta) = (alpha as f64, beta as f64);
+  Ok(match (a, b) {
+    (Tensor::F32(a), Tensor::F32(b)) => gemm_variant!(F32, a, b, c, alpha, beta, trans_a, trans_b),
+    (Tensor::F64(a), Tensor::F64(b)) => gemm_variant!(F64, a, b, c, alpha, beta, trans_a, trans_b),
+    (Tensor::I64(a), Tensor::I64(b)) => gemm_variant!(I64, a, b, c, alpha, beta, trans_a, trans_b),
+    (Tensor::I32(a), Tensor::I32(b)) => gemm_variant!(I32, a, b, c, alpha, beta, trans_a, trans_b),
+    /* float16 has no native arithmetic: the product is computed in float */
+    (Tensor::F16(a), Tensor::F16(b)) => {
+      let y = gemm_with(&a.mapv(f16::to_f32), &b.mapv(f16::to_f32), c.map(|c| c.to_f32()).as_ref(), alpha, beta, trans_a, trans_b)?;
+      Tensor::F16(y.mapv(f16::from_f32).into_dyn())
+    }
+    _ => return Err(format!("Gemm is not defined for {:?} by {:?}", a.data_type(), b.data_type()))
+  })
+}
+
+fn gemm_with<T>(a: &ArrayD<T>, b: &ArrayD<T>, c: Option<&ArrayD<T>>, alpha: f64, beta: f64, trans_a: bool, trans_b: bool) -> Result<Array2<T>, String>
+  where T: LinalgScalar + AsPrimitive<f64>, f64: AsPrimitive<T> {
+  let a = match a.view().into_dimensionality::<Ix2>() {
+    Ok(a) => if trans_a { a.reversed_axes() } else { a },
+    Err(_) => return Err(format!("input A has shape {:?}, expected rank 2", a.shape()))
+  };
+  let b = match b.view().into_dimensionality::<Ix2>() {
+    Ok(b) => if trans_b { b.reversed_axes() } else { b },
+    Err(_) => return Err(format!("input B has shape {:?}, expected rank 2", b.shape()))
+  };
+  if a.shape()[1] != b.shape()[0] {
+    return Err(format!("cannot multiply {:?} by {:?}", a.shape(), b.shape()));
+  }
+
+  /* The scaling is computed in double, so that alpha and beta keep their fractional part also for the integers */
+  let mut y: Array2<T> = a.dot(&b);
+  if alpha != 1.0 {
+    y.mapv_inplace(|v| (alpha * v.as_()).as_());
+  }
+
+  if let Some(c) = c {
+    let (m, n) = (y.shape()[0], y.shape()[1]);
+    if c.ndim() > 2 {
+      return Err(format!("C has shape {:?}, expected rank 0, 1 or 2", c.shape()));
+    }
+    let c = match c.broadcast(IxDyn(&[m, n])) {
+      Some(c) => c.into_dimensionality::<Ix2>().unwrap(),
+      None => return Err(format!("cannot broadcast C {:?} to {:?}", c.shape(), [m, n]))
+    };
+    y.zip_mut_with(&c, |y, &c| *y = if beta == 1.0 { *y + c } else { (y.as_() + beta * c.as_()).as_() });
+  }
+
+  Ok(y)
+}
+
+#[allow(dead_code)]
+pub fn test_gemm() {
+  let a = Tensor::F32(Array::from_shape_vec((2, 3), vec![1., 2., 3., 4., 5., 6.]).unwrap().into_dyn());
+  let b = Tensor::F32(Array::from_shape_vec((3, 2), vec![7., 8., 9., 10., 11., 12.]).unwrap().into_dyn());
+
+  // A * B = [[58, 64], [139, 154]]
+  let result = gemm(&a, &b, None, 1., 1., false, false).unwrap();
+  println!("gemm: {:?}", result);
+  println!("expected: {:?}", array![[58., 64.], [139., 154.]]);
+
+  // A' * B' with A (3, 2) and B (2, 3): the same product of before
+  let a_t = Tensor::F32(a.to_f32().t().to_owned());
+  let b_t = Tensor::F32(b.to_f32().t().to_owned());
+  let result = gemm(&a_t, &b_t, None, 1., 1., true, true).unwrap();
+  println!("gemm transA transB: {:?}", result);
+  println!("expected: {:?}", array![[58., 64.], [139., 154.]]);
+
+  // 0.5 * A * B + 2 * C with C of shape (N): the row [1, -1] is added to every row
+  let c = Tensor::F32(array![1., -1.].into_dyn());
+  let result = gemm(&a, &b, Some(&c), 0.5, 2., false, false).unwrap();
+  println!("gemm alpha beta C (N): {:?}", result);
+  println!("expected: {:?}", array![[31., 30.], [71.5, 75.]]);
+
+  // C of shape (M, 1): the column [10, 20] is added to every column
+  let c = Tensor::F32(array![[10.], [20.]].into_dyn());
+  let result = gemm(&a, &b, Some(&c), 1., 1., false, false).unwrap();
+  println!("gemm C (M, 1): {:?}", result);
+  println!("expected: {:?}", array![[68., 74.], [159., 174.]]);
+
+  // Scalar C
+  let c = Tensor::F32(ArrayD::from_elem(IxDyn(&[]), 1.));
+  let result = gemm(&a, &b, Some(&c), 1., -1., false, false).unwrap();
+  println!("gemm scalar C: {:?}", result);
+  println!("expected: {:?}", array![[57., 63.], [138., 153.]]);
+
+  // C of shape (3) can't be broadcast to (2, 2)
+  let c = Tensor::F32(array![1., 2., 3.].into_dyn());
+  println!("gemm wrong C: {:?}", gemm(&a, &b, Some(&c), 1., 1., false, false));
+
+  // int64 operands keep their element type, alpha is applied in double and truncated
+  let a_int = Tensor::I64(array![[1, 2], [3, 4]].into_dyn());
+  let c_int = Tensor::I64(array![100, 200].into_dyn());
+  println!("gemm int64: {:?}", gemm(&a_int, &a_int, Some(&c_int), 0.5, 1., false, false));
+  println!("expected: I64 [[103, 205], [107, 211]]");
+  println!("gemm int64 with float C: {:?}", gemm(&a_int, &a_int, Some(&c), 1., 1., false, false));
+  println!("expected: error");
+}
@@ -1,22 +1,26 @@
-use ndarray::{Array, Array4, Axis};
+use ndarray::{Array, ArrayD, Axis, IxDyn};
+use num_traits::Float;
 
 //OPSET VERSION. N channel out
-pub(crate) fn global_average_pool(x: Array4<f32>) -> Array4<f32> {
-  let mut output: Array4<f32> = Array::zeros((
-    x.len_of(Axis(0)),
-    x.len_of(Axis(1)),
-    1,
-    1,
-  ));
+//The input is (N, C, D1, ..., Dn) with any number of spatial axes, the output (N, C, 1, ..., 1)
+pub(crate) fn global_average_pool<F: Float>(x: ArrayD<F>) -> ArrayD<F> {
+  let mut output_shape = vec![1; x.ndim()];
+  output_shape[0] = x.len_of(Axis(0));
+  output_shape[1] = x.len_of(Axis(1));
+  let mut output: ArrayD<F> = ArrayD::zeros(IxDyn(&output_shape));
 
+  let batch = x.len_of(Axis(0)); //foreach image of the batch
   let ch = x.len_of(Axis(1)); //foreach channel
 
-  for c in 0..ch {
-    let channel_slice = x.index_axis(Axis(1), c);
-    let mut sum: f32 = channel_slice.iter().sum();
-    let counter = channel_slice.len();
-    sum = sum / counter as f32;
-    output[[0, c, 0, 0]] = sum;
+  for b in 0..batch {
+    for c in 0..ch {
+      let channel_slice = x.index_axis(Axis(0), b);
+      let channel_slice = channel_slice.index_axis(Axis(0), c);
+      let mut sum: F = channel_slice.iter().fold(F::zero(), |acc, &v| acc + v);
+      let counter = channel_slice.len();
+      sum = sum / F::from(counter).unwrap();
+      output.index_axis_mut(Axis(0), b).index_axis_mut(Axis(0), c).fill(sum);
+    }
   }
 
   output
@@ -31,6 +35,6 @@ pub fn test_global_average_pool() {
   )
     .unwrap();
   println!("input: {:?}", input);
-  let output = global_average_pool(input);
+  let output = global_average_pool(input.into_dyn());
   println!("output: {:?}", output);
 }
@@ -0,0 +1,38 @@
+use ndarray::{Array, ArrayD, Axis, IxDyn};
+use num_traits::Float;
+
+//OPSET VERSION. N channel out
+//The input is (N, C, D1, ..., Dn) with any number of spatial axes, the output (N, C, 1, ..., 1)
+pub(crate) fn global_max_pool<F: Float>(x: ArrayD<F>) -> ArrayD<F> {
+  let mut output_shape = vec![1; x.ndim()];
+  output_shape[0] = x.len_of(Axis(0));
+  output_shape[1] = x.len_of(Axis(1));
+  let mut output: ArrayD<F> = ArrayD::zeros(IxDyn(&output_shape));
+
+  let batch = x.len_of(Axis(0)); //foreach image of the batch
+  let ch = x.len_of(Axis(1)); //foreach channel
+
+  for b in 0..batch {
+    for c in 0..ch {
+      let channel_slice = x.index_axis(Axis(0), b);
+      let channel_slice = channel_slice.index_axis(Axis(0), c);
+      output.index_axis_mut(Axis(0), b).index_axis_mut(Axis(0), c).fill(channel_slice.iter().fold(F::neg_infinity(), |max, &v| max.max(v)));
+    }
+  }
+
+  output
+}
+
+#[allow(dead_code)]
+pub fn test_global_max_pool() {
+  //(batch size, channels out, height, width)
+  let input = Array::from_shape_vec(
+    (1, 2, 2, 2),
+    vec![1., -2., 7., 4., -5., -6., -3., -8.]
+  )
+    .unwrap();
+  println!("input: {:?}", input);
+  let output = global_max_pool(input.into_dyn());
+  println!("output: {:?}", output);
+  println!("expected: [[[[7]], [[-3]]]]");
+}
@@ -0,0 +1,61 @@
+use ndarray::{Array, Array1, ArrayD, Axis};
+use num_traits::Float;
+
+//OPSET VERSION = 21
+//The channels of the input (N, C, D1, ..., Dn) are split into num_groups groups of consecutive channels; every group of
+//every image is normalized with its own mean and variance, then each channel is scaled and shifted:
+//y = (x - mean) / sqrt(var + epsilon) * scale[c] + bias[c]
+//With opset 18 scale and bias have one value for each group instead of one for each channel (per_channel = false)
+pub fn group_normalization<F: Float>(x: &ArrayD<F>, scale: &Array1<F>, bias: &Array1<F>, num_groups: usize, epsilon: f32, per_channel: bool) -> Result<ArrayD<F>, String> {
+  let epsilon = F::from(epsilon).unwrap();
+  if x.ndim() < 2 {
+    return Err(format!("input has shape {:?}, expected (N, C, ...)", x.shape()));
+  }
+  let channels = x.shape()[1];
+  if num_groups == 0 || !channels.is_multiple_of(num_groups) {
+    return Err(format!("{} channels cannot be split into {} groups", channels, num_groups));
+  }
+  let group_size = channels / num_groups;
+  let parameters = if per_channel { channels } else { num_groups };
+  for (name, parameter) in [("scale", scale), ("bias", bias)] {
+    if parameter.len() != parameters {
+      return Err(format!("{} has {} values, expected {}", name, parameter.len(), parameters));
+    }
+  }
+
+  let mut y = x.clone();
+  for mut image in y.axis_iter_mut(Axis(0)) {
+    for (g, mut group) in image.axis_chunks_iter_mut(Axis(0), group_size).enumerate() {
+      let size = F::from(group.len()).unwrap();
+      let mean = group.sum() / size;
+      let var = group.fold(F::zero(), |acc, &v| acc + (v - mean) * (v - mean)) / size;
+      let inv_std_dev = (var + epsilon).sqrt().recip();
+      for (i, mut channel) in group.axis_iter_mut(Axis(0)).enumerate() {
+        let p = if per_channel { g * group_size + i } else { g };
+        channel.mapv_inplace(|v| (v - mean) * inv_std_dev * scale[p] + bias[p]);
+      }
+    }
+  }
+  Ok(y)
+}
+
+#[allow(dead_code)]
+pub fn test_group_normalization() {
+  // Input (1, 4, 2): groups {channel 0, channel 1} = [1, 2, 3, 4] and {channel 2, channel 3} = [0, 0, 10, 10]
+  let x = Array::from_shape_vec((1, 4, 2), vec![1., 2., 3., 4., 0., 0., 10., 10.]).unwrap().into_dyn();
+
+  // One scale for each channel (opset 21)
+  let scale = Array1::from(vec![1., 2., 1., 1.]);
+  let bias = Array1::from(vec![0., 0., 0., 1.]);
+  println!("group normalization: {:?}", group_normalization(&x, &scale, &bias, 2, 0., true));
+  println!("expected: [[[-1.3416, -0.4472], [0.8944, 2.6833], [-1, -1], [2, 2]]]");
+
+  // One scale for each group (opset 18)
+  let scale = Array1::from(vec![1., 2.]);
+  let bias = Array1::from(vec![0., 1.]);
+  println!("group normalization per group: {:?}", group_normalization(&x, &scale, &bias, 2, 0., false));
+  println!("expected: [[[-1.3416, -0.4472], [0.4472, 1.3416], [-1, -1], [3, 3]]]");
+
+  // 4 channels cannot be split into 3 groups
+  println!("group normalization wrong: {:?}", group_normalization(&x, &scale, &bias, 3, 0., false));
+}
@@ -0,0 +1,355 @@
+use std::ops::{Add, Mul};
+use half::f16;
+use ndarray::{Array, ArrayD, Axis, Dimension, IxDyn, Slice};
+use crate::tensor::{map_each_variant, Tensor};
+
+//OPSET VERSION = 18
+//Operations that select or move elements of a tensor by index, keeping their element type.
+//Indices can be negative (counted from the end of the dimension)
+
+/*
+This function computes the elements selected by a Slice along a dimension, with the clamping rules of onnx
+  -It takes 4 parameters:
+    ~ size: size of the dimension
+    ~ start: first index (included)
+    ~ end: last index (excluded)
+    ~ step: distance between two selected indices (negative to go backward, not 0)
+  -It returns the first selected index and how many indices are selected
+*/
+pub(crate) fn slice_range(size: usize, start: i64, end: i64, step: i64) -> (usize, usize) {
+  if size == 0 {
+    return (0, 0);
+  }
+  let size = size as i64;
+  /* A step longer than the dimension selects at most the start, like a step as long as the dimension: clamping it
+  keeps the arithmetic below from overflowing */
+  let step = step.clamp(-size, size);
+  let resolve = |index: i64| if index < 0 { index.saturating_add(size) } else { index };
+  let (start, end) = if step > 0 {
+    (resolve(start).clamp(0, size), resolve(end).clamp(0, size))
+  } else {
+    (resolve(start).clamp(0, size - 1), resolve(end).clamp(-1, size - 1))
+  };
+  let count = if step > 0 { (end - start + step - 1) / step } else { (start - end - step - 1) / -step };
+  (start.max(0) as usize, count.max(0) as usize)
+}
+
+/*
+This function executes the Slice
+  -It takes 5 parameters:
+    ~ x: input
+    ~ starts: first index for each sliced axis
+    ~ ends: last index (excluded) for each sliced axis
+    ~ axes: sliced axes, in [0, rank - 1]
+    ~ steps: step for each sliced axis
+  -It returns the selected elements, or an error if the parameters have different lengths, an axis is out of range or
+  repeated or a step is 0
+*/
+pub fn slice(x: &Tensor, starts: &[i64], ends: &[i64], axes: &[usize], steps: &[i64]) -> Result<Tensor, String> {
+  if starts.len() != axes.len() || ends.len() != axes.len() || steps.len() != axes.len() {
+    return Err(format!("starts {:?}, ends {:?}, axes {:?} and steps {:?} have different lengths", starts, ends, axes, steps));
+  }
+  let mut indices: Vec<Option<Vec<usize>>> = vec![None; x.rank()];
+  for i in 0..axes.len() {
+    if steps[i] == 0 {
+      return Err("step cannot be 0".to_string());
+    }
+    if axes[i] >= x.rank() {
+      return Err(format!("axis {} out of range for rank {}", axes[i], x.rank()));
+    }
+    if indices[axes[i]].is_some() {
+      return Err(format!("axis {} is repeated in {:?}", axes[i], axes));
+    }
+    let (start, count) = slice_range(x.shape()[axes[i]], starts[i], ends[i], steps[i]);
+    indices[axes[i]] = Some((0..count).map(|k| (start as i64 + k as i64 * steps[i]) as usize).collect());
+  }
+  Ok(map_each_variant!(x, arr => {
+    let mut output = arr.clone();
+    for (axis, selected) in indices.iter().enumerate() {
+      if let Some(selected) = selected {
+        output = select(&output, axis, selected);
+      }
+    }
+    output
+  }))
+}
+
+/* Takes the given indices along an axis (select doesn't accept an empty list of indices) */
+fn select<T: Clone>(x: &ArrayD<T>, axis: usize, indices: &[usize]) -> ArrayD<T> {
+  if indices.is_empty() {
+    x.slice_axis(Axis(axis), Slice::from(0..0)).to_owned()
+  } else {
+    x.select(Axis(axis), indices)
+  }
+}
+
+/* Converts the (possibly negative) indices into positions in a dimension of the given size */
+fn resolve_indices(indices: &ArrayD<i64>, size: usize) -> Result<ArrayD<usize>, String> {
+  let mut resolved = ArrayD::zeros(indices.raw_dim());
+  for (r, &index) in resolved.iter_mut().zip(indices.iter()) {
+    let position = if index < 0 { index + size as i64 } else { index };
+    if position < 0 || position >= size as i64 {
+      return Err(format!("index {} out of range for a dimension of size {}", index, size));
+    }
+    *r = position as usize;
+  }
+  Ok(resolved)
+}
+
+/*
+This function executes the Gather: entries of the input along an axis, picked by the indices
+  -It takes 3 parameters:
+    ~ data: input
+    ~ indices: indices along the axis, of any shape
+    ~ axis: gathered axis, in [0, rank - 1]
+  -It returns a tensor with shape data[..axis] + indices + data[axis + 1..], or an error if an index is out of range
+*/
+pub fn gather(data: &Tensor, indices: &ArrayD<i64>, axis: usize) -> Result<Tensor, String> {
+  let positions = resolve_indices(indices, data.shape()[axis])?;
+  let positions: Vec<usize> = positions.iter().copied().collect();
+  let mut output_shape = data.shape()[..axis].to_vec();
+  output_shape.extend_from_slice(indices.shape());
+  output_shape.extend_from_slice(&data.shape()[axis + 1..]);
+  /* The gathered entries replace the axis, which is then split into the dimensions of the indices */
+  Ok(map_each_variant!(data, arr => select(arr, axis, &positions).to_shape(IxDyn(&output_shape)).unwrap().into_owned()))
+}
+
+/*
+This function executes the GatherElements: every element of the output is the element of the input with the same
+position, except along the axis where the position is given by the index
+  -It takes 3 parameters:
+    ~ data: input
+    ~ indices: indices, with the rank of the input and dimensions not greater than its ones
+    ~ axis: gathered axis, in [0, rank - 1]
+  -It returns a tensor with the shape of the indices, or an error if the shapes aren't compatible or an index is out of range
+*/
+pub fn gather_elements(data: &Tensor, indices: &ArrayD<i64>, axis: usize) -> Result<Tensor, String> {
+  let compatible = indices.ndim() == data.rank()
+    && indices.shape().iter().zip(data.shape()).enumerate().all(|(d, (&i, &s))| d == axis || i <= s);
+  if !compatible {
+    return Err(format!("indices {:?} are not compatible with the input {:?}", indices.shape(), data.shape()));
+  }
+  let positions = resolve_indices(indices, data.shape()[axis])?;
+  Ok(map_each_variant!(data, arr => {
+    let mut source = vec![0; arr.ndim()];
+    let values = positions.indexed_iter().map(|(index, &position)| {
+      source.copy_from_slice(index.slice());
+      source[axis] = position;
+      arr[IxDyn(&source)]
+    }).collect::<Vec<_>>();
+    ArrayD::from_shape_vec(IxDyn(indices.shape()), values).unwrap()
+  }))
+}
+
+/*
+This function finds the slices of the input addressed by the last dimension of the indices, as in GatherND and ScatterND
+  -It takes 3 parameters:
+    ~ shape: shape of the input
+    ~ indices: indices, with shape batch + [m..] + [k]: every row of k indices addresses a slice of the input
+    ~ batch_dims: number of leading dimensions shared by the input and the indices
+  -It returns, for every row, the position of the first element of the slice in the (row-major) input,
+  and the number of elements of a slice
+*/
+fn nd_offsets(shape: &[usize], indices: &ArrayD<i64>, batch_dims: usize) -> Result<(Vec<usize>, usize), String> {
+  let k = match indices.shape().last() {
+    Some(&k) if indices.ndim() > batch_dims && batch_dims + k <= shape.len() => k,
+    _ => return Err(format!("indices {:?} are not compatible with the input {:?}", indices.shape(), shape))
+  };
+  if indices.shape()[..batch_dims] != shape[..batch_dims] {
+    return Err(format!("the batch dimensions of the indices {:?} and of the input {:?} are different", indices.shape(), shape));
+  }
+  let slice_size: usize = shape[batch_dims + k..].iter().product();
+  /* Distance in the input between two consecutive values of every indexed dimension */
+  let strides: Vec<usize> = (batch_dims..batch_dims + k).map(|d| shape[d + 1..].iter().product()).collect();
+  let batch_size: usize = shape[batch_dims..].iter().product();
+  let rows: usize = indices.shape()[..indices.ndim() - 1].iter().product();
+  let rows_per_batch = rows / indices.shape()[..batch_dims].iter().product::<usize>().max(1);
+
+  let flat: Vec<i64> = indices.iter().copied().collect();
+  let mut offsets = Vec::with_capacity(rows);
+  for row in 0..rows {
+    let mut offset = row / rows_per_batch.max(1) * batch_size;
+    for j in 0..k {
+      let size = shape[batch_dims + j] as i64;
+      let index = flat[row * k + j];
+      let position = if index < 0 { index + size } else { index };
+      if position < 0 || position >= size {
+        return Err(format!("index {} out of range for a dimension of size {}", index, size));
+      }
+      offset += position as usize * strides[j];
+    }
+    offsets.push(offset);
+  }
+  Ok((offsets, slice_size))
+}
+
+/*
+This function executes the GatherND
+  -It takes 3 parameters:
+    ~ data: input
+    ~ indices: indices, with shape batch + [m..] + [k]
+    ~ batch_dims: number of leading dimensions shared by the input and the indices
+  -It returns a tensor with shape indices[..-1] + data[batch_dims + k..], or an error if the shapes aren't compatible
+  or an index is out of range
+*/
+pub fn gather_nd(data: &Tensor, indices: &ArrayD<i64>, batch_dims: usize) -> Result<Tensor, String> {
+  let (offsets, slice_size) = nd_offsets(data.shape(), indices, batch_dims)?;
+  let k = indices.shape()[indices.ndim() - 1];
+  let mut output_shape = indices.shape()[..indices.ndim() - 1].to_vec();
+  output_shape.extend_from_slice(&data.shape()[batch_dims + k..]);
+  Ok(map_each_variant!(data, arr => {
+    let values: Vec<_> = arr.iter().cloned().collect();
+    let output: Vec<_> = offsets.iter().flat_map(|&offset| values[offset..offset + slice_size].iter().cloned()).collect();
+    ArrayD::from_shape_vec(IxDyn(&output_shape), output).unwrap()
+  }))
+}
+
+/* How ScatterND combines the updates with the values already present */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScatterReduction {
+  None,
+  Add,
+  Mul,
+  Max,
+  Min,
+}
+
+/*
+This function executes the ScatterND: a copy of the input where the slices addressed by the indices are replaced by
+(or combined with) the updates
+  -It takes 4 parameters:
+    ~ data: input
+    ~ indices: indices, with shape [m..] + [k]
+    ~ updates: new values, with shape indices[..-1] + data[k..]
+    ~ reduction: how the updates are combined with the input
+  -It returns the updated copy of the input, or an error if the shapes or the element types aren't compatible or an
+  index is out of range
+*/
+pub fn scatter_nd(data: &Tensor, indices: &ArrayD<i64>, updates: &Tensor, reduction: ScatterReduction) -> Result<Tensor, String> {
+  let (offsets, slice_size) = nd_offsets(data.shape(), indices, 0)?;
+  let k = indices.shape()[indices.ndim() - 1];
+  let mut updates_shape = indices.shape()[..indices.ndim() - 1].to_vec();
+  updates_shape.extend_from_slice(&data.shape()[k..]);
+  if updates.shape() != updates_shape.as_slice() {
+    return Err(format!("updates have shape {:?}, expected {:?}", updates.shape(), updates_shape));
+  }
+
+  match (data, updates, reduction) {
+    (Tensor::Bool(x), Tensor::Bool(u), ScatterReduction::None) => Ok(Tensor::Bool(scatter_with(x, u, &offsets, slice_size, |_, new| new))),
+    (Tensor::F32(x), Tensor::F32(u), _) => Ok(Tensor::F32(scatter_with(x, u, &offsets, slice_size, combine(reduction)))),
+    (Tensor::F64(x), Tensor::F64(u), _) => Ok(Tensor::F64(scatter_with(x, u, &offsets, slice_size, combine(reduction)))),
+    (Tensor::F16(x), Tensor::F16(u), _) => Ok(Tensor::F16(scatter_with(x, u, &offsets, slice_size, combine::<f16>(reduction)))),
+    (Tensor::I64(x), Tensor::I64(u), _) => Ok(Tensor::I64(scatter_with(x, u, &offsets, slice_size, combine(reduction)))),
+    (Tensor::I32(x), Tensor::I32(u), _) => Ok(Tensor::I32(scatter_with(x, u, &offsets, slice_size, combine(reduction)))),
+    (Tensor::U8(x), Tensor::U8(u), _) => Ok(Tensor::U8(scatter_with(x, u, &offsets, slice_size, combine(reduction)))),
+    (Tensor::I8(x), Tensor::I8(u), _) => Ok(Tensor::I8(scatter_with(x, u, &offsets, slice_size, combine(reduction)))),
+    _ => Err(format!("ScatterND with reduction {:?} is not defined between {:?} and {:?}", reduction, data.data_type(), updates.data_type()))
+  }
+}
+
+fn combine<T: Copy + PartialOrd + Add<Output = T> + Mul<Output = T>>(reduction: ScatterReduction) -> fn(T, T) -> T {
+  match reduction {
+    ScatterReduction::None => |_, new| new,
+    ScatterReduction::Add => |old, new| old + new,
+    ScatterReduction::Mul => |old, new| old * new,
+    ScatterReduction::Max => |old, new| if new > old { new } else { old },
+    ScatterReduction::Min => |old, new| if new < old { new } else { old },
+  }
+}
+
+fn scatter_with<T: Copy>(data: &ArrayD<T>, updates: &ArrayD<T>, offsets: &[usize], slice_size: usize, f: fn(T, T) -> T) -> ArrayD<T> {
+  let mut values: Vec<T> = data.iter().copied().collect();
+  let updates: Vec<T> = updates.iter().copied().collect();
+  /* The updates are applied in the order of the indices */
+  for (row, &offset) in offsets.iter().enumerate() {
+    for j in 0..slice_size {
+      values[offset + j] = f(values[offset + j], updates[row * slice_size + j]);
+    }
+  }
+  ArrayD::from_shape_vec(data.raw_dim(), values).unwrap()
+}
+
+/*
+This function computes the sizes of the parts of a Split when they aren't given: parts as equal as possible, with the
+last one smaller if the dimension isn't divisible
+*/
+pub(crate) fn split_sizes(size: usize, parts: usize) -> Vec<usize> {
+  let part = size.div_ceil(parts.max(1));
+  (0..parts).map(|i| part.min(size.saturating_sub(i * part))).collect()
+}
+
+/*
+This function executes the Split
+  -It takes 3 parameters:
+    ~ x: input
+    ~ axis: split axis, in [0, rank - 1]
+    ~ sizes: size of every part along the axis
+  -It returns the parts, or an error if the sizes don't add up to the dimension
+*/
+pub fn split(x: &Tensor, axis: usize, sizes: &[usize]) -> Result<Vec<Tensor>, String> {
+  if sizes.iter().sum::<usize>() != x.shape()[axis] {
+    return Err(format!("parts {:?} don't add up to dimension {} of {:?}", sizes, axis, x.shape()));
+  }
+  let mut start = 0;
+  Ok(sizes.iter().map(|&size| {
+    let part = map_each_variant!(x, arr => arr.slice_axis(Axis(axis), Slice::from(start..start + size)).to_owned());
+    start += size;
+    part
+  }).collect())
+}
+
+#[allow(dead_code)]
+pub fn test_indexing() {
+  let x = Tensor::I64(Array::from_shape_vec((3, 4), (0..12).collect()).unwrap().into_dyn());
+  // Slice with a negative start, an end beyond the dimension and a negative step
+  println!("slice: {:?}", slice(&x, &[-2, 3], &[i64::MAX, 0], &[0, 1], &[1, -2]));
+  println!("expected: [[7, 5], [11, 9]]");
+  println!("slice empty: {:?}", slice(&x, &[2], &[1], &[0], &[1]).map(|t| t.shape().to_vec()));
+  println!("expected: [0, 4]");
+  // Steps beyond the range of the dimension, and more sliced axes than the dimensions of the input
+  println!("slice huge steps: {:?}", slice(&x, &[1, -1], &[3, -5], &[0, 1], &[i64::MAX, i64::MIN]));
+  println!("expected: [[7]]");
+  println!("slice axis out of range: {:?}", slice(&x, &[0, 0, 0], &[1, 1, 1], &[0, 1, 2], &[1, 1, 1]));
+  println!("expected: error");
+
+  // Gather rows (embedding lookup) with 2D indices, and columns with a negative index
+  let indices = Array::from_shape_vec((2, 1), vec![2, 0]).unwrap().into_dyn();
+  println!("gather axis 0: {:?}", gather(&x, &indices, 0));
+  println!("expected: [[[8, 9, 10, 11]], [[0, 1, 2, 3]]]");
+  let indices = Array::from_shape_vec(2, vec![-1, 1]).unwrap().into_dyn();
+  println!("gather axis 1: {:?}", gather(&x, &indices, 1));
+  println!("expected: [[3, 1], [7, 5], [11, 9]]");
+  println!("gather out of range: {:?}", gather(&x, &indices.mapv(|i| i * 5), 1));
+  println!("expected: error");
+
+  let indices = Array::from_shape_vec((2, 2), vec![0, 3, -1, 1]).unwrap().into_dyn();
+  println!("gather elements axis 1: {:?}", gather_elements(&x, &indices, 1));
+  println!("expected: [[0, 3], [7, 5]]");
+
+  // GatherND: single elements, rows, and rows with a batch dimension
+  let indices = Array::from_shape_vec((2, 2), vec![0, 1, 2, -1]).unwrap().into_dyn();
+  println!("gather nd: {:?}", gather_nd(&x, &indices, 0));
+  println!("expected: [1, 11]");
+  let indices = Array::from_shape_vec((2, 1), vec![1, 0]).unwrap().into_dyn();
+  println!("gather nd rows: {:?}", gather_nd(&x, &indices, 0));
+  println!("expected: [[4, 5, 6, 7], [0, 1, 2, 3]]");
+  let indices = Array::from_shape_vec((3, 1), vec![1, 2, 3]).unwrap().into_dyn();
+  println!("gather nd batch: {:?}", gather_nd(&x, &indices, 1));
+  println!("expected: [1, 6, 11]");
+
+  // ScatterND of a row, and with the add reduction on repeated indices
+  let indices = Array::from_shape_vec((1, 1), vec![1]).unwrap().into_dyn();
+  let updates = Tensor::I64(Array::from_shape_vec((1, 4), vec![-1, -2, -3, -4]).unwrap().into_dyn());
+  println!("scatter nd: {:?}", scatter_nd(&x, &indices, &updates, ScatterReduction::None));
+  println!("expected: [[0, 1, 2, 3], [-1, -2, -3, -4], [8, 9, 10, 11]]");
+  let indices = Array::from_shape_vec((2, 2), vec![0, 0, 0, 0]).unwrap().into_dyn();
+  let updates = Tensor::I64(Array::from_shape_vec(2, vec![10, 20]).unwrap().into_dyn());
+  println!("scatter nd add: {:?}", scatter_nd(&x, &indices, &updates, ScatterReduction::Add));
+  println!("expected: [[30, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11]]");
+
+  // Split in given parts and in equal parts
+  println!("split: {:?}", split(&x, 1, &[1, 3]).map(|parts| parts.iter().map(|p| p.shape().to_vec()).collect::<Vec<_>>()));
+  println!("expected: [[3, 1], [3, 3]]");
+  println!("split sizes: {:?}", split_sizes(5, 3));
+  println!("expected: [2, 2, 1]");
+}
@@ -0,0 +1,84 @@
+use std::error::Error;
+use std::fmt;
+use crate::onnx_structure::NodeProto;
+
+/*
+Errors raised while running the inference. The node related variants carry the name and the operation
+type of the node that failed, so a bad model can be reported without killing the caller.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum InferenceError {
+  /* The operation of the node isn't implemented */
+  UnsupportedOp { node: String, op_type: String },
+  /* The operation is implemented, but not for the opset version imported by the model */
+  UnsupportedVersion { node: String, op_type: String, domain: String, version: i64 },
+  /* An input of the node is neither a graph input, an initializer or the output of a previous node */
+  MissingInput { node: String, op_type: String, input: String },
+  /* The shapes of the node inputs aren't compatible with the operation */
+  ShapeMismatch { node: String, op_type: String, message: String },
+  /* An attribute of the node is unknown, missing or has a wrong value */
+  BadAttribute { node: String, op_type: String, attribute: String, message: String },
+  /* A value fed to the model doesn't match its declaration */
+  InvalidInput { input: String, message: String },
+  /* The model itself is inconsistent (i.e. missing graph, undecodable initializer) */
+  MalformedModel(String),
+}
+
+impl InferenceError {
+  pub fn unsupported_op(node: &NodeProto) -> InferenceError {
+    InferenceError::UnsupportedOp { node: node_name(node), op_type: node_op_type(node) }
+  }
+
+  pub fn unsupported_version(node: &NodeProto, domain: &str, version: i64) -> InferenceError {
+    InferenceError::UnsupportedVersion { node: node_name(node), op_type: node_op_type(node), domain: domain.to_string(), version }
+  }
+
+  pub fn missing_input(node: &NodeProto, input: &str) -> InferenceError {
+    InferenceError::MissingInput { node: node_name(node), op_type: node_op_type(node), input: input.to_string() }
+  }
+
+  pub fn shape_mismatch(node: &NodeProto, message: impl Into<String>) -> InferenceError {
+    InferenceError::ShapeMismatch { node: node_name(node), op_type: node_op_type(node), message: message.into() }
+  }
+
+  pub fn bad_attribute(node: &NodeProto, attribute: &str, message: impl Into<String>) -> InferenceError {
+    InferenceError::BadAttribute { node: node_name(node), op_type: node_op_type(node), attribute: attribute.to_string(), message: message.into() }
+  }
+}
+
+impl fmt::Display for InferenceError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      InferenceError::UnsupportedOp { node, op_type } =>
+        write!(f, "operation '{}' of node '{}' is not supported", op_type, node),
+      InferenceError::UnsupportedVersion { node, op_type, domain, version } =>
+        write!(f, "operation '{}' of node '{}' is not supported in version {} of domain '{}'", op_type, node, version, if domain.is_empty() { "ai.onnx" } else { domain }),
+      InferenceError::MissingInput { node, op_type, input } =>
+        write!(f, "input '{}' of node '{}' ({}) is not available", input, node, op_type),
+      InferenceError::ShapeMismatch { node, op_type, message } =>
+        write!(f, "shape mismatch in node '{}' ({}): {}", node, op_type, message),
+      InferenceError::BadAttribute { node, op_type, attribute, message } =>
+        write!(f, "bad attribute '{}' in node '{}' ({}): {}", attribute, node, op_type, message),
+      InferenceError::InvalidInput { input, message } =>
+        write!(f, "invalid model input '{}': {}", input, message),
+      InferenceError::MalformedModel(message) =>
+        write!(f, "malformed model: {}", message),
+    }
+  }
+}
+
+impl Error for InferenceError {}
+
+/*
+This function gets a printable name of the node: its name, or its first output when the name isn't set
+*/
+pub(crate) fn node_name(node: &NodeProto) -> String {
+  match &node.name {
+    Some(name) if !name.is_empty() => name.clone(),
+    _ => node.output.first().cloned().unwrap_or_default(),
+  }
+}
+
+fn node_op_type(node: &NodeProto) -> String {
+  node.op_type.clone().unwrap_or_default()
+}
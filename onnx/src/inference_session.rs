@@ -0,0 +1,618 @@
+use std::collections::{HashMap, HashSet};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use crate::convolution_op::ConvolutionLayer as ConvLayerConv;
+use crate::conv_transpose_op::ConvTransposeLayer;
+use crate::inference_error::InferenceError;
+use crate::max_pool_op::ConvolutionLayer as ConvLayerMaxPool;
+use crate::model_inference::{build_conv_layer, build_conv_transpose_layer, build_pool_layer, node_inference};
+use crate::onnx_structure::{AttributeProto, ModelProto, NodeProto, ValueInfoProto};
+use crate::op_registry::{opset_imports, normalize_domain, DEFAULT_DOMAIN, OpKernel, OperatorRegistry};
+use crate::scheduler::{DependencyGraph, PoolHandle, ThreadPool};
+use crate::tensor::{Tensor, TensorType};
+
+/*
+Model ready to be executed: initializers already decoded, attributes resolved, nodes sorted in topological order
+and the layers that only depend on initializers (i.e. Conv kernels) already built.
+A session is immutable once created, so run can be called many times and from many threads at once.
+*/
+pub struct InferenceSession {
+  pub(crate) graph: Arc<PreparedGraph>,
+  pub(crate) graph_inputs: Vec<ValueInfoProto>,
+  pub(crate) graph_outputs: Vec<String>,
+  /* Declarations of the graph outputs, checked at the end of every run (value_info is only a hint and isn't checked) */
+  declared_outputs: Vec<ValueInfoProto>,
+  /* Workers executing the ready nodes. None in single thread mode */
+  pool: Option<ThreadPool>,
+}
+
+/* Options used to create the session */
+#[derive(Debug, Clone)]
+pub struct SessionOptions {
+  /* Number of worker threads. With 1 the nodes are executed one by one, in topological order, by the thread calling run */
+  pub num_threads: usize,
+  /* Implementations of the operators, picked according to the opset imported by the model */
+  pub operators: Arc<OperatorRegistry>,
+}
+
+/* Part of the session shared with the workers */
+pub(crate) struct PreparedGraph {
+  pub(crate) initializers: HashMap<String, Arc<Tensor>>,
+  /* Nodes in topological order: every node comes after the nodes producing its inputs */
+  pub(crate) nodes: Vec<PreparedNode>,
+  pub(crate) dependencies: DependencyGraph,
+}
+
+/* Node of the graph together with the data resolved when the session is created */
+pub struct PreparedNode {
+  pub(crate) proto: NodeProto,
+  /* Implementation of the operation for the opset imported by the model */
+  pub(crate) kernel: Arc<dyn OpKernel>,
+  pub(crate) attributes: NodeAttributes,
+  pub(crate) prepared_op: PreparedOp,
+}
+
+/* Layers built once when their parameters don't depend on the values computed at run time */
+pub enum PreparedOp {
+  Conv(ConvLayerConv<f32>),
+  ConvTranspose(ConvTransposeLayer),
+  Pool(ConvLayerMaxPool<f32>),
+  None,
+}
+
+/* Attributes of a node indexed by their name */
+#[derive(Default)]
+pub struct NodeAttributes {
+  attributes: HashMap<String, AttributeProto>,
+}
+
+/* State of a single run, shared by the workers executing its nodes */
+struct RunContext {
+  graph: Arc<PreparedGraph>,
+  pool: PoolHandle,
+  /* Values calculated by the nodes (and inputs of the model), indexed by name */
+  values: Mutex<HashMap<String, Arc<Tensor>>>,
+  /* For each node, how many of its producers haven't finished yet. The node is dispatched when it reaches 0 */
+  pending_inputs: Vec<AtomicUsize>,
+  progress: Mutex<RunProgress>,
+  /* Notified when no node of the run is queued or executing anymore */
+  finished: Condvar,
+}
+
+struct RunProgress {
+  /* Nodes dispatched to the pool and not completed yet */
+  in_flight: usize,
+  /* First error raised by a node. Once set, no other node is dispatched */
+  failure: Option<InferenceError>,
+}
+
+impl Default for SessionOptions {
+  fn default() -> Self {
+    SessionOptions {
+      num_threads: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+      operators: Arc::new(OperatorRegistry::default()),
+    }
+  }
+}
+
+impl SessionOptions {
+  /* Deterministic mode: every node is executed by the thread calling run, in topological order */
+  pub fn single_threaded() -> SessionOptions {
+    SessionOptions { num_threads: 1, ..SessionOptions::default() }
+  }
+}
+
+impl InferenceSession {
+  /*
+  This function prepares the model for the inference, with one worker per available core
+    -It takes 1 parameter:
+      ~ model: struct that contains the onnx model
+    -It returns the session, or an error if the model can't be executed (i.e. undecodable initializer, cycle in the graph)
+  */
+  pub fn new(model: &ModelProto) -> Result<InferenceSession, InferenceError> {
+    InferenceSession::with_options(model, SessionOptions::default())
+  }
+
+  /*
+  This function prepares the model for the inference
+    -It takes 2 parameters:
+      ~ model: struct that contains the onnx model
+      ~ options: number of threads used by run
+    -It returns the session, or an error if the model can't be executed (i.e. undecodable initializer, cycle in the graph,
+    operator not supported in the imported opset)
+  */
+  pub fn with_options(model: &ModelProto, options: SessionOptions) -> Result<InferenceSession, InferenceError> {
+    let graph = match model.graph.as_ref() {
+      Some(graph) => graph,
+      None => return Err(InferenceError::MalformedModel("model without graph".to_string()))
+    };
+
+    let mut initializers = HashMap::new();
+    for init in &graph.initializer {
+      initializers.insert(init.name.clone().unwrap_or_default(), Arc::new(Tensor::from_proto(init)?));
+    }
+
+    let mut available: HashSet<String> = initializers.keys().cloned().collect();
+    available.extend(graph.input.iter().map(|inp| inp.name.clone().unwrap_or_default()));
+
+    let opsets = opset_imports(model)?;
+    let mut nodes = Vec::with_capacity(graph.node.len());
+    for node in topological_sort(&graph.node, available)? {
+      let kernel = options.operators.resolve(node, &opsets)?;
+      nodes.push(PreparedNode::new(node.clone(), kernel, &initializers)?);
+    }
+    let dependencies = DependencyGraph::new(&nodes);
+
+    Ok(InferenceSession {
+      graph: Arc::new(PreparedGraph { initializers, nodes, dependencies }),
+      graph_inputs: graph.input.clone(),
+      graph_outputs: graph.output.iter().map(|out| out.name.clone().unwrap_or_default()).collect(),
+      declared_outputs: graph.output.clone(),
+      pool: if options.num_threads > 1 { Some(ThreadPool::new(options.num_threads)) } else { None },
+    })
+  }
+
+  /* Declared inputs of the graph (initializers included, for models that list them as inputs) */
+  pub fn inputs(&self) -> &[ValueInfoProto] {
+    &self.graph_inputs
+  }
+
+  /* Names of the graph outputs, in declaration order */
+  pub fn outputs(&self) -> &[String] {
+    &self.graph_outputs
+  }
+
+  /* Number of threads executing the nodes (1 in single thread mode) */
+  pub fn num_threads(&self) -> usize {
+    self.pool.as_ref().map(|pool| pool.num_threads()).unwrap_or(1)
+  }
+
+  /*
+  This function make the inference on the prepared model
+    -It takes 1 parameter:
+      ~ inputs: values of the model inputs, indexed by name
+    -It returns the graph outputs indexed by their name, or the error that stopped the inference.
+  */
+  pub fn run(&self, inputs: HashMap<String, Tensor>) -> Result<HashMap<String, Tensor>, InferenceError> {
+    let mut bindings = self.validate_inputs(&inputs)?;
+    let values: HashMap<String, Arc<Tensor>> = inputs.into_iter().map(|(name, value)| (name, Arc::new(value))).collect();
+
+    let values = match &self.pool {
+      Some(pool) => self.run_parallel(pool.handle(), values)?,
+      None => self.run_sequential(values)?
+    };
+    self.check_declared_outputs(&values, &mut bindings)?;
+
+    let mut outputs = HashMap::new();
+    for output_name in &self.graph_outputs {
+      match values.get(output_name).or(self.graph.initializers.get(output_name)) {
+        Some(value) => { outputs.insert(output_name.clone(), value.as_ref().clone()); }
+        None => return Err(InferenceError::MalformedModel(format!("graph output '{}' is not produced by any node", output_name)))
+      }
+    }
+
+    Ok(outputs)
+  }
+
+  /*
+  This function checks the values given to run against the declared inputs of the graph
+    -It takes 1 parameter:
+      ~ inputs: values of the model inputs, indexed by name
+    -It returns the sizes bound to the symbolic dimensions (i.e. batch_size), or InvalidInput if a value isn't a graph input,
+    doesn't match its declaration or is missing (inputs that are initializers as well can be omitted: the initializer is their default value)
+  */
+  fn validate_inputs(&self, inputs: &HashMap<String, Tensor>) -> Result<HashMap<String, usize>, InferenceError> {
+    let mut bindings = HashMap::new();
+    for name in inputs.keys() {
+      if !self.graph_inputs.iter().any(|inp| inp.name.as_ref() == Some(name)) {
+        return Err(InferenceError::InvalidInput { input: name.clone(), message: "not an input of the graph".to_string() });
+      }
+    }
+
+    for declared in &self.graph_inputs {
+      let name = declared.name.clone().unwrap_or_default();
+      match inputs.get(&name) {
+        Some(value) => TensorType::from_value_info(declared)?.check(value, &mut bindings)
+          .map_err(|message| InferenceError::InvalidInput { input: name.clone(), message })?,
+        None if self.graph.initializers.contains_key(&name) => {}
+        None => return Err(InferenceError::InvalidInput { input: name, message: "value not provided".to_string() })
+      }
+    }
+    Ok(bindings)
+  }
+
+  /*
+  This function checks the graph outputs calculated by the run against their declarations, with the symbolic dimensions
+  bound by the inputs (so an output declared as [batch_size, 10] must have the batch size of the input)
+    -It takes 2 parameters:
+      ~ values: values calculated by the run
+      ~ bindings: sizes bound to the symbolic dimensions, extended with the dimensions appearing only in the outputs
+    -It returns MalformedModel if an output doesn't match its declaration
+  */
+  fn check_declared_outputs(&self, values: &HashMap<String, Arc<Tensor>>, bindings: &mut HashMap<String, usize>) -> Result<(), InferenceError> {
+    for declared in &self.declared_outputs {
+      let name = declared.name.clone().unwrap_or_default();
+      /* Outputs without a tensor type (or not calculated, i.e. outputs that are initializers) aren't checked */
+      if let (Some(value), Ok(declared_type)) = (values.get(&name), TensorType::from_value_info(declared)) {
+        declared_type.check(value, bindings)
+          .map_err(|message| InferenceError::MalformedModel(format!("output {} doesn't match its declaration: {}", name, message)))?;
+      }
+    }
+    Ok(())
+  }
+
+  /*
+  This function executes the nodes one by one, in topological order, on the calling thread
+    -It takes 1 parameter:
+      ~ values: inputs of the model
+    -It returns all the values calculated by the nodes
+  */
+  fn run_sequential(&self, values: HashMap<String, Arc<Tensor>>) -> Result<HashMap<String, Arc<Tensor>>, InferenceError> {
+    let values = Mutex::new(values);
+    for node in &self.graph.nodes {
+      execute_node_catching_panics(node, &self.graph.initializers, &values)?;
+    }
+    Ok(values.into_inner().unwrap())
+  }
+
+  /*
+  This function executes the nodes on the workers: the nodes without dependencies are dispatched immediately,
+  the others as soon as their last producer is done. The calling thread waits for the end of the run.
+    -It takes 2 parameters:
+      ~ pool: handle of the workers
+      ~ values: inputs of the model
+    -It returns all the values calculated by the nodes, or the first error raised by a node
+  */
+  fn run_parallel(&self, pool: PoolHandle, values: HashMap<String, Arc<Tensor>>) -> Result<HashMap<String, Arc<Tensor>>, InferenceError> {
+    let context = Arc::new(RunContext {
+      graph: self.graph.clone(),
+      pool,
+      values: Mutex::new(values),
+      pending_inputs: self.graph.dependencies.pending_inputs.iter().map(|&n| AtomicUsize::new(n)).collect(),
+      progress: Mutex::new(RunProgress { in_flight: 0, failure: None }),
+      finished: Condvar::new(),
+    });
+
+    for (position, &pending) in self.graph.dependencies.pending_inputs.iter().enumerate() {
+      if pending == 0 {
+        dispatch(&context, position);
+      }
+    }
+
+    let mut progress = context.progress.lock().unwrap();
+    while progress.in_flight > 0 {
+      progress = context.finished.wait(progress).unwrap();
+    }
+    if let Some(e) = progress.failure.take() {
+      return Err(e);
+    }
+    drop(progress);
+
+    let values = context.values.lock().unwrap();
+    Ok(values.clone())
+  }
+}
+
+/*
+This function queues the node on the workers
+  -It takes 2 parameters:
+    ~ context: state of the run
+    ~ position: position of the node in the topological order
+*/
+fn dispatch(context: &Arc<RunContext>, position: usize) {
+  context.progress.lock().unwrap().in_flight += 1;
+
+  let t_context = context.clone();
+  context.pool.execute(move || run_node_job(t_context, position));
+}
+
+/*
+This function is the job executed by a worker: it runs the node and dispatches the consumers that became ready
+  -It takes 2 parameters:
+    ~ context: state of the run
+    ~ position: position of the node in the topological order
+*/
+fn run_node_job(context: Arc<RunContext>, position: usize) {
+  let node = &context.graph.nodes[position];
+  /* A panic in an operation must not leave the run waiting forever */
+  let result = execute_node_catching_panics(node, &context.graph.initializers, &context.values);
+
+  match result {
+    Ok(()) => {
+      for &consumer in &context.graph.dependencies.consumers[position] {
+        if context.pending_inputs[consumer].fetch_sub(1, Ordering::AcqRel) == 1 && context.progress.lock().unwrap().failure.is_none() {
+          dispatch(&context, consumer);
+        }
+      }
+    }
+    Err(e) => { context.progress.lock().unwrap().failure.get_or_insert(e); }
+  }
+
+  /* The consumers are dispatched before this decrement, so in_flight reaches 0 only at the end of the run */
+  let mut progress = context.progress.lock().unwrap();
+  progress.in_flight -= 1;
+  if progress.in_flight == 0 {
+    context.finished.notify_all();
+  }
+}
+
+/*
+This function is execute_node, with a panic of the operation turned into an error, so that it doesn't unwind into the
+caller (i.e. the python interpreter) or leave the workers waiting for the node
+*/
+fn execute_node_catching_panics(node: &PreparedNode, initializers: &HashMap<String, Arc<Tensor>>, values: &Mutex<HashMap<String, Arc<Tensor>>>) -> Result<(), InferenceError> {
+  catch_unwind(AssertUnwindSafe(|| execute_node(node, initializers, values)))
+    .unwrap_or_else(|_| Err(InferenceError::MalformedModel(format!("operation {} of node '{}' panicked", node.op_type(), crate::inference_error::node_name(&node.proto)))))
+}
+
+/*
+This function gathers the inputs of the node, executes it and stores its outputs
+  -It takes 3 parameters:
+    ~ node: node to execute
+    ~ initializers: decoded initializers of the model
+    ~ values: values calculated so far in the run
+*/
+fn execute_node(node: &PreparedNode, initializers: &HashMap<String, Arc<Tensor>>, values: &Mutex<HashMap<String, Arc<Tensor>>>) -> Result<(), InferenceError> {
+  let map = values.lock().unwrap();
+  let mut inputs: Vec<Option<Arc<Tensor>>> = Vec::with_capacity(node.proto.input.len());
+  for input in &node.proto.input {
+    if input.is_empty() {
+      /* Optional input not provided */
+      inputs.push(None);
+    } else {
+      match map.get(input).or(initializers.get(input)) {
+        Some(value) => inputs.push(Some(value.clone())),
+        None => return Err(InferenceError::missing_input(&node.proto, input))
+      }
+    }
+  }
+  drop(map); /* The other workers can read and store values while the node is executed */
+
+  let input_refs: Vec<Option<&Tensor>> = inputs.iter().map(|inp| inp.as_deref()).collect();
+  let outputs = node_inference(node, &input_refs)?;
+
+  let mut map_mut = values.lock().unwrap();
+  for (name, value) in node.proto.output.iter().zip(outputs) {
+    if !name.is_empty() {
+      map_mut.insert(name.clone(), Arc::new(value));
+    }
+  }
+  Ok(())
+}
+
+impl PreparedNode {
+  /*
+  This function resolves the attributes of the node and builds the layers depending only on initializers
+    -It takes 3 parameters:
+      ~ proto: node of the onnx model
+      ~ kernel: implementation of the operation
+      ~ initializers: decoded initializers of the onnx model
+  */
+  fn new(proto: NodeProto, kernel: Arc<dyn OpKernel>, initializers: &HashMap<String, Arc<Tensor>>) -> Result<PreparedNode, InferenceError> {
+    let attributes = NodeAttributes::new(&proto.attribute);
+
+    /* Custom operators may reuse the names of the standard ones */
+    let standard = normalize_domain(proto.domain.as_deref().unwrap_or_default()) == DEFAULT_DOMAIN;
+    let prepared_op = match proto.op_type.as_deref().filter(|_| standard) {
+      Some(op @ ("Conv" | "ConvTranspose")) => {
+        let kernel = proto.input.get(1).and_then(|name| initializers.get(name));
+        let bias = proto.input.get(2).map(|name| initializers.get(name));
+        match (kernel, bias) {
+          /* The layer can be built only once the kernel is known, the bias (if present) must be known as well */
+          (Some(kernel), None) | (Some(kernel), Some(Some(_))) => {
+            let bias = bias.flatten().map(|b| b.as_ref());
+            if op == "Conv" {
+              PreparedOp::Conv(build_conv_layer(&proto, &attributes, kernel, bias)?)
+            } else {
+              PreparedOp::ConvTranspose(build_conv_transpose_layer(&proto, &attributes, kernel, bias)?)
+            }
+          }
+          _ => PreparedOp::None
+        }
+      }
+      Some("MaxPool") | Some("AveragePool") | Some("LpPool") => PreparedOp::Pool(build_pool_layer(&proto, &attributes)?),
+      _ => PreparedOp::None
+    };
+
+    Ok(PreparedNode { proto, kernel, attributes, prepared_op })
+  }
+
+  /* Operation type of the node */
+  pub fn op_type(&self) -> &str {
+    self.proto.op_type.as_deref().unwrap_or_default()
+  }
+
+  /* Node of the onnx model (name, inputs and outputs) */
+  pub fn proto(&self) -> &NodeProto {
+    &self.proto
+  }
+
+  /* Attributes of the node indexed by their name */
+  pub fn attributes(&self) -> &NodeAttributes {
+    &self.attributes
+  }
+}
+
+impl NodeAttributes {
+  pub fn new(attributes: &[AttributeProto]) -> NodeAttributes {
+    NodeAttributes {
+      attributes: attributes.iter().map(|attr| (attr.name.clone().unwrap_or_default(), attr.clone())).collect()
+    }
+  }
+
+  pub fn get(&self, name: &str) -> Option<&AttributeProto> {
+    self.attributes.get(name)
+  }
+
+  pub fn names(&self) -> impl Iterator<Item = &String> {
+    self.attributes.keys()
+  }
+
+  pub fn int(&self, name: &str) -> Option<i64> {
+    self.get(name).and_then(|attr| attr.i)
+  }
+
+  pub fn ints(&self, name: &str) -> Option<&[i64]> {
+    self.get(name).map(|attr| attr.ints.as_slice())
+  }
+
+  pub fn float(&self, name: &str) -> Option<f32> {
+    self.get(name).and_then(|attr| attr.f)
+  }
+
+  pub fn floats(&self, name: &str) -> Option<&[f32]> {
+    self.get(name).map(|attr| attr.floats.as_slice())
+  }
+
+  pub fn string(&self, name: &str) -> Option<&str> {
+    self.get(name).and_then(|attr| attr.s.as_ref()).and_then(|s| std::str::from_utf8(s).ok())
+  }
+}
+
+/*
+This function sorts the nodes so that each one comes after the nodes producing its inputs (ONNX requires it, but not every exporter does it).
+Among the nodes ready at the same time the original order is kept.
+  -It takes 2 parameters:
+    ~ nodes: nodes of the graph
+    ~ available: names of the values available before any node runs (graph inputs and initializers)
+  -It returns the sorted nodes, or an error if some node can never run (cycle or undefined input)
+*/
+fn topological_sort(nodes: &[NodeProto], mut available: HashSet<String>) -> Result<Vec<&NodeProto>, InferenceError> {
+  let mut sorted: Vec<&NodeProto> = Vec::with_capacity(nodes.len());
+  let mut done = vec![false; nodes.len()];
+
+  while sorted.len() < nodes.len() {
+    let mut progress = false;
+    for (i, node) in nodes.iter().enumerate() {
+      if !done[i] && node.input.iter().all(|input| input.is_empty() || available.contains(input)) {
+        done[i] = true;
+        progress = true;
+        available.extend(node.output.iter().cloned());
+        sorted.push(node);
+      }
+    }
+
+    if !progress {
+      let (blocked, _) = nodes.iter().enumerate().find(|(i, _)| !done[*i]).unwrap();
+      let blocked = &nodes[blocked];
+      let missing = blocked.input.iter().find(|input| !input.is_empty() && !available.contains(*input)).cloned().unwrap_or_default();
+      return Err(InferenceError::MalformedModel(format!("node '{}' can never run: input '{}' is not produced by any node (or the graph has a cycle)", crate::inference_error::node_name(blocked), missing)));
+    }
+  }
+
+  Ok(sorted)
+}
+
+#[allow(dead_code)]
+pub fn test_session() {
+  use ndarray::Array;
+  use crate::onnx_structure::tensor_proto::DataType;
+  use crate::tensor::Dim;
+  use crate::test_graph::{model, node, value_info};
+
+  let batch = |columns: usize| Some(vec![Dim::Param("N".to_string()), Dim::Value(columns)]);
+  let input = |rows: usize, columns: usize| {
+    Tensor::F32(Array::from_shape_fn((rows, columns), |(row, column)| row as f32 - column as f32).into_dyn())
+  };
+  // x [N, 3] -> Relu -> h -> Relu -> y [N, 3], with a value_info for h that doesn't match (it's only a hint)
+  let build = |output_columns: usize| {
+    let mut model = model(
+      vec![node("Relu", &["x"], &["h"], vec![]), node("Relu", &["h"], &["y"], vec![])],
+      vec![value_info("x", DataType::FLOAT, batch(3))],
+      vec![value_info("y", DataType::FLOAT, batch(output_columns))],
+      vec![],
+      14,
+    );
+    model.graph.mut_or_insert_default().value_info.push(value_info("h", DataType::FLOAT, batch(7)));
+    model
+  };
+
+  let session = InferenceSession::new(&build(3)).unwrap();
+  for rows in [2, 3] {
+    let outputs = session.run(HashMap::from([("x".to_string(), input(rows, 3))]));
+    println!("N = {}: {:?}", rows, outputs.map(|outputs| outputs["y"].shape().to_vec()));
+    println!("expected: Ok([{}, 3])", rows);
+  }
+
+  let outputs = session.run(HashMap::from([("x".to_string(), input(2, 4))]));
+  println!("wrong input: {:?}", outputs.map(|outputs| outputs["y"].shape().to_vec()));
+  println!("expected: InvalidInput (x has 4 columns instead of 3)");
+
+  let outputs = InferenceSession::new(&build(5)).unwrap().run(HashMap::from([("x".to_string(), input(2, 3))]));
+  println!("wrong output declaration: {:?}", outputs.map(|outputs| outputs["y"].shape().to_vec()));
+  println!("expected: MalformedModel (y has 3 columns instead of 5)");
+}
+
+#[allow(dead_code)]
+pub fn test_parallel_run() {
+  use ndarray::Array;
+  use crate::onnx_structure::OperatorSetIdProto;
+  use crate::onnx_structure::tensor_proto::DataType;
+  use crate::test_graph::{model, node, value_info};
+
+  /* Custom operation that panics, to check that a panic of a worker becomes an error of the run */
+  struct Panic;
+  impl OpKernel for Panic {
+    fn compute(&self, _node: &PreparedNode, _inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+      panic!("the kernel panicked")
+    }
+  }
+
+  // Fan-out of x into four branches, joined two by two and then into y
+  let fan_out_fan_in = |last: NodeProto| model(
+    vec![
+      node("Relu", &["x"], &["a"], vec![]),
+      node("Neg", &["x"], &["b"], vec![]),
+      node("Abs", &["x"], &["c"], vec![]),
+      node("Sigmoid", &["x"], &["d"], vec![]),
+      node("Add", &["a", "b"], &["e"], vec![]),
+      last,
+      node("Add", &["e", "f"], &["y"], vec![]),
+    ],
+    vec![value_info("x", DataType::FLOAT, None), value_info("z", DataType::FLOAT, None)],
+    vec![value_info("y", DataType::FLOAT, None)],
+    vec![],
+    13,
+  );
+  let inputs = |z: Vec<f32>| HashMap::from([
+    ("x".to_string(), Tensor::F32(Array::from_shape_vec((2, 3), vec![-3., -2., -1., 1., 2., 3.]).unwrap().into_dyn())),
+    ("z".to_string(), Tensor::F32(Array::from_vec(z).into_dyn())),
+  ]);
+  let run = |model: &ModelProto, num_threads: usize, operators: Arc<OperatorRegistry>, inputs: HashMap<String, Tensor>| {
+    InferenceSession::with_options(model, SessionOptions { num_threads, operators }).unwrap().run(inputs)
+  };
+
+  let mut operators = OperatorRegistry::default();
+  operators.register_kernel("com.example", "Panic", 1, Panic);
+  let operators = Arc::new(operators);
+
+  let valid = fan_out_fan_in(node("Mul", &["c", "d"], &["f"], vec![]));
+  let sequential = run(&valid, 1, operators.clone(), inputs(vec![1., 1., 1.]));
+  let parallel = run(&valid, 4, operators.clone(), inputs(vec![1., 1., 1.]));
+  println!("sequential: {:?}", sequential);
+  println!("parallel: {:?}", parallel);
+  println!("same outputs: {}", sequential.is_ok() && sequential == parallel);
+  println!("expected: y = relu(x) - x + |x| * sigmoid(x) = [[3.1423, 2.2384, 1.2689], [0.7311, 1.7616, 2.8577]], same outputs: true");
+
+  // z has 2 elements, so f = c * z can't be broadcast
+  let failing = fan_out_fan_in(node("Mul", &["c", "z"], &["f"], vec![]));
+  for num_threads in [1, 4] {
+    println!("error with {} thread(s): {:?}", num_threads, run(&failing, num_threads, operators.clone(), inputs(vec![1., 1.])));
+  }
+  println!("expected: ShapeMismatch in node 'f' for both");
+
+  // The panic message is still printed by the default hook, the run must end with an error anyway
+  let mut panicking = node("Panic", &["c", "d"], &["f"], vec![]);
+  panicking.domain = Some("com.example".to_string());
+  let mut panicking = fan_out_fan_in(panicking);
+  let mut opset = OperatorSetIdProto::new();
+  opset.domain = Some("com.example".to_string());
+  opset.version = Some(1);
+  panicking.opset_import.push(opset);
+  for num_threads in [1, 4] {
+    println!("panic with {} thread(s): {:?}", num_threads, run(&panicking, num_threads, operators.clone(), inputs(vec![1., 1., 1.])));
+  }
+  println!("expected: MalformedModel (operation Panic of node 'f' panicked) for both");
+}
@@ -0,0 +1,113 @@
+use ndarray::{Array, Array2, ArrayD, Axis, IxDyn, s};
+use num_traits::Float;
+
+/* Outputs of the layer normalization: y, the mean and the inverse of the standard deviation */
+pub type LayerNormalizationOutput<F> = (ArrayD<F>, ArrayD<F>, ArrayD<F>);
+/* Input reshaped into rows, with scale and bias flattened to the length of a row */
+type SplitRows<F> = (Array2<F>, Vec<F>, Option<Vec<F>>);
+
+//OPSET VERSION = 17
+//The input is split into the dimensions before axis (independent rows) and the dimensions from axis on (normalized together):
+//y = (x - mean) / sqrt(var + epsilon) * scale + bias, where scale and bias are broadcast to the normalized dimensions.
+//It returns y, the mean and the inverse of the standard deviation (both with the shape of x and 1 from axis on), all
+//with the element type of x
+pub fn layer_normalization<F: Float>(x: &ArrayD<F>, scale: &ArrayD<F>, bias: Option<&ArrayD<F>>, axis: usize, epsilon: f32) -> Result<LayerNormalizationOutput<F>, String> {
+  let epsilon = F::from(epsilon).unwrap();
+  let (rows, scale, bias) = split_rows(x, scale, bias, axis)?;
+
+  let mut y = rows;
+  let mut mean = Vec::with_capacity(y.nrows());
+  let mut inv_std_dev = Vec::with_capacity(y.nrows());
+  for mut row in y.axis_iter_mut(Axis(0)) {
+    let size = F::from(row.len()).unwrap();
+    let row_mean = row.sum() / size;
+    let var = row.fold(F::zero(), |acc, &v| acc + (v - row_mean) * (v - row_mean)) / size;
+    let row_inv_std_dev = (var + epsilon).sqrt().recip();
+    for (i, v) in row.iter_mut().enumerate() {
+      *v = (*v - row_mean) * row_inv_std_dev * scale[i] + bias.as_ref().map(|b| b[i]).unwrap_or(F::zero());
+    }
+    mean.push(row_mean);
+    inv_std_dev.push(row_inv_std_dev);
+  }
+
+  let statistics_shape = statistics_shape(x.shape(), axis);
+  Ok((
+    y.into_shape(IxDyn(x.shape())).unwrap(),
+    ArrayD::from_shape_vec(IxDyn(&statistics_shape), mean).unwrap(),
+    ArrayD::from_shape_vec(IxDyn(&statistics_shape), inv_std_dev).unwrap(),
+  ))
+}
+
+//OPSET VERSION = 23
+//Like the layer normalization but without centering: y = x / sqrt(mean(x^2) + epsilon) * scale
+pub fn rms_normalization<F: Float>(x: &ArrayD<F>, scale: &ArrayD<F>, axis: usize, epsilon: f32) -> Result<ArrayD<F>, String> {
+  let epsilon = F::from(epsilon).unwrap();
+  let (rows, scale, _) = split_rows(x, scale, None, axis)?;
+
+  let mut y = rows;
+  for mut row in y.axis_iter_mut(Axis(0)) {
+    let mean_square = row.fold(F::zero(), |acc, &v| acc + v * v) / F::from(row.len()).unwrap();
+    let inv_rms = (mean_square + epsilon).sqrt().recip();
+    for (i, v) in row.iter_mut().enumerate() {
+      *v = *v * inv_rms * scale[i];
+    }
+  }
+  Ok(y.into_shape(IxDyn(x.shape())).unwrap())
+}
+
+/*
+This function reshapes the input into a matrix with one row for each independent group of values and flattens scale and bias
+to the length of a row
+  -It returns an error if axis is out of range or scale/bias can't be broadcast to the normalized dimensions
+*/
+fn split_rows<F: Float>(x: &ArrayD<F>, scale: &ArrayD<F>, bias: Option<&ArrayD<F>>, axis: usize) -> Result<SplitRows<F>, String> {
+  if axis >= x.ndim() {
+    return Err(format!("axis {} out of range for input {:?}", axis, x.shape()));
+  }
+  let normalized_shape = &x.shape()[axis..];
+  let row_size: usize = normalized_shape.iter().product();
+  let rows = x.to_shape((x.len() / row_size.max(1), row_size)).unwrap().into_owned();
+
+  let flatten = |name: &str, parameter: &ArrayD<F>| -> Result<Vec<F>, String> {
+    match parameter.broadcast(IxDyn(normalized_shape)) {
+      Some(p) => Ok(p.iter().copied().collect()),
+      None => Err(format!("{} {:?} cannot be broadcast to the normalized dimensions {:?}", name, parameter.shape(), normalized_shape))
+    }
+  };
+  let scale = flatten("scale", scale)?;
+  let bias = match bias {
+    Some(b) => Some(flatten("B", b)?),
+    None => None
+  };
+  Ok((rows, scale, bias))
+}
+
+/* Shape of the mean and of the standard deviation: the one of the input with 1 from axis on */
+fn statistics_shape(shape: &[usize], axis: usize) -> Vec<usize> {
+  shape.iter().enumerate().map(|(d, &size)| if d < axis { size } else { 1 }).collect()
+}
+
+#[allow(dead_code)]
+pub fn test_layer_normalization() {
+  // Rows [1, 2, 3] and [2, 4, 6]: mean 2 and 4, variance 2/3 and 8/3
+  let x = Array::from_shape_vec((2, 3), vec![1., 2., 3., 2., 4., 6.]).unwrap().into_dyn();
+  let scale = Array::from_shape_vec(3, vec![1., 1., 2.]).unwrap().into_dyn();
+  let bias = Array::from_shape_vec(3, vec![0., 1., 0.]).unwrap().into_dyn();
+  let (y, mean, inv_std_dev) = layer_normalization(&x, &scale, Some(&bias), 1, 0.).unwrap();
+  println!("layer normalization: {:?}", y);
+  println!("expected: [[-1.2247, 1, 2.4495], [-1.2247, 1, 2.4495]]");
+  println!("mean: {:?}, inverse std dev: {:?}", mean, inv_std_dev);
+  println!("expected: [[2], [4]], [[1.2247], [0.6124]]");
+
+  // axis = 0: the whole tensor is normalized together, scale is broadcast from a scalar
+  let (y, _, _) = layer_normalization(&x, &ArrayD::from_elem(IxDyn(&[]), 1.), None, 0, 0.).unwrap();
+  println!("layer normalization axis 0: {:?}", y);
+  println!("expected: [[-1.2247, -0.6124, 0], [-0.6124, 0.6124, 1.8371]]");
+
+  // RMS: rows have mean square 14/3 and 56/3
+  println!("rms normalization: {:?}", rms_normalization(&x, &ArrayD::from_elem(IxDyn(&[]), 1.), 1, 0.));
+  println!("expected: [[0.4629, 0.9258, 1.3887], [0.4629, 0.9258, 1.3887]]");
+
+  // scale with the wrong length
+  println!("layer normalization wrong: {:?}", layer_normalization(&x, &bias.slice_move(s![..2]).into_dyn(), None, 1, 0.));
+}
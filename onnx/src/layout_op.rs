@@ -0,0 +1,153 @@
+use ndarray::{Array, ArrayD, Axis, concatenate, IxDyn, Slice};
+use crate::tensor::{broadcast_shapes, map_each_variant, Tensor};
+
+//OPSET VERSION = 13
+//Operations that move or repeat the elements of a tensor, keeping their element type
+
+/*
+This function executes the Transpose
+  -It takes 2 parameters:
+    ~ x: input
+    ~ perm: dimension of the input that becomes the i-th dimension of the output (the reversed dimensions if None)
+  -It returns the result, or an error if perm isn't a permutation of the dimensions
+*/
+pub fn transpose(x: &Tensor, perm: Option<&[usize]>) -> Result<Tensor, String> {
+  let perm: Vec<usize> = match perm {
+    Some(perm) => perm.to_vec(),
+    None => (0..x.rank()).rev().collect()
+  };
+  let mut sorted = perm.clone();
+  sorted.sort_unstable();
+  if sorted != (0..x.rank()).collect::<Vec<usize>>() {
+    return Err(format!("perm {:?} is not a permutation of the dimensions of {:?}", perm, x.shape()));
+  }
+  Ok(map_each_variant!(x, arr => arr.view().permuted_axes(IxDyn(&perm)).as_standard_layout().into_owned()))
+}
+
+/*
+This function executes the Expand: the input is broadcast (numpy style) with the requested shape
+  -It takes 2 parameters:
+    ~ x: input
+    ~ shape: requested shape (a dimension of size 1 keeps the one of the input)
+  -It returns the result, with the broadcast shape, or an error if the shapes aren't compatible
+*/
+pub fn expand(x: &Tensor, shape: &[i64]) -> Result<Tensor, String> {
+  if shape.iter().any(|&dim| dim < 0) {
+    return Err(format!("invalid shape {:?}", shape));
+  }
+  let shape: Vec<usize> = shape.iter().map(|&dim| dim as usize).collect();
+  let output_shape = match broadcast_shapes(x.shape(), &shape) {
+    Some(output_shape) => output_shape,
+    None => return Err(format!("cannot broadcast {:?} with {:?}", x.shape(), shape))
+  };
+  Ok(map_each_variant!(x, arr => arr.broadcast(IxDyn(&output_shape)).unwrap().to_owned()))
+}
+
+/*
+This function executes the Tile: the input is repeated along every dimension
+  -It takes 2 parameters:
+    ~ x: input
+    ~ repeats: number of copies along each dimension
+  -It returns the result, or an error if repeats hasn't one non negative value for each dimension
+*/
+pub fn tile(x: &Tensor, repeats: &[i64]) -> Result<Tensor, String> {
+  if repeats.len() != x.rank() || repeats.iter().any(|&r| r < 0) {
+    return Err(format!("repeats {:?} is not valid for {:?}", repeats, x.shape()));
+  }
+  let repeats: Vec<usize> = repeats.iter().map(|&r| r as usize).collect();
+  Ok(map_each_variant!(x, arr => tile_array(arr, &repeats)))
+}
+
+fn tile_array<T: Clone>(x: &ArrayD<T>, repeats: &[usize]) -> ArrayD<T> {
+  let mut output = x.clone();
+  for (axis, &r) in repeats.iter().enumerate() {
+    output = if r == 0 {
+      output.slice_axis(Axis(axis), Slice::from(0..0)).to_owned()
+    } else {
+      concatenate(Axis(axis), &vec![output.view(); r]).unwrap()
+    };
+  }
+  output
+}
+
+/* Joins the arrays of the inputs, which all hold the same variant (the one of the first input) */
+macro_rules! concat_variants {
+  ($inputs:expr, $axis:expr, $($variant:ident),*) => {
+    match $inputs[0] {
+      $(
+        Tensor::$variant(_) => {
+          let views: Vec<_> = $inputs.iter().filter_map(|x| match x {
+            Tensor::$variant(arr) => Some(arr.view()),
+            _ => None
+          }).collect();
+          Tensor::$variant(concatenate(Axis($axis), &views).unwrap())
+        }
+      )*
+    }
+  };
+}
+
+/*
+This function executes the Concat
+  -It takes 2 parameters:
+    ~ inputs: tensors to join, with the same element type and the same shape except along axis
+    ~ axis: dimension along which the tensors are joined, in [0, rank - 1]
+  -It returns the result, or an error if there are no inputs or they aren't compatible
+*/
+pub fn concat(inputs: &[&Tensor], axis: usize) -> Result<Tensor, String> {
+  let first = match inputs.first() {
+    Some(first) => *first,
+    None => return Err("Concat needs at least one input".to_string())
+  };
+  for x in &inputs[1..] {
+    if x.data_type() != first.data_type() {
+      return Err(format!("cannot concatenate {:?} and {:?}", first.data_type(), x.data_type()));
+    }
+    let compatible = x.rank() == first.rank() && (0..first.rank()).all(|d| d == axis || x.shape()[d] == first.shape()[d]);
+    if !compatible {
+      return Err(format!("cannot concatenate {:?} and {:?} on axis {}", first.shape(), x.shape(), axis));
+    }
+  }
+  Ok(concat_variants!(inputs, axis, F32, F64, I64, I32, U8, I8, Bool, F16))
+}
+
+#[allow(dead_code)]
+pub fn test_layout() {
+  let x = Tensor::F32(Array::from_shape_vec((2, 3), vec![1., 2., 3., 4., 5., 6.]).unwrap().into_dyn());
+  println!("transpose: {:?}", transpose(&x, None));
+  println!("expected: [[1, 4], [2, 5], [3, 6]]");
+  let y = Tensor::I64(Array::from_shape_vec((1, 2, 3), (0..6).collect()).unwrap().into_dyn());
+  println!("transpose perm [2, 0, 1]: {:?}", transpose(&y, Some(&[2, 0, 1])));
+  println!("expected: [[[0, 3]], [[1, 4]], [[2, 5]]]");
+  println!("transpose perm [0, 0, 1]: {:?}", transpose(&y, Some(&[0, 0, 1])));
+  println!("expected: error");
+
+  // Expand of a column with a shape of lower rank, and with a shape of higher rank
+  let column = Tensor::F32(Array::from_shape_vec((3, 1), vec![1., 2., 3.]).unwrap().into_dyn());
+  println!("expand [1, 2]: {:?}", expand(&column, &[1, 2]));
+  println!("expected: [[1, 1], [2, 2], [3, 3]]");
+  println!("expand [2, 1, 4]: {:?}", expand(&column, &[2, 1, 4]).map(|t| t.shape().to_vec()));
+  println!("expected: [2, 3, 4]");
+  println!("expand [2, 2]: {:?}", expand(&column, &[2, 2]));
+  println!("expected: error");
+
+  let z = Tensor::I32(Array::from_shape_vec((2, 2), vec![1, 2, 3, 4]).unwrap().into_dyn());
+  println!("tile [1, 2]: {:?}", tile(&z, &[1, 2]));
+  println!("expected: [[1, 2, 1, 2], [3, 4, 3, 4]]");
+  println!("tile [2, 0]: {:?}", tile(&z, &[2, 0]).map(|t| t.shape().to_vec()));
+  println!("expected: [4, 0]");
+
+  // Concat of several inputs on the last axis, of int64 shapes and of incompatible inputs
+  let a = Tensor::F32(Array::from_shape_vec((2, 1), vec![1., 2.]).unwrap().into_dyn());
+  let b = Tensor::F32(Array::from_shape_vec((2, 2), vec![3., 4., 5., 6.]).unwrap().into_dyn());
+  println!("concat axis 1: {:?}", concat(&[&a, &b, &a], 1));
+  println!("expected: [[1, 3, 4, 1], [2, 5, 6, 2]]");
+  let shape = Tensor::I64(Array::from_vec(vec![1, 3]).into_dyn());
+  let empty = Tensor::I64(ArrayD::zeros(IxDyn(&[0])));
+  println!("concat int64 axis 0: {:?}", concat(&[&shape, &empty, &shape], 0));
+  println!("expected: [1, 3, 1, 3]");
+  println!("concat axis 0: {:?}", concat(&[&a, &b], 0));
+  println!("expected: error");
+  println!("concat float and int: {:?}", concat(&[&shape, &a], 0));
+  println!("expected: error");
+}
@@ -3,21 +3,49 @@ mod read_proto;
 mod read_onnx;
 mod write_onnx;
 mod convolution_op;
+mod conv_transpose_op;
+mod activation_op;
 mod relu_op;
 mod max_pool_op;
 mod dropout_op;
 mod global_average_pool_op;
+mod global_max_pool_op;
 mod softmax;
-mod model_inference;
+pub mod model_inference;
+pub mod tensor;
+mod test_graph;
+pub mod inference_error;
+pub mod inference_session;
+mod scheduler;
+pub mod shape_inference;
+pub mod checker;
+pub mod op_registry;
 mod reshape_op;
+mod layout_op;
+mod indexing_op;
+mod pad_op;
+mod gemm_op;
+mod matmul_op;
+mod binary_op;
+mod normalization_op;
+mod layer_normalization;
+mod group_normalization;
+mod reduce_op;
+mod unary_op;
 
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Read;
+use half::f16;
+use ndarray::{ArrayD, IxDyn};
 use pyo3::prelude::*;
+use pyo3::exceptions::{PyIOError, PyRuntimeError, PyTypeError, PyValueError};
+use pyo3::types::{PyDict, PyTuple};
 
 use crate::onnx_structure::{ModelProto, TensorProto};
 use crate::read_onnx::generate_onnx_model;
 use crate::model_inference::inference;
+use crate::tensor::Tensor;
 use protobuf::Message;
 
 /// A Python module implemented in Rust.
@@ -27,42 +55,138 @@ fn Group17(_py: Python, m: &PyModule) -> PyResult<()> {
   Ok(())
 }
 
+/*
+This function runs the model on the given inputs and gives its outputs to python
+  -It takes 3 parameters:
+    ~ onnx_file: path of the .onnx model
+    ~ inputs: value of each graph input, as a numpy array (or anything numpy.asarray accepts, with the dtype of the
+    declared input) or as the path of a serialized TensorProto
+    ~ output_path: optional path of a serialized TensorProto with the expected output, compared with the computed one
+  -It returns a dict with a numpy array for each graph output. It raises an exception if the inference fails or
+  the output differs from the expected one
+*/
 #[pyfunction]
-fn onnx_make_inference (onnx_file: String, input_path: &str, output_path: &str, input_tensor_name: Vec<&str>) {
+#[pyo3(signature = (onnx_file, inputs, output_path = None))]
+fn onnx_make_inference(py: Python<'_>, onnx_file: String, inputs: HashMap<String, &PyAny>, output_path: Option<&str>) -> PyResult<PyObject> {
   /* LIBRARY PARSING */
-  let onnx_bytes = std::fs::read(onnx_file.clone()).expect("Failed to read file");
-  let mut model = ModelProto::parse_from_bytes(&*onnx_bytes).expect("Failed to convert the file");
+  let onnx_bytes = std::fs::read(onnx_file.clone()).map_err(|e| PyIOError::new_err(format!("Failed to read file {}: {}", onnx_file, e)))?;
+  let model = ModelProto::parse_from_bytes(&*onnx_bytes).map_err(|e| PyRuntimeError::new_err(format!("Failed to convert the file: {}", e)))?;
 
   /* CUSTOM PARSING */
   //let mut model = generate_onnx_model(&onnx_file, "models/onnx.proto");
   //println!("{:?}", model);
 
-  let input_data = read_input_data(input_path).unwrap();
-  let output_data = read_input_data(output_path).unwrap();
+  /* Each input keeps its own shape and type */
+  let numpy = py.import("numpy")?;
+  let mut input_data = HashMap::new();
+  for (input_name, input_value) in inputs {
+    let tensor = match input_value.extract::<String>() {
+      Ok(input_path) => read_input_data(&input_path)?,
+      Err(_) => tensor_from_python(numpy, input_value)?
+    };
+    input_data.insert(input_name, tensor);
+  }
 
-  inference(model, input_data, input_tensor_name);
+  let outputs = inference(model, input_data).map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
 
-  println!("Expected Data: {:?}", output_data);
-}
+  if let Some(output_path) = output_path {
+    compare_output(&outputs, &read_input_data(output_path)?)?;
+  }
 
-fn read_input_data(input_path: &str) -> Option<Vec<f32>>{
-  let mut res: Option<Vec<f32>> = None;
+  let result = PyDict::new(py);
+  for (output_name, output_value) in &outputs {
+    result.set_item(output_name, tensor_to_python(py, numpy, output_value)?)?;
+  }
+  Ok(result.to_object(py))
+}
 
-  let mut file = File::open(input_path).expect("Cannot open input file");
+fn read_input_data(input_path: &str) -> PyResult<Tensor> {
+  let mut file = File::open(input_path).map_err(|e| PyIOError::new_err(format!("Cannot open input file {}: {}", input_path, e)))?;
 
   let mut buffer = Vec::new();
-  file.read_to_end(&mut buffer).expect("Error while reading file");
+  file.read_to_end(&mut buffer).map_err(|e| PyIOError::new_err(format!("Error while reading file {}: {}", input_path, e)))?;
+
+  let parsed_message = TensorProto::parse_from_bytes(&buffer).map_err(|e| PyRuntimeError::new_err(format!("Error while deserializing the message: {}", e)))?;
+
+  Tensor::from_proto(&parsed_message).map_err(|e| PyRuntimeError::new_err(format!("Error while decoding tensor {}: {}", input_path, e)))
+}
 
-  let parsed_message = TensorProto::parse_from_bytes(&buffer).expect("Error while deserializing the message");
+/*
+This function converts a python array into a tensor with the same shape and element type
+  -It takes 2 parameters:
+    ~ numpy: the numpy module
+    ~ value: the array (or a nested list, converted by numpy.asarray)
+  -It returns the tensor, or TypeError if the dtype has no tensor type
+*/
+fn tensor_from_python(numpy: &PyModule, value: &PyAny) -> PyResult<Tensor> {
+  let array = numpy.call_method1("asarray", (value,))?;
+  let shape: Vec<usize> = array.getattr("shape")?.extract()?;
+  let dtype: String = array.getattr("dtype")?.getattr("name")?.extract()?;
+  /* tolist gives the values in row-major order, whatever the memory layout of the array */
+  let values = array.call_method0("ravel")?.call_method0("tolist")?;
 
-  res = Some(parsed_message.raw_data.clone().unwrap().chunks_exact(4).map(|chunk| u8_to_f32(chunk)).collect());
+  Ok(match dtype.as_str() {
+    "float32" => Tensor::F32(array_from(&shape, values.extract()?)?),
+    "float64" => Tensor::F64(array_from(&shape, values.extract()?)?),
+    "float16" => Tensor::F16(array_from(&shape, values.extract::<Vec<f32>>()?.into_iter().map(f16::from_f32).collect())?),
+    "int64" => Tensor::I64(array_from(&shape, values.extract()?)?),
+    "int32" => Tensor::I32(array_from(&shape, values.extract()?)?),
+    "uint8" => Tensor::U8(array_from(&shape, values.extract()?)?),
+    "int8" => Tensor::I8(array_from(&shape, values.extract()?)?),
+    "bool" => Tensor::Bool(array_from(&shape, values.extract()?)?),
+    _ => return Err(PyTypeError::new_err(format!("arrays of {} are not supported", dtype)))
+  })
+}
+
+fn array_from<T>(shape: &[usize], values: Vec<T>) -> PyResult<ArrayD<T>> {
+  ArrayD::from_shape_vec(IxDyn(shape), values).map_err(|e| PyValueError::new_err(e.to_string()))
+}
 
-  res
+/*
+This function converts a tensor into a numpy array with the same shape and element type
+  -It takes 3 parameters:
+    ~ py: the python interpreter
+    ~ numpy: the numpy module
+    ~ tensor: the tensor
+  -It returns the numpy array
+*/
+fn tensor_to_python(py: Python<'_>, numpy: &PyModule, tensor: &Tensor) -> PyResult<PyObject> {
+  /* The values are given in row-major order, whatever the memory layout of the tensor */
+  let (values, dtype) = match tensor {
+    Tensor::F32(arr) => (arr.iter().copied().collect::<Vec<f32>>().to_object(py), "float32"),
+    Tensor::F64(arr) => (arr.iter().copied().collect::<Vec<f64>>().to_object(py), "float64"),
+    Tensor::F16(arr) => (arr.iter().map(|v| v.to_f32()).collect::<Vec<f32>>().to_object(py), "float16"),
+    Tensor::I64(arr) => (arr.iter().copied().collect::<Vec<i64>>().to_object(py), "int64"),
+    Tensor::I32(arr) => (arr.iter().copied().collect::<Vec<i32>>().to_object(py), "int32"),
+    Tensor::U8(arr) => (arr.iter().copied().collect::<Vec<u8>>().to_object(py), "uint8"),
+    Tensor::I8(arr) => (arr.iter().copied().collect::<Vec<i8>>().to_object(py), "int8"),
+    Tensor::Bool(arr) => (arr.iter().copied().collect::<Vec<bool>>().to_object(py), "bool"),
+  };
+  let array = numpy.call_method1("array", (values, dtype))?;
+  Ok(array.call_method1("reshape", (PyTuple::new(py, tensor.shape()),))?.to_object(py))
 }
 
-fn u8_to_f32(bytes: &[u8]) -> f32 {
-  assert_eq!(bytes.len(), 4);
-  let mut array: [u8; 4] = Default::default();
-  array.copy_from_slice(&bytes);
-  f32::from_le_bytes(array)
-}
\ No newline at end of file
+/*
+This function compares the computed output with the expected one (same shape, values equal up to a tolerance of
+1e-4 + 1e-3 * |expected|, enough for the rounding of float computations done in a different order)
+  -It takes 2 parameters:
+    ~ outputs: computed outputs of the model
+    ~ expected: expected value of the only output of the model
+  -It returns ValueError if the model hasn't a single output or it differs from the expected one
+*/
+fn compare_output(outputs: &HashMap<String, Tensor>, expected: &Tensor) -> PyResult<()> {
+  let computed = match outputs.values().next() {
+    Some(computed) if outputs.len() == 1 => computed,
+    _ => return Err(PyValueError::new_err(format!("the model has {} outputs, the expected output can be compared only with a single one", outputs.len())))
+  };
+  if computed.shape() != expected.shape() {
+    return Err(PyValueError::new_err(format!("the output has shape {:?}, expected {:?}", computed.shape(), expected.shape())));
+  }
+  let (computed, expected) = (computed.to_f32(), expected.to_f32());
+  let differing = computed.iter().zip(expected.iter()).position(|(&c, &e)| (c - e).abs() > 1e-4 + 1e-3 * e.abs());
+  if let Some(position) = differing {
+    let (c, e) = (computed.iter().nth(position).unwrap(), expected.iter().nth(position).unwrap());
+    return Err(PyValueError::new_err(format!("the output differs from the expected one at position {}: {} instead of {}", position, c, e)));
+  }
+  Ok(())
+}
@@ -2,44 +2,66 @@ pub mod onnx_structure;
 
 use std::io::{Read};
 use std::fs::{File};
+use std::collections::HashMap;
 use protobuf::{Message};
 
 mod read_proto;
 mod read_onnx;
 mod write_onnx;
 mod convolution_op;
+mod conv_transpose_op;
+mod activation_op;
 mod relu_op;
 mod max_pool_op;
 mod dropout_op;
 mod global_average_pool_op;
+mod global_max_pool_op;
 mod softmax;
 mod model_inference;
+mod inference_error;
+mod inference_session;
+mod scheduler;
+mod shape_inference;
+mod checker;
+mod op_registry;
 mod reshape_op;
+mod layout_op;
+mod indexing_op;
+mod pad_op;
+mod gemm_op;
+mod matmul_op;
+mod binary_op;
+mod normalization_op;
+mod layer_normalization;
+mod group_normalization;
+mod reduce_op;
+mod unary_op;
+mod tensor;
+mod test_graph;
 
 use crate::read_onnx::generate_onnx_model;
 use crate::model_inference::inference;
+use crate::tensor::Tensor;
 use crate::onnx_structure::{ModelProto, NodeProto, TensorProto};
 use crate::write_onnx::generate_onnx_file;
 
 fn main() {
   //MNIST-8
   let mut onnx_file = String::from("models/mnist-8.onnx");
-  let input_path = "mnist_data_0.pb"; //image gathered from the mnist repo
+  let inputs = vec![("Input3", "mnist_data_0.pb")]; //image gathered from the mnist repo
   let output_path = "mnist_output_0.pb"; //output gathered from the mnist repo
-  let input_tensor_name = vec!["Input3", "Parameter193"];
 
   //SQUEEZENET1.0-8
   /*let mut onnx_file = String::from("models/squeezenet1.0-8.onnx");
-  let input_path = "squeezenet_data_0.pb"; //image gathered from the squeezenet repo
-  let output_path = "squeezenet_output_0.pb"; //output gathered from the squeezenet repo
-  let input_tensor_name = vec!["data_0"];*/
+  let inputs = vec![("data_0", "squeezenet_data_0.pb")]; //image gathered from the squeezenet repo
+  let output_path = "squeezenet_output_0.pb"; //output gathered from the squeezenet repo*/
 
-  read_and_make_inference(onnx_file, input_path, output_path, input_tensor_name);
-  //read_and_write(onnx_file, input_path, output_path, input_tensor_name);
-  //read_modify_write(onnx_file, input_path, output_path, input_tensor_name);
+  read_and_make_inference(onnx_file, inputs, output_path);
+  //read_and_write(onnx_file, inputs, output_path);
+  //read_modify_write(onnx_file, inputs, output_path);
 }
 
-fn read_and_make_inference(onnx_file: String, input_path: &str, output_path: &str, input_tensor_name: Vec<&str>) {
+fn read_and_make_inference(onnx_file: String, inputs: Vec<(&str, &str)>, output_path: &str) {
   /*Library parsing call*/
   let onnx_bytes = std::fs::read(onnx_file).expect("Failed to read file");
   let mut model = ModelProto::parse_from_bytes(&*onnx_bytes).expect("Failed to convert the file");
@@ -48,15 +70,21 @@ fn read_and_make_inference(onnx_file: String, input_path: &str, output_path: &st
   //let mut model = generate_onnx_model(&onnx_file, "models/onnx.proto");
   //println!("{:?}", model);
 
-  let input_data = read_input_data(input_path).unwrap();
+  let input_data: HashMap<String, Tensor> = inputs.iter().map(|&(name, path)| (name.to_string(), read_input_data(path).unwrap())).collect();
   let output_data = read_input_data(output_path).unwrap();
 
-  inference(model, input_data, input_tensor_name);
-
+  match inference(model, input_data) {
+    Ok(outputs) => {
+      for (output_name, output_value) in outputs {
+        println!("Output {}: {:?}", output_name, output_value);
+      }
+    }
+    Err(e) => println!("Inference failed: {}", e)
+  }
   println!("Expected Data: {:?}", output_data);
 }
 
-fn read_and_write(mut onnx_file: String, input_path: &str, output_path: &str, input_tensor_name: Vec<&str>) {
+fn read_and_write(mut onnx_file: String, inputs: Vec<(&str, &str)>, output_path: &str) {
   /*Library parsing call*/
   let onnx_bytes = std::fs::read(onnx_file.clone()).expect("Failed to read file");
   let mut model = ModelProto::parse_from_bytes(&*onnx_bytes).expect("Failed to convert the file");
@@ -71,7 +99,7 @@ fn read_and_write(mut onnx_file: String, input_path: &str, output_path: &str, in
   generate_onnx_file(&onnx_file, &mut model);
 }
 
-fn read_modify_write(mut onnx_file: String, input_path: &str, output_path: &str, input_tensor_name: Vec<&str>) {
+fn read_modify_write(mut onnx_file: String, inputs: Vec<(&str, &str)>, output_path: &str) {
   /*Library parsing call*/
   let onnx_bytes = std::fs::read(onnx_file.clone()).expect("Failed to read file");
   let mut model = ModelProto::parse_from_bytes(&*onnx_bytes).expect("Failed to convert the file");
@@ -97,7 +125,7 @@ fn read_modify_write(mut onnx_file: String, input_path: &str, output_path: &str,
   generate_onnx_file(&onnx_file, &mut model);
 }
 
-fn read_input_data(input_path: &str) -> Option<Vec<f32>> {
+fn read_input_data(input_path: &str) -> Option<Tensor> {
   let mut file = File::open(input_path).expect("Cannot open input file");
 
   let mut buffer = Vec::new();
@@ -105,12 +133,5 @@ fn read_input_data(input_path: &str) -> Option<Vec<f32>> {
 
   let parsed_message = TensorProto::parse_from_bytes(&buffer).expect("Error while deserializing the message");
 
-  Some(parsed_message.raw_data.clone().unwrap().chunks_exact(4).map(|chunk| u8_to_f32(chunk)).collect())
-}
-
-fn u8_to_f32(bytes: &[u8]) -> f32 {
-  assert_eq!(bytes.len(), 4);
-  let mut array: [u8; 4] = Default::default();
-  array.copy_from_slice(&bytes);
-  f32::from_le_bytes(array)
+  Tensor::from_proto(&parsed_message).ok()
 }
@@ -0,0 +1,121 @@
+use std::thread;
+use half::f16;
+use ndarray::{Array, Array3, ArrayD, Axis, IxDyn, LinalgScalar};
+use crate::tensor::{broadcast_shapes, Tensor};
+
+//OPSET VERSION = 13
+//Matrix product with the semantics of numpy.matmul:
+//  - a 1-D first operand is promoted to a row (1, K), a 1-D second operand to a column (K, 1), and the added dimension is removed from the result
+//  - the dimensions before the last two are batch dimensions, broadcast between the operands
+//The products of the batch are split among the available cores.
+//The operands must have the same element type (float, double, float16, int32 or int64), which is kept by the result
+pub fn matmul(a: &Tensor, b: &Tensor) -> Result<Tensor, String> {
+  match (a, b) {
+    (Tensor::F32(a), Tensor::F32(b)) => Ok(Tensor::F32(matmul_with(a, b)?)),
+    (Tensor::F64(a), Tensor::F64(b)) => Ok(Tensor::F64(matmul_with(a, b)?)),
+    (Tensor::I64(a), Tensor::I64(b)) => Ok(Tensor::I64(matmul_with(a, b)?)),
+    (Tensor::I32(a), Tensor::I32(b)) => Ok(Tensor::I32(matmul_with(a, b)?)),
+    /* float16 has no native arithmetic: the products are computed in float */
+    (Tensor::F16(a), Tensor::F16(b)) => Ok(Tensor::F16(matmul_with(&a.mapv(f16::to_f32), &b.mapv(f16::to_f32))?.mapv(f16::from_f32))),
+    _ => Err(format!("MatMul is not defined for {:?} by {:?}", a.data_type(), b.data_type()))
+  }
+}
+
+fn matmul_with<T: LinalgScalar + Send + Sync>(a: &ArrayD<T>, b: &ArrayD<T>) -> Result<ArrayD<T>, String> {
+  if a.ndim() == 0 || b.ndim() == 0 {
+    return Err(format!("matrix product of a scalar ({:?} by {:?})", a.shape(), b.shape()));
+  }
+  let a_view = if a.ndim() == 1 { a.view().insert_axis(Axis(0)) } else { a.view() };
+  let b_view = if b.ndim() == 1 { b.view().insert_axis(Axis(1)) } else { b.view() };
+
+  let (a_batch, a_matrix) = a_view.shape().split_at(a_view.ndim() - 2);
+  let (b_batch, b_matrix) = b_view.shape().split_at(b_view.ndim() - 2);
+  let (m, k, n) = (a_matrix[0], a_matrix[1], b_matrix[1]);
+  if k != b_matrix[0] {
+    return Err(format!("cannot multiply {:?} by {:?}", a.shape(), b.shape()));
+  }
+  let batch = match broadcast_shapes(a_batch, b_batch) {
+    Some(batch) => batch,
+    None => return Err(format!("cannot broadcast the batch dimensions of {:?} and {:?}", a.shape(), b.shape()))
+  };
+  let batch_size: usize = batch.iter().product();
+
+  /* Both operands become a list of matrices, one for each product of the batch */
+  let a_shape: Vec<usize> = batch.iter().copied().chain([m, k]).collect();
+  let b_shape: Vec<usize> = batch.iter().copied().chain([k, n]).collect();
+  let a_matrices = a_view.broadcast(IxDyn(&a_shape)).unwrap();
+  let a_matrices = a_matrices.to_shape((batch_size, m, k)).unwrap();
+  let b_matrices = b_view.broadcast(IxDyn(&b_shape)).unwrap();
+  let b_matrices = b_matrices.to_shape((batch_size, k, n)).unwrap();
+
+  let mut output: Array3<T> = Array::zeros((batch_size, m, n));
+  let num_threads = thread::available_parallelism().map(|t| t.get()).unwrap_or(1).min(batch_size).max(1);
+  let chunk_size = batch_size.div_ceil(num_threads);
+  if num_threads <= 1 {
+    for (i, mut product) in output.axis_iter_mut(Axis(0)).enumerate() {
+      product.assign(&a_matrices.index_axis(Axis(0), i).dot(&b_matrices.index_axis(Axis(0), i)));
+    }
+  } else {
+    thread::scope(|s| {
+      for (chunk, mut products) in output.axis_chunks_iter_mut(Axis(0), chunk_size).enumerate() {
+        let (a_matrices, b_matrices) = (&a_matrices, &b_matrices);
+        s.spawn(move || {
+          for (i, mut product) in products.axis_iter_mut(Axis(0)).enumerate() {
+            let position = chunk * chunk_size + i;
+            product.assign(&a_matrices.index_axis(Axis(0), position).dot(&b_matrices.index_axis(Axis(0), position)));
+          }
+        });
+      }
+    });
+  }
+
+  let mut output_shape = batch;
+  if a.ndim() > 1 {
+    output_shape.push(m);
+  }
+  if b.ndim() > 1 {
+    output_shape.push(n);
+  }
+  Ok(output.into_shape(IxDyn(&output_shape)).unwrap())
+}
+
+#[allow(dead_code)]
+pub fn test_matmul() {
+  // 2-D by 2-D
+  let a = Tensor::F32(Array::from_shape_vec((2, 3), vec![1., 2., 3., 4., 5., 6.]).unwrap().into_dyn());
+  let b = Tensor::F32(Array::from_shape_vec((3, 2), vec![7., 8., 9., 10., 11., 12.]).unwrap().into_dyn());
+  println!("matmul 2-D: {:?}", matmul(&a, &b));
+  println!("expected: [[58, 64], [139, 154]]");
+
+  // 1-D by 2-D and 2-D by 1-D: the promoted dimension is removed
+  let v = Tensor::F32(Array::from_shape_vec(3, vec![1., 0., -1.]).unwrap().into_dyn());
+  println!("matmul 1-D by 2-D: {:?}", matmul(&Tensor::F32(Array::from_shape_vec(2, vec![1., 1.]).unwrap().into_dyn()), &a));
+  println!("expected: [5, 7, 9]");
+  println!("matmul 2-D by 1-D: {:?}", matmul(&a, &v));
+  println!("expected: [-2, -2]");
+  println!("matmul 1-D by 1-D: {:?}", matmul(&v, &v));
+  println!("expected: 2 (scalar)");
+
+  // Batch (2, 1, 2, 3) by (3, 3, 2): the batch dimensions are broadcast to (2, 3)
+  let batch_a = Tensor::F32(Array::from_shape_fn((2, 1, 2, 3), |(i, _, r, c)| (i * 6 + r * 3 + c) as f32).into_dyn());
+  let batch_b = Tensor::F32(Array::from_shape_fn((3, 3, 2), |(j, r, c)| if r == c { (j + 1) as f32 } else { 0. }).into_dyn());
+  let result = matmul(&batch_a, &batch_b).unwrap().to_f32();
+  println!("matmul batch shape: {:?}", result.shape());
+  println!("expected: [2, 3, 2, 2]");
+  println!("matmul batch [1, 2]: {:?}", result.index_axis(Axis(0), 1).index_axis(Axis(0), 2));
+  println!("expected: [[18, 21], [27, 30]]");
+
+  // Inner dimensions differ
+  println!("matmul wrong: {:?}", matmul(&a, &a));
+
+  // Integer and double operands keep their element type (the int64 products don't fit in a float)
+  let big = Tensor::I64(Array::from_shape_vec((1, 2), vec![1 << 40, 1]).unwrap().into_dyn());
+  let column = Tensor::I64(Array::from_shape_vec((2, 1), vec![1 << 20, 3]).unwrap().into_dyn());
+  println!("matmul int64: {:?}", matmul(&big, &column));
+  println!("expected: I64 [[1152921504606846979]]");
+  let d = Tensor::F64(Array::from_shape_vec((1, 2), vec![0.1, 0.2]).unwrap().into_dyn());
+  println!("matmul double: {:?}", matmul(&d, &Tensor::F64(Array::from_shape_vec(2, vec![1., 1.]).unwrap().into_dyn())));
+  println!("expected: F64 [0.30000000000000004]");
+  println!("matmul float by int64: {:?}", matmul(&a, &column));
+  println!("expected: error");
+}
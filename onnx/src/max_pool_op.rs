@@ -1,6 +1,8 @@
 use ndarray::*;
 use num_traits::Float;
 
+use crate::convolution_op::get_padding_size;
+
 // Padding (specific way of adding zeros to the input matrix) kind used in the convolution.
 #[derive(PartialEq, Debug, Clone, Copy)]
 pub enum Padding {
@@ -14,361 +16,229 @@ pub enum Padding {
   Valid,
 }
 
-// Rust implementation of a convolutional layer.
-// The weight matrix (aka kernel) shall have dimension (in that order)
-// channels/groups(input channels), feature maps(output channels), kernel width, kernel height,
-// specified by kernel_size
+// Kind of pooling computed over each window of the image
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum PoolType {
+  // maximum of the window
+  Max,
+  // average of the window; with count_include_pad the padding zeros are counted too
+  Average { count_include_pad: bool },
+  // Lp norm of the window: (sum of |x|^p)^(1/p)
+  Lp { p: i32 },
+}
+
+// Rust implementation of a pooling layer with any number of spatial axes (1-D, 2-D, 3-D, ...).
+// The kernel_size array only gives the size of the window over each axis (its values are not used)
 pub struct ConvolutionLayer<F: Float> {
   pub(in crate) auto_pad: Padding,
   pub(in crate) pads: Array1<F>,
-  pub(in crate) kernel_size: Array2<i32>,
-  pub(in crate) storage_order: Option<i32>,
+  pub(in crate) kernel_size: ArrayD<i32>,
   pub(in crate) strides: Array1<F>,
+  pub(in crate) dilations: Array1<F>,
+  pub(in crate) ceil_mode: bool,
 }
 
 impl<F: 'static + Float + std::ops::AddAssign> ConvolutionLayer<F> where f32: From<F> {
-  // Creates new convolution layer.
+  // Creates new pooling layer.
   pub fn new(
     auto_pad: Padding,
     pads: Array1<F>,
-    kernel_size: Array2<i32>,
-    storage_order: Option<i32>,
+    kernel_size: ArrayD<i32>,
     strides: Array1<F>,
+    dilations: Array1<F>,
+    ceil_mode: bool,
   ) -> ConvolutionLayer<F> {
-    ConvolutionLayer { auto_pad, pads, kernel_size, storage_order, strides }
+    ConvolutionLayer { auto_pad, pads, kernel_size, strides, dilations, ceil_mode }
   }
 
-  /// Analog to max_pool2d
-  pub fn max_pool(&self, image: &Array4<F>) -> Array4<F> {
-    max_pool2d(
-      image,
+  /// Analog to max_pool1d/max_pool2d/max_pool3d, depending on the rank of the window.
+  pub fn max_pool(&self, image: &ArrayD<F>) -> ArrayD<F> {
+    self.pool(image, PoolType::Max)
+  }
+
+  /// Analog to average_pool1d/average_pool2d/average_pool3d, depending on the rank of the window.
+  pub fn average_pool(&self, image: &ArrayD<F>, count_include_pad: bool) -> ArrayD<F> {
+    self.pool(image, PoolType::Average { count_include_pad })
+  }
+
+  /// Analog to lp_pool1d/lp_pool2d/lp_pool3d, depending on the rank of the window.
+  pub fn lp_pool(&self, image: &ArrayD<F>, p: i32) -> ArrayD<F> {
+    self.pool(image, PoolType::Lp { p })
+  }
+
+  fn pool(&self, image: &ArrayD<F>, pool_type: PoolType) -> ArrayD<F> {
+    pool(
+      image.view(),
       self.auto_pad,
       &self.pads,
       &self.kernel_size,
-      self.storage_order.as_ref(),
+      &self.dilations,
+      self.ceil_mode,
       &self.strides,
+      pool_type,
     )
   }
+
+  /// Spatial dimensions of the output of the layer for an image with the given spatial dimensions,
+  /// None if the window (with its dilations) is bigger than the padded image
+  pub fn output_size(&self, input: &[usize]) -> Option<Vec<usize>> {
+    let (output_size, _, _) = pool_geometry(input, self.auto_pad, &self.pads, &self.kernel_size, &self.dilations, self.ceil_mode, &self.strides);
+    output_size
+  }
 }
 
-/// OPSET VERSION: 8
-/// Performs a max pooling on the given image data using this layers parameters.
-/// We always max pool on flattened images and expect the input array in im2col
-/// style format.
-///
-/// Read more here:
-/// - <https://leonardoaraujosantos.gitbook.io/artificial-inteligence/machine_learning/deep_learning/convolution_layer/making_faster>
+/// OPSET VERSION: 19
+/// Performs a pooling on the given image data using this layers parameters.
+/// Every output element is computed from the window of the (padded) image starting at
+/// output position * stride, whose elements are taken every dilation positions.
 ///
 /// Input:
 ///
-///  2D images legend:
-///  - im2d(batch size, channels, height, width): Array4.
+///  - im(batch size, channels, d1, ..., dn): ArrayViewD.
 ///  - auto_pad: ["NOTSET"->pads has meant not to be None (manually specified padding),
-///               "SAME_UPPER"->padding equally split between axis(if odd number, extra padding added to bottom),
-///               "SAME_LOWER"->padding equally split between axis(if odd number, extra padding added to top,
-///               "VALID"->no padding
-///               ]
-///  - pads: Array1. Manual padding specified accordingly to auto_pad
-///  - kernel_size: Array2. It specifies the height and width size of kernel
-///  - storage_order: Option<i32>. 0-> default is row major, 1-> is col major
+///    "SAME_UPPER"->padding equally split between axis(if odd number, extra padding added at the end),
+///    "SAME_LOWER"->padding equally split between axis(if odd number, extra padding added at the beginning),
+///    "VALID"->no padding
+///    ]
+///  - pads: Array1. Manual padding specified accordingly to auto_pad (the beginning of each axis, then the end of each axis)
+///  - kernel_size: ArrayD. Its shape is the size of the window over each spatial axis
+///  - dilations: Array1. Distance between the elements of the window over each axis (empty means 1)
+///  - ceil_mode: the output size is rounded up instead of down: the last windows can go beyond the padding
 ///  - strides: Array1. Moving offset over each x input axis.
+///  - pool_type: maximum, average or Lp norm of the window. The padding is never part of the maximum and
+///    of the norm, and is part of the average only with count_include_pad
 /// Returns:
 /// -----------------------------------------------
-/// - out: Output data, of shape (B, F, H', W')
-#[allow(unused_variables)]
-#[allow(unused_assignments)]
-pub fn max_pool2d<'a, T, V, F: 'static + Float + std::ops::AddAssign>(
-  im2d: T,
+/// - out: Output data, of shape (B, C, d1', ..., dn')
+#[allow(clippy::too_many_arguments)]
+pub fn pool<F: 'static + Float>(
+  im: ArrayViewD<F>,
   auto_pad: Padding,
-  pads: V,
-  kernel_size: &Array2<i32>,
-  _storage_order: Option<&i32>,
-  strides: V,
-) -> Array4<F>
-  where
-  // This trait bound ensures that kernel and im2d can be passed as owned array or view.
-  // AsArray just ensures that im2d can be converted to an array view via ".into()".
-  // Read more here: https://docs.rs/ndarray/0.12.1/ndarray/trait.AsArray.html
-    T: AsArray<'a, F, Ix4>,
-    V: AsArray<'a, F, Ix1>, f32: From<F>
-{
-  // Initialisations
-  let im2d_arr: ArrayView4<F> = im2d.into();
-  let kernel_size_arr: ArrayView2<i32> = kernel_size.into();
-  let strides_arr: ArrayView1<F> = strides.into();
-  let pads_arr: ArrayView1<F> = pads.into();
-  let im_col: Array2<F>; // output of fn: im2col_ref()
-  let new_im_height: usize;
-  let new_im_width: usize;
-
-  let kernel_height = kernel_size_arr.len_of(Axis(0));
-  let kernel_width = kernel_size_arr.len_of(Axis(1));
-
-  let mut pads_height_start: usize = 0;
-  let mut pads_height_end: usize = 0;
-  let mut pads_width_start: usize = 0;
-  let mut pads_width_end: usize = 0;
-  if auto_pad == Padding::NotSet {
-    let pads_height_start_as_f = *pads_arr.get(0).unwrap();
-    let pads_height_start_as_f32: f32 = pads_height_start_as_f.into();
-    pads_height_start = pads_height_start_as_f32 as usize;
-    let pads_height_end_as_f = *pads_arr.get(2).unwrap();
-    let pads_height_end_as_f32: f32 = pads_height_end_as_f.into();
-    pads_height_end = pads_height_end_as_f32 as usize;
-    let pads_width_start_as_f = *pads_arr.get(1).unwrap();
-    let pads_width_start_as_f32: f32 = pads_width_start_as_f.into();
-    pads_width_start = pads_width_start_as_f32 as usize;
-    let pads_width_end_as_f = *pads_arr.get(3).unwrap();
-    let pads_width_end_as_f32: f32 = pads_width_end_as_f.into();
-    pads_width_end = pads_width_end_as_f32 as usize;
-  }
-
-  let im_batch_size = im2d_arr.len_of(Axis(0));
-  let im_channel = im2d_arr.len_of(Axis(1));
-  let im_height = im2d_arr.len_of(Axis(2));
-  let im_width = im2d_arr.len_of(Axis(3));
-  let im_height_stride_as_f = *strides_arr.get(0).unwrap();
-  let im_height_stride_as_f32: f32 = im_height_stride_as_f.into();
-  let im_height_stride = im_height_stride_as_f32 as usize;
-  let im_width_stride_as_f = *strides_arr.get(1).unwrap();
-  let im_width_stride_as_f32: f32 = im_width_stride_as_f.into();
-  let im_width_stride = im_width_stride_as_f32 as usize;
-
-  // Calculate output shapes H', W' for two types of Padding
-  match auto_pad {
-    Padding::SameLower => {
-      // H' = (H / stride).ceil()
-      // W' = (W / stride).ceil()
-      let new_im_height_float = (im_height as f32 / im_height_stride as f32).ceil();
-      let new_im_width_float = (im_width as f32 / im_width_stride as f32).ceil();
-
-      new_im_height = new_im_height_float as usize;
-      new_im_width = new_im_width_float as usize;
-    }
-    Padding::SameUpper => {
-      // H' = (H / stride).ceil()
-      // W' = (W / stride).ceil()
-      let new_im_height_float = (im_height as f32 / im_height_stride as f32).ceil();
-      let new_im_width_float = (im_width as f32 / im_width_stride as f32).ceil();
-
-      new_im_height = new_im_height_float as usize;
-      new_im_width = new_im_width_float as usize;
-    }
-    Padding::NotSet => {
-      // H' = {[H - HH + (2*padding)] / stride}+ 1
-      // W' = {[W - WW + (2*padding)] / stride} + 1
-      new_im_height = ((im_height - kernel_height + (pads_height_start + pads_height_end)) / im_height_stride) + 1;
-      new_im_width = ((im_width - kernel_width + (pads_width_start + pads_width_end)) / im_width_stride) + 1;
-    }
-    Padding::Valid => {
-      // H' =  (H - HH) / (stride_height + 1)
-      // W' =  (W - WW) / (stride_width + 1)
-      new_im_height = ((im_height - kernel_height) / im_height_stride) + 1;
-      new_im_width = ((im_width - kernel_width) / im_width_stride) + 1;
-    }
-  };
-
-  if auto_pad != Padding::Valid {
-    let mut pad_num_h = 0;
-    let mut pad_num_w = 0;
-    let mut pad_top = 0;
-    let mut pad_bottom = 0;
-    let mut pad_left = 0;
-    let mut pad_right = 0;
-    if auto_pad == Padding::SameUpper || auto_pad == Padding::SameLower {
-      (pad_num_h, pad_num_w, pad_top, pad_bottom, pad_left, pad_right) = get_padding_size(im_height, im_width, im_height_stride, im_width_stride, kernel_height, kernel_width);
-    } else if auto_pad == Padding::NotSet {
-      pad_top = pads_height_start;
-      pad_bottom = pads_height_end;
-      pad_left = pads_width_start;
-      pad_right = pads_width_end;
-      pad_num_h = pads_height_start + pads_height_end;
-      pad_num_w = pads_width_start + pads_width_end;
-    }
-    let mut im2d_arr_pad: Array4<F> = Array::zeros((
-      im_batch_size,
-      im_channel,
-      im_height + pad_num_h,
-      im_width + pad_num_w,
-    ));
-    let pad_bottom_int = (im_height + pad_num_h) - pad_bottom;
-    let pad_right_int = (im_width + pad_num_w) - pad_right;
-    // https://github.com/rust-ndarray/ndarray/issues/823
-    im2d_arr_pad
-      .slice_mut(s![.., .., pad_top..pad_bottom_int, pad_left..pad_right_int])
-      .assign(&im2d_arr);
-
-    let im_height_pad = im2d_arr_pad.len_of(Axis(2));
-    let im_width_pad = im2d_arr_pad.len_of(Axis(3));
-
-    im_col = im2col_ref(
-      im2d_arr_pad.view(),
-      kernel_height,
-      kernel_width,
-      im_height_pad,
-      im_width_pad,
-      im_channel,
-      im_height_stride,
-      im_width_stride,
-    );
-  } else {
-    im_col = im2col_ref(
-      im2d_arr,
-      kernel_height,
-      kernel_width,
-      im_height,
-      im_width,
-      im_channel,
-      im_height_stride,
-      im_width_stride,
-    );
-  }
-
-
-  let mut output: Array4<F> = Array4::zeros((im_batch_size, im_channel, new_im_height, new_im_width));
-  let mut image_height = 0usize;
-  let mut image_width = 0usize;
-  let mut displacement = 0;
-  //println!("num_channels_out: {}, num_channels_in: {}, image_as_row[{}, {}]", num_channels_out, num_filters, im_col.len_of(Axis(0)), im_col.len_of(Axis(1)));
-  for num_channel_output in 0..im_channel {
-    image_height = 0usize;
-    image_width = 0usize;
-    let mut image_start = 0usize;
-    let mut image_end = im_col.len_of(Axis(0));
-    if im_channel > 1 {
-      image_start = (displacement * im_col.len_of(Axis(0))) / im_channel;
-      if image_start >= im_col.len_of(Axis(0)) {
-        image_start = 0;
-        displacement = 0;
+  pads: &Array1<F>,
+  kernel_size: &ArrayD<i32>,
+  dilations: &Array1<F>,
+  ceil_mode: bool,
+  strides: &Array1<F>,
+  pool_type: PoolType,
+) -> ArrayD<F> where f32: From<F> {
+  let n = kernel_size.ndim();
+  let input = &im.shape()[2..];
+  let (output_size, pads_begin, pads_end) = pool_geometry(input, auto_pad, pads, kernel_size, dilations, ceil_mode, strides);
+  let output_size = output_size.unwrap_or_else(|| vec![0; n]);
+  let strides_arr: Vec<usize> = strides.iter().map(|&s| to_usize(s)).collect();
+  let dilations_arr: Vec<usize> = if dilations.is_empty() { vec![1; n] } else { dilations.iter().map(|&d| to_usize(d)).collect() };
+  let windows: Vec<IxDyn> = indices(kernel_size.shape()).into_iter().collect();
+
+  let mut output_shape = im.shape()[..2].to_vec();
+  output_shape.extend(&output_size);
+  let mut output = ArrayD::<F>::zeros(IxDyn(&output_shape));
+  let mut position = vec![0usize; n + 2];
+  for (out, value) in output.indexed_iter_mut() {
+    position[0] = out[0];
+    position[1] = out[1];
+
+    let mut max = F::neg_infinity();
+    let mut sum = F::zero();
+    let mut valid = 0usize;
+    let mut inside_padding = 0usize;
+    for k in &windows {
+      let mut in_image = true;
+      let mut in_padding = true;
+      for axis in 0..n {
+        let p = (out[axis + 2] * strides_arr[axis] + k[axis] * dilations_arr[axis]) as isize - pads_begin[axis] as isize;
+        in_padding &= p >= -(pads_begin[axis] as isize) && p < (input[axis] + pads_end[axis]) as isize;
+        in_image &= p >= 0 && p < input[axis] as isize;
+        position[axis + 2] = p.max(0) as usize;
       }
-      image_end = (im_col.len_of(Axis(0)) / im_channel) + image_start;
-    }
-    //println!("image_start: {}, image_end: {}", image_start, image_end);
-    for row in image_start..image_end {
-      let im_row = im_col.slice(s![row, ..]);
-      /*
-        println!("image row:");
-        for row in im_row.rows() {
-          for &elem in row.iter() {
-            print!("{:?}, ", f32::from(elem));
-          }
-          println!("");
-        }
-       */
-      assert_eq!(im_row.len_of(Axis(0)), kernel_height * kernel_width);
-
-      output[[0, num_channel_output, image_height, image_width]] = im_row.iter().fold(F::min_value(), |max: F, &x| max.max(x.into()));
-
-      if image_width + 1 < new_im_width {
-        image_width += 1;
-      } else {
-        image_height += 1;
-        image_width = 0;
+      if in_padding {
+        inside_padding += 1;
+      }
+      if !in_image {
+        continue;
+      }
+      let x = im[&position[..]];
+      valid += 1;
+      match pool_type {
+        PoolType::Max => max = max.max(x),
+        PoolType::Average { .. } => sum = sum + x,
+        PoolType::Lp { p } => sum = sum + x.abs().powi(p),
       }
     }
-    displacement += 1;
-  }
 
-  /*
-  println!("output:");
-  for row in output.rows() {
-    for &elem in row.iter() {
-      print!("{:?}, ", f32::from(elem));
-    }
-    println!("");
+    *value = match pool_type {
+      PoolType::Max => max,
+      PoolType::Average { count_include_pad } => {
+        let count = if count_include_pad { inside_padding } else { valid };
+        if count == 0 { F::zero() } else { sum / F::from(count).unwrap() }
+      }
+      PoolType::Lp { p } => sum.powf(F::one() / F::from(p).unwrap()),
+    };
   }
-  */
 
   output
 }
 
-
-pub(in crate) fn get_padding_size(
-  input_h: usize,
-  input_w: usize,
-  stride_h: usize,
-  stride_w: usize,
-  kernel_h: usize,
-  kernel_w: usize,
-) -> (usize, usize, usize, usize, usize, usize) {
-  let pad_along_height: usize;
-  let pad_along_width: usize;
-  let idx_0: usize = 0;
-
-  if input_h % stride_h == idx_0 {
-    pad_along_height = (kernel_h - stride_h).max(idx_0);
-  } else {
-    pad_along_height = (kernel_h - (input_h % stride_h)).max(idx_0);
-  };
-  if input_w % stride_w == idx_0 {
-    pad_along_width = (kernel_w - stride_w).max(idx_0);
-  } else {
-    pad_along_width = (kernel_w - (input_w % stride_w)).max(idx_0);
-  };
-
-  let pad_top = pad_along_height / 2;
-  let pad_bottom = pad_along_height - pad_top;
-  let pad_left = pad_along_width / 2;
-  let pad_right = pad_along_width - pad_left;
-
-  // yes top/bottom and right/left are swapped. No, I don't know
-  // why this change makes it conform to the pytorch implementation.
-  (
-    pad_along_height,
-    pad_along_width,
-    pad_bottom,
-    pad_top,
-    pad_right,
-    pad_left,
-  )
-}
-
-pub(in crate) fn im2col_ref<'a, T, F: 'a + Float>(
-  im_arr: T,
-  ker_height: usize,
-  ker_width: usize,
-  im_height: usize,
-  im_width: usize,
-  im_channel: usize,
-  stride_h: usize,
-  stride_w: usize,
-) -> Array2<F>
-  where
-  // Args:
-  //   im_arr: image matrix to be translated into columns, (C,H,W)
-  //   ker_height: filter height (hh)
-  //   ker_width: filter width (ww)
-  //   im_height: image height
-  //   im_width: image width
-  //
-  // Returns:
-  //   col: (new_h*new_w,hh*ww*C) matrix, each column is a cube that will convolve with a filter
-  //         new_h = (H-hh) // stride + 1, new_w = (W-ww) // stride + 1
-    T: AsArray<'a, F, Ix4>,
-{
-  let im2d_arr: ArrayView4<F> = im_arr.into();
-  let new_h = ((im_height - ker_height) / stride_h) + 1;
-  let new_w = ((im_width - ker_width) / stride_w) + 1;
-  let mut cols_img: Array2<F> = Array::zeros((new_h * new_w * im_channel, ker_height * ker_width));
-  let mut cont = 0_usize;
-
-  for k in 0..im_channel {
-    for i in 1..new_h + 1 {
-      for j in 1..new_w + 1 {
-        let patch = im2d_arr.slice(s![
-                0,
-                k,
-                (i - 1) * stride_h..((i - 1) * stride_h + ker_height),
-                (j - 1) * stride_w..((j - 1) * stride_w + ker_width),
-            ]);
-        let patchrow_unwrap: Array1<F> = Array::from_iter(patch.map(|a| *a));
-
-        cols_img.row_mut(cont).assign(&patchrow_unwrap);
-        cont += 1;
+/// Output size, padding at the beginning and padding at the end of each spatial axis of a pooling.
+/// The output size is None if the window is bigger than the padded image
+fn pool_geometry<F: Float>(
+  input: &[usize],
+  auto_pad: Padding,
+  pads: &Array1<F>,
+  kernel_size: &ArrayD<i32>,
+  dilations: &Array1<F>,
+  ceil_mode: bool,
+  strides: &Array1<F>,
+) -> (Option<Vec<usize>>, Vec<usize>, Vec<usize>) where f32: From<F> {
+  let n = input.len();
+  let mut output = Some(Vec::with_capacity(n));
+  let mut pads_begin = Vec::with_capacity(n);
+  let mut pads_end = Vec::with_capacity(n);
+  for axis in 0..n {
+    let size = input[axis];
+    let stride = to_usize(strides[axis]);
+    let dilation = if dilations.is_empty() { 1 } else { to_usize(dilations[axis]) };
+    // Size of the window, dilations included
+    let effective = (kernel_size.shape()[axis] - 1) * dilation + 1;
+
+    let (pad_begin, pad_end) = match auto_pad {
+      Padding::NotSet if pads.len() == 2 * n => (to_usize(pads[axis]), to_usize(pads[axis + n])),
+      Padding::NotSet | Padding::Valid => (0, 0),
+      Padding::SameUpper => {
+        let (_, small, big) = get_padding_size(size, stride, effective);
+        (small, big)
       }
-    }
+      Padding::SameLower => {
+        let (_, small, big) = get_padding_size(size, stride, effective);
+        (big, small)
+      }
+    };
+    pads_begin.push(pad_begin);
+    pads_end.push(pad_end);
+
+    let output_dim = if auto_pad == Padding::SameUpper || auto_pad == Padding::SameLower {
+      // D' = (D / stride).ceil()
+      Some(size.div_ceil(stride))
+    } else {
+      // D' = {[D - K + padding] / stride} + 1, rounded down or up
+      (size + pad_begin + pad_end).checked_sub(effective).map(|span| {
+        let output = if ceil_mode { span.div_ceil(stride) } else { span / stride } + 1;
+        // The last window must start inside the image or the initial padding
+        if ceil_mode && (output - 1) * stride >= size + pad_begin { output - 1 } else { output }
+      })
+    };
+    output = match (output, output_dim) {
+      (Some(mut output), Some(dim)) => {
+        output.push(dim);
+        Some(output)
+      }
+      _ => None
+    };
   }
-  cols_img
+  (output, pads_begin, pads_end)
+}
+
+fn to_usize<F: Float>(value: F) -> usize where f32: From<F> {
+  f32::from(value) as usize
 }
 
 #[allow(dead_code)]
@@ -378,18 +248,117 @@ pub fn test_max_pool() {
     (1, 1, 4, 4),
     vec![1., 2., 3., 4., 5., 6., 7., 8., 9., 10., 11., 12., 13., 14., 15., 16.],
   )
-    .unwrap();
+    .unwrap()
+    .into_dyn();
 
   println!("{:?}", input);
   // Kernel has shape (channels in, channels out, height, width)
-  let kernel_size: Array2<i32> = Array::zeros((3, 3));
+  let kernel_size: ArrayD<i32> = ArrayD::zeros(IxDyn(&[3, 3]));
 
   let strides: Array1<f32> = array![1., 1.];
   let pads: Array1<f32> = array![0., 0., 0., 0.];
+  let dilations: Array1<f32> = array![1., 1.];
 
   let conv_layer =
-    ConvolutionLayer::new(Padding::Valid, pads, kernel_size, Some(0), strides);
-  let output_layer: Array4<f32> = conv_layer.max_pool(&input);
+    ConvolutionLayer::new(Padding::Valid, pads, kernel_size, strides, dilations, false);
+  let output_layer = conv_layer.max_pool(&input);
 
   println!("Layer: {:?}", output_layer);
+  println!("expected: [[[[11, 12], [15, 16]]]]");
+
+  // 2x2 window with dilations 2: the elements (0,0), (0,2), (2,0), (2,2) of each window
+  let dilated_layer =
+    ConvolutionLayer::new(Padding::Valid, array![0., 0., 0., 0.], ArrayD::zeros(IxDyn(&[2, 2])), array![1., 1.], array![2., 2.], false);
+  println!("dilations: {:?}", dilated_layer.max_pool(&input));
+  println!("expected: [[[[11, 12], [15, 16]]]]");
+
+  // 2x2 window with strides 3: with ceil_mode the second window starts at 3 and only covers the last row/column
+  let ceil_layer =
+    ConvolutionLayer::new(Padding::NotSet, array![0., 0., 0., 0.], ArrayD::zeros(IxDyn(&[2, 2])), array![3., 3.], array![1., 1.], true);
+  println!("ceil_mode: {:?}", ceil_layer.max_pool(&input));
+  println!("expected: [[[[6, 8], [14, 16]]]]");
+
+  // 1-D: windows of 2 elements with stride 2
+  let input = Array::from_shape_vec((1, 1, 6), vec![1., 5., 2., 8., 3., 0.]).unwrap().into_dyn();
+  let layer_1d =
+    ConvolutionLayer::new(Padding::Valid, array![], ArrayD::zeros(IxDyn(&[2])), array![2.], array![1.], false);
+  println!("max_pool 1d: {:?}", layer_1d.max_pool(&input));
+  println!("expected: [[[5, 8, 3]]]");
+
+  // 3-D: 2x2x1 windows (depth, height, width) over a 2x2x2 image
+  let input = Array::from_shape_vec((1, 1, 2, 2, 2), vec![1., 2., 3., 4., 5., 6., 7., 8.]).unwrap().into_dyn();
+  let layer_3d =
+    ConvolutionLayer::new(Padding::Valid, array![], ArrayD::zeros(IxDyn(&[2, 2, 1])), array![1., 1., 1.], array![1., 1., 1.], false);
+  println!("max_pool 3d: {:?}", layer_3d.max_pool(&input));
+  println!("expected: [[[[[7, 8]]]]]");
+  println!("average_pool 3d: {:?}", layer_3d.average_pool(&input, false));
+  println!("expected: [[[[[4, 5]]]]]");
+}
+
+#[allow(dead_code)]
+pub fn test_average_pool() {
+  let input = Array::from_shape_vec(
+    (1, 1, 3, 3),
+    vec![1., 2., 3., 4., 5., 6., 7., 8., 9.],
+  )
+    .unwrap()
+    .into_dyn();
+
+  // 2x2 window, stride 1, 1 padding on every side: the corners cover 1 element of the image and 3 of padding
+  let layer =
+    ConvolutionLayer::new(Padding::NotSet, array![1., 1., 1., 1.], ArrayD::zeros(IxDyn(&[2, 2])), array![1., 1.], array![1., 1.], false);
+  println!("average_pool: {:?}", layer.average_pool(&input, false));
+  println!("expected: [[[[1, 1.5, 2.5, 3], [2.5, 3, 4, 4.5], [5.5, 6, 7, 7.5], [7, 7.5, 8.5, 9]]]]");
+  println!("average_pool count_include_pad: {:?}", layer.average_pool(&input, true));
+  println!("expected: [[[[0.25, 0.75, 1.25, 0.75], [1.25, 3, 4, 2.25], [2.75, 6, 7, 3.75], [1.75, 3.75, 4.25, 2.25]]]]");
+
+  // 2x2 window, stride 2, ceil_mode: the last windows cover only the last row/column
+  let ceil_layer =
+    ConvolutionLayer::new(Padding::NotSet, array![0., 0., 0., 0.], ArrayD::zeros(IxDyn(&[2, 2])), array![2., 2.], array![1., 1.], true);
+  println!("average_pool ceil_mode: {:?}", ceil_layer.average_pool(&input, true));
+  println!("expected: [[[[3, 4.5], [7.5, 9]]]]");
+
+  // L2 norm of 2x2 windows
+  let lp_layer =
+    ConvolutionLayer::new(Padding::Valid, array![0., 0., 0., 0.], ArrayD::zeros(IxDyn(&[2, 2])), array![1., 1.], array![1., 1.], false);
+  println!("lp_pool: {:?}", lp_layer.lp_pool(&input, 2));
+  println!("expected: [[[[sqrt(46), sqrt(74)], [sqrt(154), sqrt(206)]]]] = [[[[6.7823, 8.6023], [12.4097, 14.3527]]]]");
+
+  // SAME_UPPER with stride 2: output ceil(3 / 2) = 2, one padding row/column at the end
+  let same_layer =
+    ConvolutionLayer::new(Padding::SameUpper, array![], ArrayD::zeros(IxDyn(&[2, 2])), array![2., 2.], array![1., 1.], false);
+  println!("average_pool SAME_UPPER: {:?}", same_layer.average_pool(&input, false));
+  println!("expected: [[[[3, 4.5], [7.5, 9]]]]");
+}
+
+#[allow(dead_code)]
+pub fn test_pool_node() {
+  use std::collections::HashMap;
+  use crate::inference_session::InferenceSession;
+  use crate::onnx_structure::tensor_proto::DataType;
+  use crate::shape_inference::infer_value_types;
+  use crate::tensor::{Dim, Tensor};
+  use crate::test_graph::{int_attribute, ints_attribute, model, node, value_info};
+
+  // Stem of ResNet: 3x3 window, strides 2 and pads 1 without auto_pad (the default is NOTSET, so the pads are used)
+  let input = Tensor::F32(Array::from_shape_vec((1, 1, 4, 4), (1..=16).map(|v| v as f32).collect()).unwrap().into_dyn());
+  let shape = |size: usize| Some(vec![Dim::Value(1), Dim::Value(1), Dim::Value(size), Dim::Value(size)]);
+  for (op_type, extra) in [("MaxPool", vec![]), ("AveragePool", vec![]), ("AveragePool", vec![int_attribute("count_include_pad", 1)])] {
+    let mut attributes = vec![ints_attribute("kernel_shape", &[3, 3]), ints_attribute("strides", &[2, 2]), ints_attribute("pads", &[1, 1, 1, 1])];
+    attributes.extend(extra);
+    let model = model(
+      vec![node(op_type, &["x"], &["y"], attributes)],
+      vec![value_info("x", DataType::FLOAT, shape(4))],
+      vec![value_info("y", DataType::FLOAT, shape(2))],
+      vec![],
+      13,
+    );
+    println!("{} inferred: {:?}", op_type, infer_value_types(&model).map(|types| types["y"].clone()));
+    let outputs = InferenceSession::new(&model).unwrap().run(HashMap::from([("x".to_string(), input.clone())]));
+    println!("{}: {:?}", op_type, outputs.map(|outputs| outputs["y"].clone()));
+  }
+  println!("expected: [1, 1, 2, 2] for all");
+  println!("expected: MaxPool [[[[6, 8], [14, 16]]]]");
+  println!("expected: AveragePool [[[[3.5, 5], [9.5, 11]]]]");
+  println!("expected: AveragePool count_include_pad [[[[1.5556, 3.3333], [6.3333, 11]]]]");
 }
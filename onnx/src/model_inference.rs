@@ -1,874 +1,2003 @@
 use std::collections::HashMap;
-use std::{io, thread};
-use std::sync::{Arc, Condvar, Mutex};
-use std::thread::JoinHandle;
-use ndarray::{Array1, Array2, Array3, Array4, Axis, concatenate};
-use num_traits::Float;
-use crate::onnx_structure::{ModelProto, NodeProto, TensorProto, ValueInfoProto};
-
+use std::thread;
+use half::f16;
+use num_traits::cast;
+use ndarray::{Array, Array1, ArrayD, Dimension, IxDyn};
+use crate::onnx_structure::{ModelProto, NodeProto};
+
+use crate::activation_op::{activation, clip, prelu, Activation};
+use crate::binary_op::{binary, BinaryOp};
+use crate::conv_transpose_op::ConvTransposeLayer;
 use crate::convolution_op::{ConvolutionLayer as ConvLayerConv, Padding as PadConv};
 use crate::dropout_op::dropout;
+use crate::gemm_op::gemm;
 use crate::global_average_pool_op::global_average_pool;
-use crate::onnx_structure::tensor_shape_proto::dimension::Value::{DimParam, DimValue};
-use crate::onnx_structure::type_proto::Value;
+use crate::global_max_pool_op::global_max_pool;
+use crate::group_normalization::group_normalization;
+use crate::indexing_op::{gather, gather_elements, gather_nd, scatter_nd, slice, split, split_sizes, ScatterReduction};
+use crate::inference_error::InferenceError;
+use crate::inference_session::{InferenceSession, NodeAttributes, PreparedNode, PreparedOp};
+use crate::layout_op::{concat, expand, tile, transpose};
+use crate::reduce_op::{arg_reduce, reduce, ReduceOp};
 use crate::relu_op::relu;
-use crate::max_pool_op::{ConvolutionLayer as ConvLayerMaxPool, Padding as PadMaxPool};
-use crate::reshape_op::reshape;
-use crate::softmax::softmax;
+use crate::normalization_op::{batch_normalization, instance_normalization};
+use crate::op_registry::{DEFAULT_DOMAIN, OperatorRegistry};
+use crate::pad_op::{pad, PadMode};
+use crate::matmul_op::matmul;
+use crate::layer_normalization::{layer_normalization, rms_normalization};
+use crate::max_pool_op::{ConvolutionLayer as ConvLayerMaxPool, Padding as PadMaxPool, PoolType};
+use crate::reshape_op::{flatten, reshape, squeeze, unsqueeze};
+use crate::softmax::{softmax, softmax_axis};
+use crate::tensor::Tensor;
+use crate::unary_op::{unary, UnaryOp};
 
+/*
+Runs a floating point computation over the array of a tensor keeping its element type: the variants listed are computed
+natively (the body is instantiated for each of them), float16 is computed in float. Any other element type is an error
+of the node (the macro returns it from the calling function)
+*/
+macro_rules! map_float_variant {
+  ($node:expr, $tensor:expr, [$($native:ident),*], $arr:ident => $body:expr) => {
+    match $tensor {
+      $(Tensor::$native($arr) => Tensor::$native($body),)*
+      Tensor::F16(arr) => {
+        let $arr = &arr.mapv(f16::to_f32);
+        Tensor::F16(($body).mapv(f16::from_f32))
+      }
+      other => return Err(InferenceError::shape_mismatch(&$node.proto, format!("{} is not defined for {:?}", $node.op_type(), other.data_type())))
+    }
+  };
+}
 
 /*
 This function make the inference on the model received in input
   -It takes 3 parameters:
     ~ model: struct that contains the onnx model
-    ~ input_data: this is the input vector of the model (i.e image of a cat)
-    ~ input_tensor_name: name(s) of the model's input(s)
-  -It prints intermediate type of operations, threads that are working and final result.
+    ~ inputs: values of the model inputs indexed by name (i.e image of a cat), each one checked against its declared type and shape
+  -It prints intermediate type of operations and threads that are working.
+  -It returns the graph outputs (model.graph.output), indexed by their name, or the error that stopped the inference.
+  For running the same model many times, build an InferenceSession once and call run on it.
 */
-pub fn inference(model: ModelProto, input_data: Vec<f32>, input_tensor_name: Vec<&str>) {
-  let hashmap_outputs_to_inputs: Arc<Mutex<HashMap<String, (Option<Array2<f32>>, Option<Array4<f32>>)>>> = Arc::new(Mutex::new(HashMap::new()));
-  let arc_model= Arc::new(model);
-
-  /* Used by main thread while the node considered hasn't already ready inputs data (they will be generated by other threads) */
-  let condition_var: Arc<(Mutex<Vec<String>>, Condvar)> = Arc::new((Mutex::new(Vec::new()), Condvar::new()));
-
-  let mut position = 0;
-  /* Positions of nodes that has already executed by threads. Main has to skip this nodes */
-  let mut position_to_skip: Vec<i32> = Vec::new();
-
-  let mut found_indipendent_nodes = false;
-
-  manage_input_data(&hashmap_outputs_to_inputs, &arc_model, input_data, input_tensor_name);
-
-  let map = hashmap_outputs_to_inputs.lock().unwrap();
-  let result = search_node_without_previous_dependencies(&arc_model, map.keys().collect());
-  drop(map);  /* For unlocking the lock. If not, main thread could not try to execute nodes while threads works */
+pub fn inference(model: ModelProto, inputs: HashMap<String, Tensor>) -> Result<HashMap<String, Tensor>, InferenceError> {
+  let session = InferenceSession::new(&model)?;
+  session.run(inputs)
+}
 
-  let mut threads: Vec<io::Result<JoinHandle<()>>> = Vec::new();
+/*
+This function execute the inference operation of the node, with the implementation picked for the opset imported by the model.
+  -It takes 2 parameters:
+    ~ node: inference node, with its attributes already resolved
+    ~ inputs: values of the node inputs, in the same order of node.input (None for the optional inputs not provided)
+  -It returns the values of the node outputs, or an error if the operation fails
+*/
+pub fn node_inference(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let operation = match &node.proto.op_type {
+    None => return Err(InferenceError::unsupported_op(&node.proto)),
+    Some(op) => { op }
+  };
 
-  if result.is_some() {
-    /* Launching threads */
-    found_indipendent_nodes = true;
+  println!("INFERENCE ON INPUT(s) {:?} OVER {} OPERATION done by {}", node.proto.input, operation, thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
 
-    position_to_skip = result.clone().unwrap().1;
-    let independent_nodes = result.unwrap().0;
+  node.kernel.compute(node, inputs)
+}
 
-    let mut n_t = 0;
-    for node in independent_nodes {
-      let t_map = hashmap_outputs_to_inputs.clone();
-      let t_model = arc_model.clone();
-      let t_condvar = condition_var.clone();
+/*
+Registers a reduction twice: from opset 1 with axes as an attribute, and from the version where axes becomes an input
+*/
+macro_rules! register_reduce {
+  ($registry:expr, $op_type:literal, $op:ident, $axes_input_since:literal) => {
+    $registry.register(DEFAULT_DOMAIN, $op_type, 1, |node, inputs| reduce_node_op(node, inputs, ReduceOp::$op, false));
+    $registry.register(DEFAULT_DOMAIN, $op_type, $axes_input_since, |node, inputs| reduce_node_op(node, inputs, ReduceOp::$op, true));
+  };
+}
 
-      threads.push(thread::Builder::new()
-        .name(format!("{}{}", "Thread", n_t))
-        .spawn(move || {
-          node_inference(&node, &t_map, &t_model);
+/*
+Registers an element-wise unary operation, computed by unary_node_op
+*/
+macro_rules! register_unary {
+  ($registry:expr, $op_type:literal, $op:ident, $since_version:literal) => {
+    $registry.register(DEFAULT_DOMAIN, $op_type, $since_version, |node, inputs| unary_node_op(node, inputs, UnaryOp::$op, $op_type));
+  };
+}
 
-          /* Notify to main thread that some nodes are executed */
-          let (l, cvar) = &*t_condvar;
-          let mut new_value_added = l.lock().unwrap();
-          new_value_added.extend(node.output.clone());
-          cvar.notify_all();
-        }));
+/*
+This function registers the operations implemented by the engine, once for each opset version that changed their semantics
+  -It takes 1 parameter:
+    ~ registry: where the operations are added
+*/
+pub(crate) fn register_default_operators(registry: &mut OperatorRegistry) {
+  registry.register(DEFAULT_DOMAIN, "Conv", 1, convolution_op);
+  registry.register(DEFAULT_DOMAIN, "ConvTranspose", 1, conv_transpose_op);
+  registry.register(DEFAULT_DOMAIN, "Relu", 1, relu_op);
+  registry.register(DEFAULT_DOMAIN, "Relu", 14, relu_op_v14);
+  /* Before opset 6 the legacy consumed_inputs attribute is accepted (not supported) */
+  registry.register(DEFAULT_DOMAIN, "LeakyRelu", 6, leaky_relu_op);
+  registry.register(DEFAULT_DOMAIN, "Elu", 6, elu_op);
+  registry.register(DEFAULT_DOMAIN, "Selu", 6, selu_op);
+  registry.register(DEFAULT_DOMAIN, "Sigmoid", 6, sigmoid_op);
+  registry.register(DEFAULT_DOMAIN, "HardSigmoid", 6, hard_sigmoid_op);
+  registry.register(DEFAULT_DOMAIN, "Tanh", 6, tanh_op);
+  /* Before opset 7 the slope is broadcast only with the legacy consumed_inputs semantics (not supported) */
+  registry.register(DEFAULT_DOMAIN, "PRelu", 7, prelu_op);
+  registry.register(DEFAULT_DOMAIN, "HardSwish", 14, hard_swish_op);
+  registry.register(DEFAULT_DOMAIN, "Softplus", 1, softplus_op);
+  registry.register(DEFAULT_DOMAIN, "Softsign", 1, softsign_op);
+  registry.register(DEFAULT_DOMAIN, "Gelu", 20, gelu_op);
+  registry.register(DEFAULT_DOMAIN, "Mish", 18, mish_op);
+  registry.register(DEFAULT_DOMAIN, "ThresholdedRelu", 10, thresholded_relu_op);
+  /* Up to opset 10 min and max are attributes, then they become optional inputs */
+  registry.register(DEFAULT_DOMAIN, "Clip", 6, clip_op);
+  registry.register(DEFAULT_DOMAIN, "Clip", 11, clip_op_v11);
+  registry.register(DEFAULT_DOMAIN, "MaxPool", 1, max_pool_op);
+  registry.register(DEFAULT_DOMAIN, "AveragePool", 1, average_pool_op);
+  /* Before opset 2 p is a float attribute */
+  registry.register(DEFAULT_DOMAIN, "LpPool", 2, lp_pool_op);
+  /* Before opset 4 axis is optional (default 1), then it is required. Before opset 11 it can't be negative, which is a special case */
+  registry.register(DEFAULT_DOMAIN, "Concat", 1, concatenate_op);
+  registry.register(DEFAULT_DOMAIN, "Concat", 4, concatenate_op_v4);
+  /* Up to opset 10 ratio is an attribute, then it becomes an input together with training_mode */
+  registry.register(DEFAULT_DOMAIN, "Dropout", 1, drop_out_op);
+  registry.register(DEFAULT_DOMAIN, "Dropout", 12, drop_out_op_v12);
+  registry.register(DEFAULT_DOMAIN, "GlobalAveragePool", 1, global_average_pool_op);
+  registry.register(DEFAULT_DOMAIN, "GlobalMaxPool", 1, global_max_pool_op);
+  /* Before opset 7 the is_test attribute selects the mode (not supported), from opset 14 training_mode does it */
+  registry.register(DEFAULT_DOMAIN, "BatchNormalization", 7, batch_normalization_op);
+  registry.register(DEFAULT_DOMAIN, "BatchNormalization", 14, batch_normalization_op_v14);
+  registry.register(DEFAULT_DOMAIN, "InstanceNormalization", 6, instance_normalization_op);
+  registry.register(DEFAULT_DOMAIN, "LayerNormalization", 17, layer_normalization_op);
+  /* Up to opset 20 scale and bias have one value for each group, then one for each channel */
+  registry.register(DEFAULT_DOMAIN, "GroupNormalization", 18, group_normalization_op);
+  registry.register(DEFAULT_DOMAIN, "GroupNormalization", 21, group_normalization_op_v21);
+  registry.register(DEFAULT_DOMAIN, "RMSNormalization", 23, rms_normalization_op);
+  /* Up to opset 12 (17 for the other reductions) axes is an attribute, then it becomes an input */
+  register_reduce!(registry, "ReduceSum", Sum, 13);
+  register_reduce!(registry, "ReduceMean", Mean, 18);
+  register_reduce!(registry, "ReduceMax", Max, 18);
+  register_reduce!(registry, "ReduceMin", Min, 18);
+  register_reduce!(registry, "ReduceProd", Prod, 18);
+  register_reduce!(registry, "ReduceL1", L1, 18);
+  register_reduce!(registry, "ReduceL2", L2, 18);
+  register_reduce!(registry, "ReduceLogSum", LogSum, 18);
+  register_reduce!(registry, "ReduceLogSumExp", LogSumExp, 18);
+  register_reduce!(registry, "ReduceSumSquare", SumSquare, 18);
+  /* select_last_index is added in opset 12, its default keeps the behavior of the previous versions */
+  registry.register(DEFAULT_DOMAIN, "ArgMax", 1, arg_max_op);
+  registry.register(DEFAULT_DOMAIN, "ArgMin", 1, arg_min_op);
+  /* Before opset 6 the legacy consumed_inputs attribute is accepted (not supported) */
+  register_unary!(registry, "Exp", Exp, 6);
+  register_unary!(registry, "Log", Log, 6);
+  register_unary!(registry, "Sqrt", Sqrt, 6);
+  register_unary!(registry, "Reciprocal", Reciprocal, 6);
+  register_unary!(registry, "Abs", Abs, 6);
+  register_unary!(registry, "Neg", Neg, 6);
+  register_unary!(registry, "Floor", Floor, 6);
+  register_unary!(registry, "Ceil", Ceil, 6);
+  register_unary!(registry, "Round", Round, 11);
+  register_unary!(registry, "Sign", Sign, 9);
+  register_unary!(registry, "Erf", Erf, 9);
+  register_unary!(registry, "Sin", Sin, 7);
+  register_unary!(registry, "Cos", Cos, 7);
+  register_unary!(registry, "Tan", Tan, 7);
+  register_unary!(registry, "Asin", Asin, 7);
+  register_unary!(registry, "Acos", Acos, 7);
+  register_unary!(registry, "Atan", Atan, 7);
+  register_unary!(registry, "Sinh", Sinh, 9);
+  register_unary!(registry, "Cosh", Cosh, 9);
+  register_unary!(registry, "Asinh", Asinh, 9);
+  register_unary!(registry, "Acosh", Acosh, 9);
+  register_unary!(registry, "Atanh", Atanh, 9);
+  /* Up to opset 12 the input is coerced into 2D at axis (default 1), then the softmax is along axis only (default -1) */
+  registry.register(DEFAULT_DOMAIN, "Softmax", 1, softmax_op);
+  registry.register(DEFAULT_DOMAIN, "Softmax", 13, softmax_op_v13);
+  /* Before opset 5 the shape is an attribute (not supported), allowzero is added in opset 14 */
+  registry.register(DEFAULT_DOMAIN, "Reshape", 5, reshape_op);
+  registry.register(DEFAULT_DOMAIN, "Reshape", 14, reshape_op_v14);
+  registry.register(DEFAULT_DOMAIN, "Transpose", 1, transpose_op);
+  /* Before opset 11 axis can't be negative, which is a special case */
+  registry.register(DEFAULT_DOMAIN, "Flatten", 1, flatten_op);
+  /* Up to opset 12 axes is an attribute, then it becomes an input */
+  registry.register(DEFAULT_DOMAIN, "Squeeze", 1, squeeze_op);
+  registry.register(DEFAULT_DOMAIN, "Squeeze", 13, squeeze_op_v13);
+  registry.register(DEFAULT_DOMAIN, "Unsqueeze", 1, unsqueeze_op);
+  registry.register(DEFAULT_DOMAIN, "Unsqueeze", 13, unsqueeze_op_v13);
+  registry.register(DEFAULT_DOMAIN, "Expand", 8, expand_op);
+  /* Before opset 6 tiles and axis are inputs (not supported) */
+  registry.register(DEFAULT_DOMAIN, "Tile", 6, tile_op);
+  registry.register(DEFAULT_DOMAIN, "Identity", 1, identity_op);
+  /* Up to opset 9 starts, ends and axes are attributes, then they become inputs together with steps */
+  registry.register(DEFAULT_DOMAIN, "Slice", 1, slice_op);
+  registry.register(DEFAULT_DOMAIN, "Slice", 10, slice_op_v10);
+  registry.register(DEFAULT_DOMAIN, "Gather", 1, gather_op);
+  registry.register(DEFAULT_DOMAIN, "GatherElements", 11, gather_elements_op);
+  /* batch_dims is added in opset 12, the reductions of ScatterND in opsets 16 (add, mul) and 18 (max, min):
+  their defaults keep the behavior of the previous versions */
+  registry.register(DEFAULT_DOMAIN, "GatherND", 11, gather_nd_op);
+  registry.register(DEFAULT_DOMAIN, "ScatterND", 11, scatter_nd_op);
+  /* Before opset 2 split can also be an input (not supported), from opset 13 it is only an input, num_outputs is added in opset 18 */
+  registry.register(DEFAULT_DOMAIN, "Split", 2, split_op);
+  registry.register(DEFAULT_DOMAIN, "Split", 13, split_op_v13);
+  /* Up to opset 10 pads and value are attributes, then they become inputs. axes is added in opset 18, the wrap mode in opset 19 */
+  registry.register(DEFAULT_DOMAIN, "Pad", 2, pad_op);
+  registry.register(DEFAULT_DOMAIN, "Pad", 11, pad_op_v11);
+  /* Before opset 7 the broadcast is unidirectional and driven by the broadcast/axis attributes (not supported) */
+  registry.register(DEFAULT_DOMAIN, "Add", 7, add_op);
+  registry.register(DEFAULT_DOMAIN, "Sub", 7, sub_op);
+  registry.register(DEFAULT_DOMAIN, "Mul", 7, multiply_op);
+  registry.register(DEFAULT_DOMAIN, "Div", 7, div_op);
+  registry.register(DEFAULT_DOMAIN, "Pow", 7, pow_op);
+  registry.register(DEFAULT_DOMAIN, "Mod", 10, mod_op);
+  /* Before opset 8 the inputs of Max and Min must have the same shape, which is a special case of the broadcast */
+  registry.register(DEFAULT_DOMAIN, "Max", 6, max_op);
+  registry.register(DEFAULT_DOMAIN, "Min", 6, min_op);
+  registry.register(DEFAULT_DOMAIN, "MatMul", 1, mul_op);
+  /* Before opset 7 C is broadcast only with the broadcast attribute (not supported), from opset 11 C is optional */
+  registry.register(DEFAULT_DOMAIN, "Gemm", 7, gemm_op);
+  registry.register(DEFAULT_DOMAIN, "Gemm", 11, gemm_op_v11);
+}
 
-      n_t += 1;
+/*
+This function builds the convolutional layer of the node (1-D, 2-D or 3-D, depending on the rank of the weights)
+  -It takes 4 parameters:
+    ~ node: node on which convolution has to be executed
+    ~ attributes: attributes of the node
+    ~ kernel: weights W of the convolution
+    ~ bias: bias B of the convolution, if present
+  -It returns the layer, or an error if the attributes or the weights aren't valid
+*/
+pub(crate) fn build_conv_layer(node: &NodeProto, attributes: &NodeAttributes, kernel: &Tensor, bias: Option<&Tensor>) -> Result<ConvLayerConv<f32>, InferenceError> {
+  let kernel: ArrayD<f32> = kernel.to_f32();
+  if kernel.ndim() < 3 {
+    return Err(InferenceError::shape_mismatch(node, format!("weights {:?} must be (M, C/group, k1, ..., kn)", kernel.shape())));
+  }
+  let n = kernel.ndim() - 2;
+  let bias: Option<Array1<f32>> = match bias {
+    Some(b) => Some(into_rank(node, b.to_f32(), "bias B")?),
+    None => None
+  };
+  if let Some(b) = &bias {
+    if b.len() != kernel.shape()[0] {
+      return Err(InferenceError::shape_mismatch(node, format!("bias {:?} doesn't match weights {:?}", b.shape(), kernel.shape())));
     }
   }
 
-  for node in &arc_model.graph.node {
-    //print!("TRY INFERENCE ON {:?} OVER {} OPERATION by main", node.input);
+  check_attributes(node, attributes, &["auto_pad", "dilations", "group", "kernel_shape", "pads", "strides"])?;
 
-    // We have to check if after the start node, that is independent, there are parallel nodes
-    if found_indipendent_nodes {
-      check_pararrel_nodes_and_start_threads(&arc_model, position, node, &mut position_to_skip, &hashmap_outputs_to_inputs, &condition_var, &mut threads);
-      found_indipendent_nodes = false;
-    }
-
-    if !position_to_skip.contains(&position) {
-      possibile_wating_for_previous_results(node, &hashmap_outputs_to_inputs, &condition_var, &arc_model);
+  let mut auto_pad = match attributes.string("auto_pad").unwrap_or("NOTSET") {
+    "SAME_UPPER" => PadConv::SameUpper,
+    "SAME_LOWER" => PadConv::SameLower,
+    "VALID" => PadConv::Valid,
+    "NOTSET" | "NOT_SET" => PadConv::Valid,
+    other => return Err(InferenceError::bad_attribute(node, "auto_pad", format!("unknown padding {}", other)))
+  };
+  let dilations: Option<Array1<i32>> = match attributes.ints("dilations") {
+    Some(d) if d.len() != n || d.iter().any(|&v| v < 1) => return Err(InferenceError::bad_attribute(node, "dilations", format!("expected {} positive values", n))),
+    Some(d) => Some(d.iter().map(|&v| v as i32).collect()),
+    None => None
+  };
+  let group = attributes.int("group").unwrap_or(1);
+  if group <= 0 || !kernel.shape()[0].is_multiple_of(group as usize) {
+    return Err(InferenceError::bad_attribute(node, "group", format!("group {} doesn't divide the {} output channels", group, kernel.shape()[0])));
+  }
+  let pads: Array1<f32> = attributes.ints("pads").unwrap_or_default().iter().map(|&x| x as f32).collect();
+  let mut strides: Array1<f32> = attributes.ints("strides").unwrap_or_default().iter().map(|&x| x as f32).collect();
 
-      check_pararrel_nodes_and_start_threads(&arc_model, position, node, &mut position_to_skip, &hashmap_outputs_to_inputs, &condition_var, &mut threads);
+  //dbg!(kernel.clone());
+  if !pads.is_empty() {
+    if pads.len() != 2 * n {
+      return Err(InferenceError::bad_attribute(node, "pads", format!("expected {} values", 2 * n)));
+    }
+    if pads.iter().any(|&p| p > 0.0) {
+      auto_pad = PadConv::NotSet;
     }
-
-    position += 1;
   }
-
-
-  for t in threads {
-    t.expect("PROBLEM JOINING").join().expect("ERROR");
+  if strides.is_empty() {
+    strides = Array1::ones(n);
+  } else if strides.len() != n || strides.iter().any(|&s| s < 1.0) {
+    return Err(InferenceError::bad_attribute(node, "strides", format!("expected {} positive values", n)));
   }
+  if let Some(k) = attributes.ints("kernel_shape") {
+    if k.len() != n || k.iter().zip(&kernel.shape()[2..]).any(|(&a, &b)| a as usize != b) {
+      return Err(InferenceError::bad_attribute(node, "kernel_shape", format!("{:?} doesn't match weights {:?}", k, kernel.shape())));
+    }
+  }
+  //println!("PADS: {:?}, STRIDES: {:?}, DILATIONS: {:?}", pads, strides, dilations);
+  Ok(ConvLayerConv::new(kernel, bias, auto_pad, dilations, Some(group as i32), pads, strides))
 }
 
 /*
-This function allow the Main to stop if the inputs of the considered nodes aren't present (They will be calculated by others threads).
+This function builds the transposed convolution layer of the node
   -It takes 4 parameters:
-    ~ node: the considered node in the model
-    ~ hasmpa_outputs_to_inputs: contains the partial results calculated by inferences operations (i.e. convolution, relu)
-    ~ condition_var: the variable used for waiting in case of the inputs aren't present
-    ~ arc_model: smart pointer that contains the onnx struct
-*/
-pub fn possibile_wating_for_previous_results(node: &NodeProto, hashmap_outputs_to_inputs: &Arc<Mutex<HashMap<String, (Option<Array2<f32>>, Option<Array4<f32>>)>>>, condition_var: &Arc<(Mutex<Vec<String>>, Condvar)>, arc_model: &Arc<ModelProto>) {
-  let mut inputs_are_present = false;
-
-  while !inputs_are_present {
-    inputs_are_present = true;
-
-    for input in &node.input {
-      let map = hashmap_outputs_to_inputs.lock().unwrap();
-      if !map.contains_key(input) {
-        if !already_into_initializer(&arc_model.graph.initializer, input.as_str()) {
-          inputs_are_present = false;
-          break;
-        }
-      }
-    }
-
-    if inputs_are_present {
-      node_inference(&node, &hashmap_outputs_to_inputs, &arc_model);
-    } else {
-      println!("MAIN THREAD WAITING FOR CHILDREN THREADS RESULTS");
-      let (l, cvar) = &**condition_var;
-      let mut new_values_added = l.lock().unwrap();
-
-      // avoid to fall asleep when results are already available (and spurious notification as well)
-      while new_values_added.len() == 0 {
-        new_values_added = cvar.wait(new_values_added).unwrap();
-      }
-
-      //The input data of the node is search in the hash because it's possible that the node could need additional data (compered to the ones
-      //calculated by the child threads)
+    ~ node: node on which the transposed convolution has to be executed
+    ~ attributes: attributes of the node
+    ~ kernel: weights of the layer (C x M/group x k1 x ... x kn)
+    ~ bias: optional bias of the layer (M)
+  -It returns the layer, or an error if the attributes or the weights aren't valid
+*/
+pub(crate) fn build_conv_transpose_layer(node: &NodeProto, attributes: &NodeAttributes, kernel: &Tensor, bias: Option<&Tensor>) -> Result<ConvTransposeLayer, InferenceError> {
+  check_attributes(node, attributes, &["auto_pad", "dilations", "group", "kernel_shape", "output_padding", "output_shape", "pads", "strides"])?;
 
-      //println!("Values obtained {:?}", new_values_added);
-      *new_values_added = Vec::new();
+  let kernel: ArrayD<f32> = kernel.to_f32();
+  if kernel.ndim() < 3 {
+    return Err(InferenceError::shape_mismatch(node, format!("weights {:?} must be (C, M/group, k1, ..., kn)", kernel.shape())));
+  }
+  let n = kernel.ndim() - 2;
+  let group = attributes.int("group").unwrap_or(1);
+  if group <= 0 || !kernel.shape()[0].is_multiple_of(group as usize) {
+    return Err(InferenceError::bad_attribute(node, "group", format!("group {} doesn't divide the {} input channels", group, kernel.shape()[0])));
+  }
+  let group = group as usize;
+  let bias: Option<Array1<f32>> = match bias {
+    Some(b) => Some(into_rank(node, b.to_f32(), "bias B")?),
+    None => None
+  };
+  if let Some(b) = &bias {
+    if b.len() != kernel.shape()[1] * group {
+      return Err(InferenceError::shape_mismatch(node, format!("bias {:?} doesn't match weights {:?} with group {}", b.shape(), kernel.shape(), group)));
+    }
+  }
+  if let Some(k) = attributes.ints("kernel_shape") {
+    if k.len() != n || k.iter().zip(&kernel.shape()[2..]).any(|(&a, &b)| a as usize != b) {
+      return Err(InferenceError::bad_attribute(node, "kernel_shape", format!("{:?} doesn't match weights {:?}", k, kernel.shape())));
     }
   }
-}
 
-/*
-This function start threads if there are nodes that can be executed in parallel.
-  -It takes 7 parameters:
-    ~ arc_model: smart pointer that contains the onnx model
-    ~ position: position of the node in the onnx model
-    ~ node: considered node
-    ~ position_to_skip: positions of the nodes that are already been executed (in terms of inference)
-    ~ hashmap_outputs_to_inputs: contains the partial results calculated by inferences operations (i.e. convolution, relu)
-    ~ condition_var: the variable used for notifying that result(s) is ready
-    ~ threads: vector that contains all the generated threads
-*/
-pub fn check_pararrel_nodes_and_start_threads(arc_model: &Arc<ModelProto>, position: i32, node: &NodeProto, position_to_skip: &mut Vec<i32>, hashmap_outputs_to_inputs: &Arc<Mutex<HashMap<String, (Option<Array2<f32>>, Option<Array4<f32>>)>>>, condition_var: &Arc<(Mutex<Vec<String>>, Condvar)>, threads: &mut Vec<io::Result<JoinHandle<()>>>) {
-  let result = search_node_who_shares_input(&arc_model.graph.node[position as usize + 1..arc_model.graph.node.len() as usize], &node.output[0]);
-  if result.is_some() {
-    let mut vec_to_add = result.clone().unwrap().1;
-    let parallel_nodes = result.unwrap().0;
-
-    for el in vec_to_add.iter_mut() {
-      *el += position as i32 + 1;
+  let auto_pad = match attributes.string("auto_pad").unwrap_or("NOTSET") {
+    "SAME_UPPER" => PadConv::SameUpper,
+    "SAME_LOWER" => PadConv::SameLower,
+    "VALID" => PadConv::Valid,
+    "NOTSET" | "NOT_SET" => PadConv::NotSet,
+    other => return Err(InferenceError::bad_attribute(node, "auto_pad", format!("unknown padding {}", other)))
+  };
+  /* Values of an attribute with one (positive or non negative) value for each spatial axis */
+  let axes_values = |name: &str, count: usize, default: usize, min: i64| -> Result<Vec<usize>, InferenceError> {
+    match attributes.ints(name) {
+      None => Ok(vec![default; count]),
+      Some(v) if v.len() != count || v.iter().any(|&x| x < min) => {
+        Err(InferenceError::bad_attribute(node, name, format!("expected {} values not smaller than {}", count, min)))
+      }
+      Some(v) => Ok(v.iter().map(|&x| x as usize).collect())
     }
-    position_to_skip.extend(vec_to_add);
-
-    let mut n_t = 0;
-    for group in parallel_nodes {
-      let t_map = hashmap_outputs_to_inputs.clone();
-      let t_model = arc_model.clone();
-      let t_condvar = condition_var.clone();
-
-      threads.push(thread::Builder::new()
-        .name(format!("{}{}", "Thread", n_t))
-        .spawn(move || {
-          for n in  group {
-            node_inference(&n, &t_map, &t_model);
-
-            let (l, cvar) = &*t_condvar;
-            let mut new_value_added = l.lock().unwrap();
-            new_value_added.push(n.output[0].clone());
-            cvar.notify_all();
-          }
-        }));
-
-      n_t += 1;
+  };
+  let dilations = axes_values("dilations", n, 1, 1)?;
+  let strides = axes_values("strides", n, 1, 1)?;
+  let pads = axes_values("pads", 2 * n, 0, 0)?;
+  let output_padding = axes_values("output_padding", n, 0, 0)?;
+  for i in 0..n {
+    if output_padding[i] >= strides[i].max(dilations[i]) {
+      return Err(InferenceError::bad_attribute(node, "output_padding", format!("{:?} must be smaller than the strides {:?} or the dilations {:?}", output_padding, strides, dilations)));
     }
   }
+  /* output_shape may contain only the spatial dimensions or the whole shape */
+  let output_shape = match attributes.ints("output_shape") {
+    Some(shape) if (shape.len() == n || shape.len() == n + 2) && shape.iter().all(|&d| d > 0) => {
+      Some(shape[shape.len() - n..].iter().map(|&d| d as usize).collect())
+    }
+    Some(shape) => return Err(InferenceError::bad_attribute(node, "output_shape", format!("{:?} is not a valid output shape", shape))),
+    None => None
+  };
+
+  Ok(ConvTransposeLayer::new(kernel, bias, auto_pad, dilations, group, output_padding, output_shape, pads, strides))
 }
 
 /*
-This function execute the inference operation of the node.
-  -It takes 3 parameters:
-    ~ node: inference node
-    ~ hashmap_outputs_to_inputs: contains the partial results calculated by inferences operations (i.e. convolution, relu)
-    ~ model: smart pointer that contains the onnx model
+This function builds the pooling layer of the node (MaxPool, AveragePool or LpPool)
+  -It takes 2 parameters:
+    ~ node: node on which the pooling has to be executed
+    ~ attributes: attributes of the node
+  -It returns the layer, or an error if the attributes aren't valid
 */
-pub fn node_inference(node: &NodeProto, hashmap_outputs_to_inputs: &Arc<Mutex<HashMap<String, (Option<Array2<f32>>, Option<Array4<f32>>)>>>, model: &Arc<ModelProto>) {
-  println!("INFERENCE ON INPUT(s) {:?} OVER {} OPERATION done by {}", node.input, node.op_type.clone().unwrap() ,thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
-
-  let operation = match &node.op_type {
-    None => { panic!("Operation {:?} NOT found for node {}", &node.op_type, &node.name.as_ref().unwrap()) }
-    Some(op) => { op }
+pub(crate) fn build_pool_layer(node: &NodeProto, attributes: &NodeAttributes) -> Result<ConvLayerMaxPool<f32>, InferenceError> {
+  let known: &[&str] = match node.op_type.as_deref() {
+    Some("AveragePool") => &["auto_pad", "ceil_mode", "count_include_pad", "dilations", "kernel_shape", "pads", "strides"],
+    Some("LpPool") => &["auto_pad", "ceil_mode", "dilations", "kernel_shape", "p", "pads", "strides"],
+    _ => &["auto_pad", "ceil_mode", "dilations", "kernel_shape", "pads", "storage_order", "strides"],
   };
-
-  match operation.as_str() {
-    "Conv" => convolution_op(hashmap_outputs_to_inputs, node, &model.graph.input, &model.graph.initializer),
-    "Relu" => relu_op(hashmap_outputs_to_inputs, node),
-    "MaxPool" => max_pool_op(hashmap_outputs_to_inputs, node, &model.graph.input, &model.graph.initializer),
-    "Concat" => concatenate_op(hashmap_outputs_to_inputs, node),
-    "Dropout" => drop_out_op(hashmap_outputs_to_inputs, node),
-    "GlobalAveragePool" => global_average_pool_op(hashmap_outputs_to_inputs, node),
-    "Softmax" => softmax_op(hashmap_outputs_to_inputs, node),
-    "Reshape" => reshape_op(hashmap_outputs_to_inputs, node, &model.graph.input, &model.graph.initializer),
-    "Add" => add_op(hashmap_outputs_to_inputs, node, &model.graph.input, &model.graph.initializer),
-    "MatMul" => mul_op(hashmap_outputs_to_inputs, node),
-    _ => { panic!("INFERENCE OPERATION '{}' NOT FOUND FOR NODE {}", operation.as_str(), &node.name.as_ref().unwrap()) }
+  check_attributes(node, attributes, known)?;
+
+  let auto_pad = match attributes.string("auto_pad").unwrap_or("NOTSET") {
+    "SAME_UPPER" => PadMaxPool::SameUpper,
+    "SAME_LOWER" => PadMaxPool::SameLower,
+    "VALID" => PadMaxPool::Valid,
+    "NOTSET" => PadMaxPool::NotSet,
+    other => return Err(InferenceError::bad_attribute(node, "auto_pad", format!("unknown padding {}", other)))
+  };
+  /* The window can have 1, 2 or 3 spatial axes (or more), the other attributes must have a value for each of them */
+  let kernel_shape: ArrayD<i32> = match attributes.ints("kernel_shape") {
+    Some([]) => return Err(InferenceError::bad_attribute(node, "kernel_shape", "expected at least 1 value")),
+    Some(k) if k.iter().any(|&v| v < 1) => return Err(InferenceError::bad_attribute(node, "kernel_shape", "expected positive values")),
+    Some(k) => ArrayD::zeros(IxDyn(&k.iter().map(|&v| v as usize).collect::<Vec<usize>>())),
+    None => return Err(InferenceError::bad_attribute(node, "kernel_shape", "required attribute not set"))
+  };
+  let n = kernel_shape.ndim();
+  let mut pads: Array1<f32> = attributes.ints("pads").unwrap_or_default().iter().map(|&x| x as f32).collect();
+  /* storage_order only changes the Indices output, that isn't produced: it is just validated */
+  if !matches!(attributes.int("storage_order").unwrap_or(0), 0 | 1) {
+    return Err(InferenceError::bad_attribute(node, "storage_order", "expected 0 (row major) or 1 (column major)"));
   }
-}
-
-/*
-This function insert into initializers the input(s) data of the onnx model
-  -It takes 4 parameters:
-    ~ hashmap_outputs_to_inputs: contains the partial results calculated by inferences operations (i.e. convolution, relu)
-    ~ model: smart pointer that contains the onnx model
-    ~ input_data: model inputs(s)
-    ~ input_tensor_names: names of the model inputs
-*/
-fn manage_input_data(hashmap_outputs_to_inputs: &Arc<Mutex<HashMap<String, (Option<Array2<f32>>, Option<Array4<f32>>)>>>, model: &Arc<ModelProto>, input_data: Vec<f32>, input_tensor_name: Vec<&str>) {
-  for input_name in input_tensor_name {
-    if !already_into_initializer(&model.graph.initializer, input_name) {
-      let dims: Vec<&i64> = search_input_data_shape(&model.graph.input, input_name);
-      let array = Array4::from_shape_vec((*dims[0] as usize, *dims[1] as usize, *dims[2] as usize, *dims[3] as usize), input_data.clone()).unwrap();
-      let mut map = hashmap_outputs_to_inputs.lock().unwrap();
-      map.insert(input_name.to_string(), (None, Some(array)));
-    }
+  let mut strides: Array1<f32> = attributes.ints("strides").unwrap_or_default().iter().map(|&x| x as f32).collect();
+  let mut dilations: Array1<f32> = attributes.ints("dilations").unwrap_or_default().iter().map(|&x| x as f32).collect();
+  let ceil_mode = attributes.int("ceil_mode").unwrap_or(0) != 0;
+  if strides.is_empty() {
+    strides = Array1::ones(n);
+  } else if strides.len() != n || strides.iter().any(|&s| s < 1.0) {
+    return Err(InferenceError::bad_attribute(node, "strides", format!("expected {} positive values", n)));
   }
+  if dilations.is_empty() {
+    dilations = Array1::ones(n);
+  } else if dilations.len() != n || dilations.iter().any(|&d| d < 1.0) {
+    return Err(InferenceError::bad_attribute(node, "dilations", format!("expected {} positive values", n)));
+  }
+  if !pads.is_empty() && pads.len() != 2 * n {
+    return Err(InferenceError::bad_attribute(node, "pads", format!("expected {} values", 2 * n)));
+  }
+  if pads.is_empty() {
+    pads = Array1::zeros(2 * n);
+  }
+  Ok(ConvLayerMaxPool::new(auto_pad, pads, kernel_shape, strides, dilations, ceil_mode))
 }
 
 /*
 This function do the convolution
-  -It takes 4 parameters:
-    ~ output_container: contains the partial results calculated by inferences operations (i.e. convolution, relu)
+  -It takes 2 parameters:
     ~ node: node on which convolution has to be executed
-    ~ model_inputs: inputs of the onnx model
-    ~ model_initializers: initializers of the onnx model
-*/
-fn convolution_op(output_container: &Arc<Mutex<HashMap<String, (Option<Array2<f32>>, Option<Array4<f32>>)>>>, node: &NodeProto, model_inputs: &Vec<ValueInfoProto>, model_initializers: &Vec<TensorProto>) {
-  let map = output_container.lock().unwrap();
-  let input_image = match map.get(&node.input[0]).clone() {
-    Some(image) => image.1.clone().unwrap(),
-    None => {
-      let (arr4, _, _, _, _) = get_stored_tensor_for_convolution(0, node, model_inputs, model_initializers);
-      arr4.unwrap()
-    }
-  };
-  let kernel = match map.get(&node.input[1]).clone() {
-    Some(ker) => ker.1.clone().unwrap(),
-    None => {
-      let (arr4, _, _, _, _) = get_stored_tensor_for_convolution(1, node, model_inputs, model_initializers);
-      arr4.unwrap()
+    ~ inputs: values of the node inputs
+*/
+fn convolution_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let input_image = get_input_tensor(0, node, inputs)?;
+
+  /* The layer is built here only when the weights aren't initializers */
+  let runtime_layer;
+  let conv_layer = match &node.prepared_op {
+    PreparedOp::Conv(layer) => layer,
+    _ => {
+      let bias = if inputs.len() > 2 { inputs[2] } else { None };
+      runtime_layer = build_conv_layer(&node.proto, &node.attributes, get_input_tensor(1, node, inputs)?, bias)?;
+      &runtime_layer
     }
   };
 
-  drop(map); /* Per rilasciare il lock */
-
-  let mut bias: Option<Array1<f32>> = None;
-  if node.input.len() > 2usize {
-    let (_, _, _, arr1, _) = get_stored_tensor_for_convolution(2, node, model_inputs, model_initializers);
-    bias = Some(arr1.unwrap());
-  }
-
-  let mut strides: Array1<f32> = Default::default();
-  let mut pads: Array1<f32> = Default::default();
-  let mut auto_pad: PadConv = PadConv::Valid;
-  let mut group: Option<i32> = Some(1);
-  let mut dilations: Option<Array2<i32>> = None;
-  for attr in &node.attribute {
-    if attr.name.is_some() {
-      match attr.name.as_ref().unwrap().as_str() {
-        "auto_pad" => match std::str::from_utf8(&attr.s.clone().unwrap()).unwrap() {
-          "SAME_UPPER" => auto_pad = PadConv::SameUpper,
-          "SAME_LOWER" => auto_pad = PadConv::SameLower,
-          "VALID" => auto_pad = PadConv::Valid,
-          "NOT_SET" => auto_pad = PadConv::NotSet,
-          _ => panic!("Convolution Auto Pad specified not found: {}", std::str::from_utf8(&attr.s.clone().unwrap()).unwrap())
-        },
-        "dilations" => dilations = Some(Array2::from_shape_vec((1, 2), vec![attr.ints[0] as i32, attr.ints[1] as i32]).unwrap()),
-        "group" => group = Some(attr.i.unwrap() as i32),
-        "kernel_shape" => {}
-        "pads" => pads = attr.ints.clone().iter().map(|&x| x as f32).collect::<Vec<f32>>().into(),
-        "strides" => strides = attr.ints.clone().iter().map(|&x| x as f32).collect::<Vec<f32>>().into(),
-        _ => panic!("ATTRIBUTE NAME FOR CONVOLUTION NOT FOUND, {}", attr.name.as_ref().unwrap().as_str())
-      }
-    }
+  /* The kernel of the layer is (M, C/group, k1, ..., kn) */
+  let kernel_shape = conv_layer.kernel.shape();
+  let g = conv_layer.group.unwrap_or(1) as usize;
+  if input_image.rank() != kernel_shape.len() || input_image.shape()[1] != kernel_shape[1] * g {
+    return Err(InferenceError::shape_mismatch(&node.proto, format!("input {:?} and weights {:?} are not compatible with group {}", input_image.shape(), kernel_shape, g)));
   }
-
-  //dbg!(input_image.clone());
-  //dbg!(kernel.clone());
-  if !pads.is_empty() {
-    if pads[[0]] > 0.0 || pads[[1]] > 0.0 || pads[[2]] > 0.0 || pads[[3]] > 0.0 {
-      auto_pad = PadConv::NotSet;
-    }
+  if conv_layer.output_size(&input_image.shape()[2..]).is_none() {
+    return Err(InferenceError::shape_mismatch(&node.proto, format!("kernel {:?} is bigger than input {:?}", kernel_shape, input_image.shape())));
   }
-  //println!("PADS: {:?}, STRIDES: {:?}, DILATIONS: {:?}", pads, strides, dilations);
-  let conv_layer = ConvLayerConv::new_onnx_tensor_flow(kernel.clone(), bias, auto_pad, dilations, group, pads, strides);
-  let output_layer: Array4<f32> = conv_layer.convolve(&input_image);
+  //dbg!(input_image.clone());
+  /* The layer works in float: the double inputs aren't supported */
+  let output_layer = map_float_variant!(node, input_image, [F32], image => conv_layer.convolve(image));
 
   //dbg!("Conv: {:?}", output_layer.clone());
   //println!("Conv: {:?}", output_layer.clone());
   println!("Convolve, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
 
-  let mut map_mut = output_container.lock().unwrap();
-  map_mut.insert(node.output[0].clone(), (None, Some(output_layer)));
+  Ok(vec![output_layer])
 }
 
 /*
-This function do the relu
+This function do the transposed convolution
   -It takes 2 parameters:
-    ~ output_container: contains the partial results calculated by inferences operations (i.e. convolution, relu)
-    ~ node: node on which relu has to be executed
+    ~ node: node on which transposed convolution has to be executed
+    ~ inputs: values of the node inputs
 */
-fn relu_op(output_container: &Arc<Mutex<HashMap<String, (Option<Array2<f32>>, Option<Array4<f32>>)>>>, node: &NodeProto) {
-  let map = output_container.lock().unwrap();
-  let input = Array4::from(map.get(node.input[0].as_str()).unwrap().1.clone().unwrap());
-
-  drop(map);
-
-  let output_layer: Array4<f32> = relu(&input);
+fn conv_transpose_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let input_image = get_input_tensor(0, node, inputs)?;
+
+  /* The layer is built here only when the weights aren't initializers */
+  let runtime_layer;
+  let conv_layer = match &node.prepared_op {
+    PreparedOp::ConvTranspose(layer) => layer,
+    _ => {
+      let bias = if inputs.len() > 2 { inputs[2] } else { None };
+      runtime_layer = build_conv_transpose_layer(&node.proto, &node.attributes, get_input_tensor(1, node, inputs)?, bias)?;
+      &runtime_layer
+    }
+  };
 
-  //dbg!("Relu: {:?}", output_layer.clone());
+  let output_layer = map_float_variant!(node, input_image, [F32], image => conv_layer.conv_transpose(image)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?);
 
-  println!("Relu, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+  //dbg!("ConvTranspose: {:?}", output_layer.clone());
+  println!("ConvTranspose, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
 
-  let mut map_mut = output_container.lock().unwrap();
-  map_mut.insert(node.output[0].clone(), (None, Some(output_layer)));
+  Ok(vec![output_layer])
 }
 
 /*
-This function do the maxpool
-  -It takes 4 parameters:
-    ~ output_container: contains the partial results calculated by inferences operations (i.e. convolution, relu)
-    ~ node: node on which maxpool has to be executed
-    ~ model_inputs: inputs of the onnx model
-    ~ model_initializers: initializers of the onnx model
+This function do the relu (opset 1-13: floating point input only)
+  -It takes 2 parameters:
+    ~ node: node on which relu has to be executed
+    ~ inputs: values of the node inputs
 */
-fn max_pool_op(output_container: &Arc<Mutex<HashMap<String, (Option<Array2<f32>>, Option<Array4<f32>>)>>>, node: &NodeProto, model_inputs: &Vec<ValueInfoProto>, model_initializers: &Vec<TensorProto>) {
-  let map = output_container.lock().unwrap();
-
-  let input_image = match map.get(&node.input[0]).clone() {
-    Some(image) => image.1.clone().unwrap(),
-    None => {
-      let (arr4, _, _, _, _) = get_stored_tensor_for_convolution(0, node, model_inputs, model_initializers);
-      arr4.unwrap()
-    }
-  };
-
-  drop(map);
-
-  let mut kernel_shape: Array2<i32> = Default::default();
-  let mut strides: Array1<f32> = Default::default();
-  let mut pads: Array1<f32> = Default::default();
-  let mut auto_pad: PadMaxPool = PadMaxPool::Valid;
-  let mut storage_order: Option<i32> = None;
-  for attr in &node.attribute {
-    if attr.name.is_some() {
-      match attr.name.as_ref().unwrap().as_str() {
-        "auto_pad" => match std::str::from_utf8(&attr.s.clone().unwrap()).unwrap() {
-          "SAME_UPPER" => auto_pad = PadMaxPool::SameUpper,
-          "SAME_LOWER" => auto_pad = PadMaxPool::SameLower,
-          "VALID" => auto_pad = PadMaxPool::Valid,
-          "NOTSET" => auto_pad = PadMaxPool::NotSet,
-          _ => panic!("MaxPool Auto Pad specified not found: {}", std::str::from_utf8(&attr.s.clone().unwrap()).unwrap())
-        },
-        "kernel_shape" => kernel_shape = Array2::zeros((attr.ints[0].clone() as usize, attr.ints[1].clone() as usize)),
-        "pads" => pads = attr.ints.clone().iter().map(|&x| x as f32).collect::<Vec<f32>>().into(),
-        "storage_order" => storage_order = Some(attr.i.unwrap() as i32),
-        "strides" => strides = attr.ints.clone().iter().map(|&x| x as f32).collect::<Vec<f32>>().into(),
-        _ => panic!("ATTRIBUTE NAME FOR MAXPOOL NOT FOUND, {}", attr.name.as_ref().unwrap().as_str())
-      }
-    }
+fn relu_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let input = get_input_tensor(0, node, inputs)?;
+  if !matches!(input, Tensor::F32(_) | Tensor::F64(_) | Tensor::F16(_)) {
+    return Err(InferenceError::shape_mismatch(&node.proto, format!("Relu is not defined for {:?} before opset 14", input.data_type())));
   }
-  let conv_layer = ConvLayerMaxPool::new(auto_pad, pads, kernel_shape, storage_order, strides);
-  let output_layer: Array4<f32> = conv_layer.max_pool(&input_image);
-
-  //dbg!("MaxPool: {:?}", output_layer.clone());
-  println!("MaxPool, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
-
-  let mut map_mut = output_container.lock().unwrap();
-  map_mut.insert(node.output[0].clone(), (None, Some(output_layer)));
+  relu_op_v14(node, inputs)
 }
 
 /*
-This function do the concatenate
+This function do the relu (opset 14 on: also signed integer input)
   -It takes 2 parameters:
-    ~ output_container: contains the partial results calculated by inferences operations (i.e. convolution, relu)
-    ~ node: node on which concatenate has to be executed
+    ~ node: node on which relu has to be executed
+    ~ inputs: values of the node inputs
 */
-fn concatenate_op(output_container: &Arc<Mutex<HashMap<String, (Option<Array2<f32>>, Option<Array4<f32>>)>>>, node: &NodeProto) {
-  let map = output_container.lock().unwrap();
-  let input_1 = Array4::from(map.get(node.input[0].as_str()).unwrap().1.clone().unwrap());
-  let input_2 = Array4::from(map.get(node.input[1].as_str()).unwrap().1.clone().unwrap().clone());
+fn relu_op_v14(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let input = get_input_tensor(0, node, inputs)?;
 
-  drop(map);
+  let output_layer = relu(input)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
 
-  let mut axis = 1;
-
-  for attr in &node.attribute {
-    if attr.name.is_some() {
-      axis = match attr.name.as_ref().unwrap().as_str() {
-        "axis" => attr.i.unwrap(),
-        _ => panic!("ATTRIBUTE NAME FOR CONCATENATE NOT FOUND, {}", attr.name.as_ref().unwrap().as_str())
-      };
-    }
-  }
-  let output_layer: Array4<f32> = concatenate(Axis(axis as usize), &[input_1.view(), input_2.view()]).unwrap();
+  //dbg!("Relu: {:?}", output_layer.clone());
 
-  //dbg!("Concatenate: {:?}", output_layer);
-  println!("Concatenate, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+  println!("Relu, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
 
-  let mut map_mut = output_container.lock().unwrap();
-  map_mut.insert(node.output[0].clone(), (None, Some(output_layer)));
+  Ok(vec![output_layer])
 }
 
 /*
-This function do the dropout
-  -It takes 4 parameters:
-    ~ output_container: contains the partial results calculated by inferences operations (i.e. convolution, relu)
-    ~ node: node on which dropout has to be executed
+This function do the leaky relu
+  -It takes 2 parameters:
+    ~ node: node on which leaky relu has to be executed
+    ~ inputs: values of the node inputs
 */
-fn drop_out_op(output_container: &Arc<Mutex<HashMap<String, (Option<Array2<f32>>, Option<Array4<f32>>)>>>, node: &NodeProto) {
-  let map = output_container.lock().unwrap();
-  let input = Array4::from(map.get(node.input[0].as_str()).unwrap().1.clone().unwrap());
-
-  drop(map);
-
-  let mut ratio: Option<f32> = None;
-  for attr in &node.attribute {
-    if attr.name.is_some() {
-      ratio = match attr.name.as_ref().unwrap().as_str() {
-        "ratio" => Some(attr.f.unwrap()),
-        _ => panic!("ATTRIBUTE NAME FOR DROP OUT NOT FOUND, {}", attr.name.as_ref().unwrap().as_str())
-      };
-    }
-  }
-  let output_layer = dropout(input, ratio, None, false, false);
-
-  /*
-   if output_layer.1.is_some() {
-     println!("Mask: {:?}", output_layer.1.unwrap());
-     hashmap_outputs_to_inputs.insert(outputs[1].clone(), output_layer.1.unwrap());
-   }
-  */
-
-  //dbg!("Dropout: {:?}", output_layer.0.clone());
-  println!("Dropout, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
-
-  let mut map_mut = output_container.lock().unwrap();
-  map_mut.insert(node.output[0].clone(), (None, Some(output_layer.0)));
+fn leaky_relu_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let alpha = node.attributes.float("alpha").unwrap_or(0.01);
+  activation_node_op(node, inputs, Activation::LeakyRelu { alpha }, "LeakyRelu", &["alpha"])
 }
 
 /*
-This function do the global average pool
-  -It takes 4 parameters:
-    ~ output_container: contains the partial results calculated by inferences operations (i.e. convolution, relu)
-    ~ node: node on which global average pool has to be executed
+This function do the parametric relu
+  -It takes 2 parameters:
+    ~ node: node on which prelu has to be executed
+    ~ inputs: values of the node inputs (input and slope)
 */
-fn global_average_pool_op(output_container: &Arc<Mutex<HashMap<String, (Option<Array2<f32>>, Option<Array4<f32>>)>>>, node: &NodeProto) {
-  let map = output_container.lock().unwrap();
-  let input = Array4::from(map.get(node.input[0].as_str()).unwrap().1.clone().unwrap());
+fn prelu_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &[])?;
+  let input = get_input_tensor(0, node, inputs)?;
+  let slope = get_input_tensor(1, node, inputs)?;
 
-  drop(map);
-
-  let output_layer = global_average_pool(input);
+  let output_layer = prelu(input, slope)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
 
   //dbg!(output_layer);
-  println!("GlobalAveragePool, done!");
+  println!("PRelu, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
 
-  let mut map_mut = output_container.lock().unwrap();
-  map_mut.insert(node.output[0].clone(), (None, Some(output_layer)));
+  Ok(vec![output_layer])
 }
 
 /*
-This function do the soft max
+This function do the elu
   -It takes 2 parameters:
-    ~ output_container: contains the partial results calculated by inferences operations (i.e. convolution, relu)
-    ~ node: node on which softmax has to be executed
+    ~ node: node on which elu has to be executed
+    ~ inputs: values of the node inputs
 */
-fn softmax_op(output_container: &Arc<Mutex<HashMap<String, (Option<Array2<f32>>, Option<Array4<f32>>)>>>, node: &NodeProto) {
-  let map = output_container.lock().unwrap();
-  let input = Array4::from(map.get(node.input[0].as_str()).unwrap().1.clone().unwrap());
+fn elu_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let alpha = node.attributes.float("alpha").unwrap_or(1.);
+  activation_node_op(node, inputs, Activation::Elu { alpha }, "Elu", &["alpha"])
+}
 
-  drop(map);
+/*
+This function do the selu
+  -It takes 2 parameters:
+    ~ node: node on which selu has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn selu_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let alpha = node.attributes.float("alpha").unwrap_or(1.673_263_2);
+  let gamma = node.attributes.float("gamma").unwrap_or(1.050_701);
+  activation_node_op(node, inputs, Activation::Selu { alpha, gamma }, "Selu", &["alpha", "gamma"])
+}
 
-  let result = softmax(input, None);
+/*
+This function do the sigmoid
+  -It takes 2 parameters:
+    ~ node: node on which sigmoid has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn sigmoid_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  activation_node_op(node, inputs, Activation::Sigmoid, "Sigmoid", &[])
+}
 
-  println!("Softmax, done!");
-  //dbg!(result.clone());
+/*
+This function do the hard sigmoid
+  -It takes 2 parameters:
+    ~ node: node on which hard sigmoid has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn hard_sigmoid_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let alpha = node.attributes.float("alpha").unwrap_or(0.2);
+  let beta = node.attributes.float("beta").unwrap_or(0.5);
+  activation_node_op(node, inputs, Activation::HardSigmoid { alpha, beta }, "HardSigmoid", &["alpha", "beta"])
+}
 
-  let mut i = 0;
-  let mut best_class_index = 0;
-  let mut best_class_percentage = f32::min_value();
-  while i < result.len_of(Axis(1)){
-    if result[[0, i]] > best_class_percentage {
-      best_class_percentage = result[[0, i]];
-      best_class_index = i+1;
-    }
-    i += 1;
-  }
-  println!("\nSqueezenet1.0-8 Inference results: Class {}-nth predicted.\nActual Data: {:?}", best_class_index, result.clone());
+/*
+This function do the hard swish
+  -It takes 2 parameters:
+    ~ node: node on which hard swish has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn hard_swish_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  activation_node_op(node, inputs, Activation::HardSwish, "HardSwish", &[])
 }
 
 /*
-This function do the reshape
-  -It takes 4 parameters:
-    ~ output_container: contains the partial results calculated by inferences operations (i.e. convolution, relu)
-    ~ node: node on which reshape has to be executed
-    ~ model_inputs: inputs of the onnx model
-    ~ model_initializers: initializers of the onnx model
-*/
-#[allow(unused_assignments)]
-#[allow(unused_variables)]
-fn reshape_op(output_container: &Arc<Mutex<HashMap<String, (Option<Array2<f32>>, Option<Array4<f32>>)>>>, node: &NodeProto, model_inputs: &Vec<ValueInfoProto>, model_initializers: &Vec<TensorProto>) {
-  let mut data: Array4<f32> = Default::default();
-  if already_into_initializer(model_initializers, node.input[0].as_str()) {
-    let (arr4, _, _, _, _) = get_stored_tensor_for_convolution(0, node, model_inputs, model_initializers);
-    data = arr4.unwrap();
-  } else {
-    let map = output_container.lock().unwrap();
-    data = Array4::from(map.get(node.input[0].as_str()).unwrap().1.clone().unwrap());
-    drop(map);
-  }
+This function do the hyperbolic tangent
+  -It takes 2 parameters:
+    ~ node: node on which tanh has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn tanh_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  activation_node_op(node, inputs, Activation::Tanh, "Tanh", &[])
+}
 
-  let mut shape: Array1<i64> = Default::default();
-  if already_into_initializer(model_initializers, node.input[1].as_str()) {
-    let (_, _, _, _, arr1) = get_stored_tensor_for_convolution(1, node, model_inputs, model_initializers);
-    shape = arr1.unwrap();
-  } else {
-    panic!("Cannot get Shape for Reshape operation");
-  }
+/*
+This function do the softplus
+  -It takes 2 parameters:
+    ~ node: node on which softplus has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn softplus_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  activation_node_op(node, inputs, Activation::Softplus, "Softplus", &[])
+}
 
-  let output_layer: Array2<f32> = reshape(data, shape, None);
+/*
+This function do the softsign
+  -It takes 2 parameters:
+    ~ node: node on which softsign has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn softsign_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  activation_node_op(node, inputs, Activation::Softsign, "Softsign", &[])
+}
 
-  //dbg!("Reshape: {:?}", output_layer.clone());
-  println!("Reshape, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+/*
+This function do the gelu, exact or with the tanh approximation
+  -It takes 2 parameters:
+    ~ node: node on which gelu has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn gelu_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let tanh_approximation = match node.attributes.string("approximate").unwrap_or("none") {
+    "none" => false,
+    "tanh" => true,
+    other => return Err(InferenceError::bad_attribute(&node.proto, "approximate", format!("unknown approximation {}", other)))
+  };
+  activation_node_op(node, inputs, Activation::Gelu { tanh_approximation }, "Gelu", &["approximate"])
+}
 
-  let mut map_mut = output_container.lock().unwrap();
-  map_mut.insert(node.output[0].clone(), (Some(output_layer), None));
+/*
+This function do the mish
+  -It takes 2 parameters:
+    ~ node: node on which mish has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn mish_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  activation_node_op(node, inputs, Activation::Mish, "Mish", &[])
 }
 
 /*
-This function do the add
-  -It takes 4 parameters:
-    ~ output_container: contains the partial results calculated by inferences operations (i.e. convolution, relu)
-    ~ node: node on which add has to be executed
-    ~ model_inputs: inputs of the onnx model
-    ~ model_initializers: initializers of the onnx model
-*/
-#[allow(unused_assignments)]
-fn add_op(output_container: &Arc<Mutex<HashMap<String, (Option<Array2<f32>>, Option<Array4<f32>>)>>>, node: &NodeProto, model_inputs: &Vec<ValueInfoProto>, model_initializers: &Vec<TensorProto>) {
-  let mut input_1_arr_4: Array4<f32> = Default::default();
-  let mut input_1_arr_2: Array2<f32> = Default::default();
-  let mut input_2_arr_3: Array3<f32> = Default::default();
-  let mut input_2_arr_2: Array2<f32> = Default::default();
-
-  let map = output_container.lock().unwrap();
-
-  if already_into_initializer(model_initializers, node.input[0].as_str()) {
-    let (arr4, _, arr2, _, _) = get_stored_tensor_for_convolution(0, node, model_inputs, model_initializers);
-    match arr4 {
-      Some(arr4) => input_1_arr_4 = arr4,
-      None => {
-        match arr2 {
-          Some(arr2) => input_1_arr_2 = arr2,
-          None => panic!("Cannot get input 1 for Add operation from initializers")
-        }
-      }
-    };
-  } else {
-    match map.get(node.input[0].as_str()).unwrap().0.clone() {
-      Some(arr2) => input_1_arr_2 = arr2,
-      None => {
-        match map.get(node.input[0].as_str()).unwrap().1.clone() {
-          Some(arr4) => input_1_arr_4 = arr4,
-          None => panic!("Cannot get input 1 for Add operation from hashmap input/output")
-        }
-      }
-    }
-  }
+This function do the thresholded relu
+  -It takes 2 parameters:
+    ~ node: node on which thresholded relu has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn thresholded_relu_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let alpha = node.attributes.float("alpha").unwrap_or(1.);
+  activation_node_op(node, inputs, Activation::ThresholdedRelu { alpha }, "ThresholdedRelu", &["alpha"])
+}
 
-  drop(map);
+fn activation_node_op(node: &PreparedNode, inputs: &[Option<&Tensor>], function: Activation, op_name: &str, known: &[&str]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, known)?;
+  let input = get_input_tensor(0, node, inputs)?;
 
-  if already_into_initializer(model_initializers, node.input[1].as_str()) {
-    let (_, arr3, arr2, _, _) = get_stored_tensor_for_convolution(1, node, model_inputs, model_initializers);
-    match arr3 {
-      Some(arr3) => input_2_arr_3 = arr3,
-      None => {
-        match arr2 {
-          Some(arr2) => input_2_arr_2 = arr2,
-          None => panic!("Cannot get input 2 for Add operation from initializes")
-        }
-      }
-    }
-  } else {
-    panic!("Cannot get input 2 for Add operation");
-  }
-
-  let mut map_mut = output_container.lock().unwrap();
-
-  let mut output_layer_2: Array2<f32> = Default::default();
-  let mut output_layer_4: Array4<f32> = Default::default();
-  if input_1_arr_4.len() > 0{
-    output_layer_4 = input_1_arr_4+input_2_arr_3;
-    //dbg!(output_layer_4.clone());
-    println!("Add, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
-    map_mut.insert(node.output[0].clone(), (None, Some(output_layer_4)));
-  }else{
-    output_layer_2 = input_1_arr_2+input_2_arr_2;
-    //dbg!("Add: {:?}", output_layer_2.clone());
-    println!("Add, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
-    map_mut.insert(node.output[0].clone(), (Some(output_layer_2.clone()), None));
-
-    let mut i = 0;
-    let mut best_class_index = 0;
-    let mut best_class_percentage = f32::min_value();
-    while i < output_layer_2.clone().len_of(Axis(1)){
-      if output_layer_2[[0, i]] > best_class_percentage {
-        best_class_percentage = output_layer_2[[0, i]];
-        best_class_index = i+1;
-      }
-      i += 1;
-    }
-    println!("\nMNist-8 Inference results: Class {}-nth predicted.\nActual Data: {:?}", best_class_index, output_layer_2);
+  let output_layer = activation(function, input)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
 
-    //output_container.insert(node.output[0].clone(), (Some(output_layer_2), None));
-  }
+  //dbg!("{}: {:?}", op_name, output_layer.clone());
+  println!("{}, done! by {}", op_name, thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
 }
 
 /*
-This function do the mul
+This function do the clip, with min and max as attributes
   -It takes 2 parameters:
-    ~ output_container: contains the partial results calculated by inferences operations (i.e. convolution, relu)
-    ~ node: node on which mul has to be executed
+    ~ node: node on which clip has to be executed
+    ~ inputs: values of the node inputs
 */
-fn mul_op(output_container: &Arc<Mutex<HashMap<String, (Option<Array2<f32>>, Option<Array4<f32>>)>>>, node: &NodeProto) {
-  let map = output_container.lock().unwrap();
+fn clip_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["min", "max"])?;
+  let min = node.attributes.float("min").map(|v| v as f64);
+  let max = node.attributes.float("max").map(|v| v as f64);
+  clip_node_op(node, inputs, min, max)
+}
 
-  let input_1 = Array2::from(map.get(node.input[0].as_str()).unwrap().0.clone().unwrap());
-  let input_2 = Array2::from(map.get(node.input[1].as_str()).unwrap().0.clone().unwrap());
+/*
+This function do the clip, with min and max as optional scalar inputs
+  -It takes 2 parameters:
+    ~ node: node on which clip has to be executed
+    ~ inputs: values of the node inputs (input, min, max)
+*/
+fn clip_op_v11(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &[])?;
+  let bound = |i: usize, name: &str| -> Result<Option<f64>, InferenceError> {
+    match inputs.get(i) {
+      Some(Some(tensor)) if tensor.len() == 1 => Ok(Some(match tensor {
+        Tensor::F64(value) => value.iter().next().copied().unwrap(),
+        Tensor::F32(_) | Tensor::F16(_) => tensor.to_f32().iter().next().copied().unwrap() as f64,
+        _ => tensor.to_i64().iter().next().copied().unwrap() as f64
+      })),
+      Some(Some(tensor)) => Err(InferenceError::shape_mismatch(&node.proto, format!("{} has shape {:?}, expected a scalar", name, tensor.shape()))),
+      _ => Ok(None)
+    }
+  };
+  let min = bound(1, "min")?;
+  let max = bound(2, "max")?;
+  clip_node_op(node, inputs, min, max)
+}
 
-  drop(map);
+fn clip_node_op(node: &PreparedNode, inputs: &[Option<&Tensor>], min: Option<f64>, max: Option<f64>) -> Result<Vec<Tensor>, InferenceError> {
+  let input = get_input_tensor(0, node, inputs)?;
 
-  let output_layer: Array2<f32> = input_1.dot(&input_2);
+  let output_layer = clip(input, min, max)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
 
-  //dbg!("MatMul: {:?}", output_layer.clone());
-  println!("MatMul, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+  //dbg!(output_layer);
+  println!("Clip, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
 
-  let mut map_mut = output_container.lock().unwrap();
-  map_mut.insert(node.output[0].clone(), (Some(output_layer), None));
+  Ok(vec![output_layer])
 }
 
 /*
-This function get the input tensors needed by the convolution
-  -It takes 4 parameters:
-    ~ i: position of the node in the onnx model
-    ~ node: node on which convolution has to be executed
-    ~ model_inputs: inputs of the onnx model
-    ~ model_initializers: initializers of the onnx model
-It returns the input tensors
-*/
-fn get_stored_tensor_for_convolution(i: usize, node: &NodeProto, model_inputs: &Vec<ValueInfoProto>, model_initializers: &Vec<TensorProto>) -> (Option<Array4<f32>>, Option<Array3<f32>>, Option<Array2<f32>>, Option<Array1<f32>>, Option<Array1<i64>>){
-  let shape = search_input_data_shape(model_inputs, &node.input[i]);
-
-  let mut raw_data: Vec<f32> = vec![];
-  let mut raw_data_i64: Vec<i64> = vec![];
-  for init in model_initializers {
-    if init.name.is_some() {
-      if init.name.as_ref().unwrap() == &node.input[i] {
-        if init.raw_data.is_some() {
-          for chunk in init.raw_data.as_ref().unwrap().chunks(4) {
-            let float32 = u8_to_f32(chunk);
-            raw_data.push(float32);
-          }
-        } else if init.float_data.len() > 0 {
-          for float_value in &init.float_data {
-            raw_data.push(*float_value);
-          }
-        } else if init.int64_data.len() > 0 {
-          for int_value in &init.int64_data {
-            raw_data_i64.push(*int_value);
-          }
-        }
-      }
-    }
-  }
-
-  if shape.len() == 4 {
-    (Some(Array4::from_shape_vec((*shape[0] as usize, *shape[1] as usize, *shape[2] as usize, *shape[3] as usize), raw_data).unwrap()), None, None, None, None)
-  } else if shape.len() == 3 {
-    (None, Some(Array3::from_shape_vec((*shape[0] as usize, *shape[1] as usize, *shape[2] as usize), raw_data).unwrap()), None, None, None)
-  } else if shape.len() == 2 {
-    (None, None, Some(Array2::from_shape_vec((*shape[0] as usize, *shape[1] as usize), raw_data).unwrap()), None, None)
-  } else {
-    if raw_data.len() > 0 {
-      (None, None, None, Some(Array1::from_shape_vec(*shape[0] as usize, raw_data).unwrap()), None)
-    }else {
-      (None, None, None, None, Some(Array1::from_shape_vec(*shape[0] as usize, raw_data_i64).unwrap()))
-    }
-  }
+This function do the maxpool
+  -It takes 2 parameters:
+    ~ node: node on which maxpool has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn max_pool_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  pool_node_op(node, inputs, PoolType::Max, "MaxPool")
 }
 
 /*
-This function searches convert u8 to f32
-  -It takes 1 parameters:
-    ~ bytes: number to convert
-  -It returns the correspondent f32 number
-*/
-fn u8_to_f32(bytes: &[u8]) -> f32 {
-  let mut arr = [0; 4];
-  arr.copy_from_slice(bytes);
-  f32::from_le_bytes(arr)
-}
-
-/*
-This function searches the node's input shape(s) into the onnx model.
-  -It takes 2 parameters:
-    ~ model_inputs: list of the inputs
-    ~ input_name: input to search
-  -It returns the list of shapes
-*/
-fn search_input_data_shape<'a>(model_inputs: &'a Vec<ValueInfoProto>, input_name: &str) -> Vec<&'a i64> {
-  let mut shape = vec![];
-  for inp in model_inputs {
-    if inp.name.is_some() {
-      if inp.name.as_ref().unwrap() == input_name {
-        if inp.type_.value.is_some() {
-          match &inp.type_.value.as_ref().unwrap() {
-            Value::TensorType(t) => {
-              if t.shape.is_some() {
-                for el in &t.shape.as_ref().unwrap().dim {
-                  if el.value.is_some() {
-                    match el.value.as_ref().unwrap() {
-                      DimValue(v) => { shape.push(v) }
-                      DimParam(_) => {}
-                    }
-                  }
-                }
-              } else {
-                panic!("ERROR WHILE GETTING SHAPE OF NODE {}", input_name)
-              }
-            }
-            Value::SequenceType(_) => {}
-            Value::MapType(_) => {}
-            Value::OptionalType(_) => {}
-            Value::SparseTensorType(_) => {}
-          };
-        } else {
-          panic!("ERROR WHILE GETTING TYPE_ OF NODE {}", input_name)
-        }
-        break;
-      }
-    }
-  }
-  shape
+This function do the average pool
+  -It takes 2 parameters:
+    ~ node: node on which average pool has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn average_pool_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let count_include_pad = node.attributes.int("count_include_pad").unwrap_or(0) != 0;
+  pool_node_op(node, inputs, PoolType::Average { count_include_pad }, "AveragePool")
 }
 
 /*
-This function searches if a node's input is already in the model's initializers
+This function do the Lp pool
   -It takes 2 parameters:
-    ~ model_initializers: model's initializers
-    ~ input: input to check
-  -It returns true if it's present, false otherwise
+    ~ node: node on which Lp pool has to be executed
+    ~ inputs: values of the node inputs
 */
-fn already_into_initializer(model_initializers: &Vec<TensorProto>, input_name: &str) -> bool {
-  for init in model_initializers {
-    if init.name.is_some() {
-      if init.name.as_ref().unwrap() == input_name {
-        return true;
-      }
-    }
+fn lp_pool_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let p = node.attributes.int("p").unwrap_or(2);
+  if p < 1 {
+    return Err(InferenceError::bad_attribute(&node.proto, "p", format!("{} is not a valid norm", p)));
   }
-  false
+  pool_node_op(node, inputs, PoolType::Lp { p: p as i32 }, "LpPool")
 }
 
 /*
-This function searches if in the model there are node that can be executed in parallel (its shares the same input(s))
-  -It takes 2 parameters:
-    ~ nodes: slice of the nexts nodes
-    ~ input_to_check: name of node's output to check if it's shared among different nodes
-  -It returns the independents nodes and their position in the onnx model
+This function executes a pooling with the layer prepared with the session (or built now if it couldn't be)
+  -It takes 4 parameters:
+    ~ node: considered node
+    ~ inputs: values of the node inputs
+    ~ pool_type: value computed over each window
+    ~ op_name: name printed when the operation is done
 */
-fn search_node_who_shares_input(nodes: &[NodeProto], input_to_check: &String) -> Option<(Vec<Vec<NodeProto>>, Vec<i32>)> {
-  let mut node_shares_input = 0;
-  let mut position = 0;
-  let mut hash_shares: HashMap<i32, NodeProto> = HashMap::new();
-
-  for node in nodes {
-    if node.input.contains(input_to_check) {
-      node_shares_input += 1;
-      hash_shares.insert(position, node.clone());
+fn pool_node_op(node: &PreparedNode, inputs: &[Option<&Tensor>], pool_type: PoolType, op_name: &str) -> Result<Vec<Tensor>, InferenceError> {
+  let input_image = get_input_tensor(0, node, inputs)?;
+
+  let runtime_layer;
+  let conv_layer = match &node.prepared_op {
+    PreparedOp::Pool(layer) => layer,
+    _ => {
+      runtime_layer = build_pool_layer(&node.proto, &node.attributes)?;
+      &runtime_layer
     }
+  };
 
-    position += 1;
+  if input_image.rank() != conv_layer.kernel_size.ndim() + 2 {
+    return Err(InferenceError::shape_mismatch(&node.proto, format!("input {:?} doesn't have {} spatial axes like the kernel", input_image.shape(), conv_layer.kernel_size.ndim())));
+  }
+  if conv_layer.output_size(&input_image.shape()[2..]).is_none() {
+    return Err(InferenceError::shape_mismatch(&node.proto, format!("kernel {:?} is bigger than input {:?}", conv_layer.kernel_size.shape(), input_image.shape())));
   }
+  /* The layer works in float: the double inputs aren't supported */
+  let output_layer = map_float_variant!(node, input_image, [F32], image => match pool_type {
+    PoolType::Max => conv_layer.max_pool(image),
+    PoolType::Average { count_include_pad } => conv_layer.average_pool(image, count_include_pad),
+    PoolType::Lp { p } => conv_layer.lp_pool(image, p),
+  });
 
-  if node_shares_input >= 2 {
-    let mut pos_to_skip: Vec<i32> = hash_shares.keys().cloned().collect();
+  //dbg!("{}: {:?}", op_name, output_layer.clone());
+  println!("{}, done! by {}", op_name, thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
 
-    let mut vec_sequence: Vec<Vec<NodeProto>> = Vec::new();
+  Ok(vec![output_layer])
+}
 
-    for el in &hash_shares {
-      vec_sequence.push(Vec::new());
-      vec_sequence.last_mut().unwrap().push(el.1.clone());
-      let output_to_find = &el.1.output[0];
+/*
+This function do the concatenate (opset 1-3: axis is optional)
+  -It takes 2 parameters:
+    ~ node: node on which concatenate has to be executed
+    ~ inputs: values of the node inputs (any number of tensors, with the same element type)
+*/
+fn concatenate_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  concatenate_node_op(node, inputs, node.attributes.int("axis").unwrap_or(1))
+}
 
-      let counter = el.0 + 1;
+/*
+This function do the concatenate (opset 4 on: axis is required)
+  -It takes 2 parameters:
+    ~ node: node on which concatenate has to be executed
+    ~ inputs: values of the node inputs (any number of tensors, with the same element type)
+*/
+fn concatenate_op_v4(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  match node.attributes.int("axis") {
+    Some(axis) => concatenate_node_op(node, inputs, axis),
+    None => Err(InferenceError::bad_attribute(&node.proto, "axis", "required attribute not set"))
+  }
+}
 
-      for node in &nodes[*el.0 as usize + 1..nodes.len() as usize] {
-        if node.input.contains(output_to_find) {
-          vec_sequence.last_mut().unwrap().push(node.clone());
-          pos_to_skip.push(counter);
-        } else {
-          break;
-          /* Sequence interrupted */
-        }
-      }
-    }
+fn concatenate_node_op(node: &PreparedNode, inputs: &[Option<&Tensor>], axis: i64) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["axis"])?;
+  let tensors = (0..node.proto.input.len())
+    .map(|i| get_input_tensor(i, node, inputs))
+    .collect::<Result<Vec<&Tensor>, InferenceError>>()?;
+  if tensors.is_empty() {
+    return Err(InferenceError::shape_mismatch(&node.proto, "Concat needs at least one input"));
+  }
+  let axis = normalize_axis(&node.proto, axis, tensors[0].rank())?;
+
+  let output_layer = concat(&tensors, axis)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!("Concatenate: {:?}", output_layer);
+  println!("Concatenate, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the dropout (opset 1-10: ratio is an attribute). In inference the input is returned as it is
+  -It takes 2 parameters:
+    ~ node: node on which dropout has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn drop_out_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let input = get_input_tensor(0, node, inputs)?;
+
+  /* is_test (opset 1-6) is ignored: the engine always runs in inference mode */
+  check_attributes(&node.proto, &node.attributes, &["ratio", "is_test"])?;
+  let ratio: Option<f32> = node.attributes.float("ratio");
+  let mask;
+  let output_layer = map_float_variant!(node, input, [F32, F64], x => {
+    let (output, output_mask) = dropout(x.clone(), ratio, None, false, node.proto.output.len() > 1);
+    mask = output_mask;
+    output
+  });
+
+  //dbg!("Dropout: {:?}", output_layer.clone());
+  println!("Dropout, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(dropout_outputs(output_layer, mask))
+}
+
+/*
+This function do the dropout (opset 12 on: ratio and training_mode are optional inputs)
+  -It takes 2 parameters:
+    ~ node: node on which dropout has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn drop_out_op_v12(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let input = get_input_tensor(0, node, inputs)?;
+
+  check_attributes(&node.proto, &node.attributes, &["seed"])?;
+  let ratio: Option<f32> = match inputs.get(1).copied().flatten() {
+    Some(r) if r.len() == 1 => r.to_f32().iter().next().copied(),
+    Some(r) => return Err(InferenceError::shape_mismatch(&node.proto, format!("ratio must be a scalar, got shape {:?}", r.shape()))),
+    None => None
+  };
+  let training_mode = match inputs.get(2).copied().flatten() {
+    Some(t) => t.to_f32().iter().any(|&x| x != 0.0),
+    None => false
+  };
+  let seed = node.attributes.int("seed").map(|s| s as u64);
+  let mask;
+  let output_layer = map_float_variant!(node, input, [F32, F64], x => {
+    let (output, output_mask) = dropout(x.clone(), ratio, seed, training_mode, node.proto.output.len() > 1);
+    mask = output_mask;
+    output
+  });
+
+  println!("Dropout, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(dropout_outputs(output_layer, mask))
+}
+
+/* Output and optional mask of the dropout */
+fn dropout_outputs(output_layer: Tensor, mask: Option<ArrayD<bool>>) -> Vec<Tensor> {
+  let mut outputs = vec![output_layer];
+  if let Some(mask) = mask {
+    outputs.push(Tensor::Bool(mask));
+  }
+  outputs
+}
+
+/*
+This function do the global average pool
+  -It takes 2 parameters:
+    ~ node: node on which global average pool has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn global_average_pool_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let input = get_input_tensor(0, node, inputs)?;
+  if input.rank() < 3 {
+    return Err(InferenceError::shape_mismatch(&node.proto, format!("input {:?} must be (N, C, D1, ..., Dn)", input.shape())));
+  }
+
+  let output_layer = map_float_variant!(node, input, [F32, F64], x => global_average_pool(x.clone()));
+
+  //dbg!(output_layer);
+  println!("GlobalAveragePool, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the global max pool
+  -It takes 2 parameters:
+    ~ node: node on which global max pool has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn global_max_pool_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let input = get_input_tensor(0, node, inputs)?;
+  if input.rank() < 3 {
+    return Err(InferenceError::shape_mismatch(&node.proto, format!("input {:?} must be (N, C, D1, ..., Dn)", input.shape())));
+  }
+
+  let output_layer = map_float_variant!(node, input, [F32, F64], x => global_max_pool(x.clone()));
+
+  //dbg!(output_layer);
+  println!("GlobalMaxPool, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the batch normalization (opset 7-13, inference mode)
+  -It takes 2 parameters:
+    ~ node: node on which batch normalization has to be executed
+    ~ inputs: values of the node inputs (X, scale, B, input_mean, input_var)
+*/
+fn batch_normalization_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["epsilon", "momentum", "spatial"])?;
+  /* spatial = 0 (one parameter for each element of C x D1 x ... x Dn) was removed in opset 9 and isn't supported */
+  if node.attributes.int("spatial").unwrap_or(1) != 1 {
+    return Err(InferenceError::bad_attribute(&node.proto, "spatial", "only spatial = 1 is supported"));
+  }
+  batch_normalization_node_op(node, inputs)
+}
+
+/*
+This function do the batch normalization (opset 14 on: with the training_mode attribute)
+  -It takes 2 parameters:
+    ~ node: node on which batch normalization has to be executed
+    ~ inputs: values of the node inputs (X, scale, B, input_mean, input_var)
+*/
+fn batch_normalization_op_v14(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["epsilon", "momentum", "training_mode"])?;
+  if node.attributes.int("training_mode").unwrap_or(0) != 0 {
+    return Err(InferenceError::bad_attribute(&node.proto, "training_mode", "only the inference mode is supported"));
+  }
+  batch_normalization_node_op(node, inputs)
+}
+
+fn batch_normalization_node_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let input = get_input_tensor(0, node, inputs)?;
+  let (scale, bias) = (get_input_tensor(1, node, inputs)?, get_input_tensor(2, node, inputs)?);
+  let (mean, var) = (get_input_tensor(3, node, inputs)?, get_input_tensor(4, node, inputs)?);
+  let epsilon = node.attributes.float("epsilon").unwrap_or(1e-5);
+
+  /* The parameters are converted to the element type of the input */
+  let output_layer = map_float_variant!(node, input, [F32, F64], x => batch_normalization(
+    x,
+    &into_rank(&node.proto, scale.to_float(), "scale")?,
+    &into_rank(&node.proto, bias.to_float(), "B")?,
+    &into_rank(&node.proto, mean.to_float(), "input_mean")?,
+    &into_rank(&node.proto, var.to_float(), "input_var")?,
+    epsilon,
+  ).map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?);
+
+  //dbg!(output_layer);
+  println!("BatchNormalization, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the instance normalization
+  -It takes 2 parameters:
+    ~ node: node on which instance normalization has to be executed
+    ~ inputs: values of the node inputs (input, scale, B)
+*/
+fn instance_normalization_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["epsilon"])?;
+  let input = get_input_tensor(0, node, inputs)?;
+  let (scale, bias) = (get_input_tensor(1, node, inputs)?, get_input_tensor(2, node, inputs)?);
+  let epsilon = node.attributes.float("epsilon").unwrap_or(1e-5);
+
+  let output_layer = map_float_variant!(node, input, [F32, F64], x => instance_normalization(
+    x,
+    &into_rank(&node.proto, scale.to_float(), "scale")?,
+    &into_rank(&node.proto, bias.to_float(), "B")?,
+    epsilon,
+  ).map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?);
+
+  //dbg!(output_layer);
+  println!("InstanceNormalization, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the layer normalization
+  -It takes 2 parameters:
+    ~ node: node on which layer normalization has to be executed
+    ~ inputs: values of the node inputs (X, Scale, optional B)
+  -The optional outputs Mean and InvStdDev are returned only if the node has them
+*/
+fn layer_normalization_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["axis", "epsilon", "stash_type"])?;
+  let input = get_input_tensor(0, node, inputs)?;
+  let scale = get_input_tensor(1, node, inputs)?;
+  let bias = inputs.get(2).copied().flatten();
+  let axis = normalize_axis(&node.proto, node.attributes.int("axis").unwrap_or(-1), input.rank())?;
+  let epsilon = node.attributes.float("epsilon").unwrap_or(1e-5);
+
+  /* Y has the element type of the input, Mean and InvStdDev are float (stash_type) */
+  let statistics;
+  let output_layer = map_float_variant!(node, input, [F32, F64], x => {
+    let (output, mean, inv_std_dev) = layer_normalization(x, &scale.to_float(), bias.map(|b| b.to_float()).as_ref(), axis, epsilon)
+      .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+    statistics = (mean.mapv(|v| cast::<_, f32>(v).unwrap()), inv_std_dev.mapv(|v| cast::<_, f32>(v).unwrap()));
+    output
+  });
+  let (mean, inv_std_dev) = statistics;
+
+  //dbg!(output_layer);
+  println!("LayerNormalization, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  let mut outputs = vec![output_layer, Tensor::F32(mean), Tensor::F32(inv_std_dev)];
+  outputs.truncate(node.proto.output.len().max(1));
+  Ok(outputs)
+}
+
+/*
+This function do the group normalization (opset 18-20: scale and bias have one value for each group)
+  -It takes 2 parameters:
+    ~ node: node on which group normalization has to be executed
+    ~ inputs: values of the node inputs (X, scale, bias)
+*/
+fn group_normalization_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  group_normalization_node_op(node, inputs, false)
+}
+
+/*
+This function do the group normalization (opset 21 on: scale and bias have one value for each channel)
+  -It takes 2 parameters:
+    ~ node: node on which group normalization has to be executed
+    ~ inputs: values of the node inputs (X, scale, bias)
+*/
+fn group_normalization_op_v21(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  group_normalization_node_op(node, inputs, true)
+}
+
+fn group_normalization_node_op(node: &PreparedNode, inputs: &[Option<&Tensor>], per_channel: bool) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["epsilon", "num_groups", "stash_type"])?;
+  let input = get_input_tensor(0, node, inputs)?;
+  let (scale, bias) = (get_input_tensor(1, node, inputs)?, get_input_tensor(2, node, inputs)?);
+  let num_groups = match node.attributes.int("num_groups") {
+    Some(n) if n > 0 => n as usize,
+    Some(n) => return Err(InferenceError::bad_attribute(&node.proto, "num_groups", format!("{} is not a valid number of groups", n))),
+    None => return Err(InferenceError::bad_attribute(&node.proto, "num_groups", "required attribute not set"))
+  };
+  let epsilon = node.attributes.float("epsilon").unwrap_or(1e-5);
+
+  let output_layer = map_float_variant!(node, input, [F32, F64], x => group_normalization(
+    x,
+    &into_rank(&node.proto, scale.to_float(), "scale")?,
+    &into_rank(&node.proto, bias.to_float(), "bias")?,
+    num_groups,
+    epsilon,
+    per_channel,
+  ).map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?);
+
+  //dbg!(output_layer);
+  println!("GroupNormalization, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the RMS normalization
+  -It takes 2 parameters:
+    ~ node: node on which RMS normalization has to be executed
+    ~ inputs: values of the node inputs (X, scale)
+*/
+fn rms_normalization_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["axis", "epsilon", "stash_type"])?;
+  let input = get_input_tensor(0, node, inputs)?;
+  let scale = get_input_tensor(1, node, inputs)?;
+  let axis = normalize_axis(&node.proto, node.attributes.int("axis").unwrap_or(-1), input.rank())?;
+  let epsilon = node.attributes.float("epsilon").unwrap_or(1e-5);
+
+  let output_layer = map_float_variant!(node, input, [F32, F64], x => rms_normalization(x, &scale.to_float(), axis, epsilon)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?);
+
+  //dbg!(output_layer);
+  println!("RMSNormalization, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
 
-    Some((vec_sequence, pos_to_skip))
+/*
+This function do the reduction op of the node (ReduceSum, ReduceMean, ...)
+  -It takes 4 parameters:
+    ~ node: node on which the reduction has to be executed
+    ~ inputs: values of the node inputs
+    ~ op: reduction to execute
+    ~ axes_input: true if axes is an optional input (opset 13 on for ReduceSum, 18 on for the others), false if it is an attribute
+*/
+fn reduce_node_op(node: &PreparedNode, inputs: &[Option<&Tensor>], op: ReduceOp, axes_input: bool) -> Result<Vec<Tensor>, InferenceError> {
+  let axes: Vec<i64> = if axes_input {
+    check_attributes(&node.proto, &node.attributes, &["keepdims", "noop_with_empty_axes"])?;
+    match inputs.get(1) {
+      Some(Some(axes)) => axes.to_i64().iter().copied().collect(),
+      _ => Vec::new()
+    }
   } else {
-    None
+    check_attributes(&node.proto, &node.attributes, &["axes", "keepdims"])?;
+    node.attributes.ints("axes").unwrap_or_default().to_vec()
+  };
+  let input = get_input_tensor(0, node, inputs)?;
+  if axes.is_empty() && node.attributes.int("noop_with_empty_axes").unwrap_or(0) != 0 {
+    return Ok(vec![input.clone()]);
   }
+  let axes = axes.iter().map(|&axis| normalize_axis(&node.proto, axis, input.rank())).collect::<Result<Vec<usize>, InferenceError>>()?;
+  let keepdims = node.attributes.int("keepdims").unwrap_or(1) != 0;
+
+  let output_layer = reduce(op, input, &axes, keepdims)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!(output_layer);
+  println!("Reduce{:?}, done! by {}", op, thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the arg max
+  -It takes 2 parameters:
+    ~ node: node on which arg max has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn arg_max_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  arg_reduce_node_op(node, inputs, true)
 }
 
 /*
-This function searches if in the model there are node without previous dependencies (they can be executed in a separated threads)
+This function do the arg min
   -It takes 2 parameters:
-    ~ model: smart pointer that contains the onnx model
-    ~ previous_outputs: vector that contains names of the operations that are already been done
-  -It returns the independents nodes and their position in the onnx model
+    ~ node: node on which arg min has to be executed
+    ~ inputs: values of the node inputs
 */
-fn search_node_without_previous_dependencies(model: &Arc<ModelProto>, previous_outputs: Vec<&String>) -> Option<(Vec<NodeProto>, Vec<i32>)> {
-  let mut position = 0;
-  let mut pos_to_skip: Vec<i32> = Vec::new();
-  let mut indipendent_nodes: Vec<NodeProto> = Vec::new();
+fn arg_min_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  arg_reduce_node_op(node, inputs, false)
+}
+
+fn arg_reduce_node_op(node: &PreparedNode, inputs: &[Option<&Tensor>], max: bool) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["axis", "keepdims", "select_last_index"])?;
+  let input = get_input_tensor(0, node, inputs)?;
+  let axis = normalize_axis(&node.proto, node.attributes.int("axis").unwrap_or(0), input.rank())?;
+  let keepdims = node.attributes.int("keepdims").unwrap_or(1) != 0;
+  let select_last_index = node.attributes.int("select_last_index").unwrap_or(0) != 0;
+
+  let output_layer = arg_reduce(input, axis, keepdims, select_last_index, max)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!(output_layer);
+  println!("{}, done! by {}", if max { "ArgMax" } else { "ArgMin" }, thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![Tensor::I64(output_layer)])
+}
+
+/*
+This function do the element-wise unary op of the node (Exp, Log, Sin, ...)
+  -It takes 4 parameters:
+    ~ node: node on which the operation has to be executed
+    ~ inputs: values of the node inputs
+    ~ op: operation to execute
+    ~ op_name: name of the operation, for the log
+*/
+fn unary_node_op(node: &PreparedNode, inputs: &[Option<&Tensor>], op: UnaryOp, op_name: &str) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &[])?;
+  let input = get_input_tensor(0, node, inputs)?;
+
+  let output_layer = unary(op, input)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!("{}: {:?}", op_name, output_layer.clone());
+  println!("{}, done! by {}", op_name, thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the soft max
+  -It takes 2 parameters:
+    ~ node: node on which softmax has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn softmax_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let input = get_input_tensor(0, node, inputs)?;
+
+  check_attributes(&node.proto, &node.attributes, &["axis"])?;
+  let axis: Option<usize> = match node.attributes.int("axis") {
+    Some(value) => Some(normalize_axis(&node.proto, value, input.rank())?),
+    None => None
+  };
+  if axis.is_none() && input.rank() < 1 {
+    return Err(InferenceError::shape_mismatch(&node.proto, "softmax of a scalar"));
+  }
+  let output_layer = map_float_variant!(node, input, [F32, F64], x => softmax(x.clone(), axis));
+
+  //dbg!(output_layer.clone());
+  println!("Softmax, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the soft max (opset 13 on: along the single axis, default -1)
+  -It takes 2 parameters:
+    ~ node: node on which softmax has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn softmax_op_v13(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let input = get_input_tensor(0, node, inputs)?;
+
+  check_attributes(&node.proto, &node.attributes, &["axis"])?;
+  if input.rank() < 1 {
+    return Err(InferenceError::shape_mismatch(&node.proto, "softmax of a scalar"));
+  }
+  let axis = normalize_axis(&node.proto, node.attributes.int("axis").unwrap_or(-1), input.rank())?;
+  let output_layer = map_float_variant!(node, input, [F32, F64], x => softmax_axis(x.clone(), axis));
+
+  println!("Softmax, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the reshape
+  -It takes 2 parameters:
+    ~ node: node on which reshape has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn reshape_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &[])?;
+  reshape_with_allowzero(node, inputs, None)
+}
 
-  for node in &model.graph.node {
-    // let is_contained = node.inputs.inter().all(|&item| previous_outputs.contains(item));
-    let mut is_contained = true;
-    for input in &node.input {
-      if !previous_outputs.contains(&input) {
-        if !already_into_initializer(&model.graph.initializer, input.as_str()) {
-          is_contained = false;
-          break;
-        }
+/*
+This function do the reshape (opset 14 on: with the allowzero attribute)
+  -It takes 2 parameters:
+    ~ node: node on which reshape has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn reshape_op_v14(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["allowzero"])?;
+  reshape_with_allowzero(node, inputs, node.attributes.int("allowzero").map(|a| a as usize))
+}
+
+fn reshape_with_allowzero(node: &PreparedNode, inputs: &[Option<&Tensor>], allowzero: Option<usize>) -> Result<Vec<Tensor>, InferenceError> {
+  let data = get_input_tensor(0, node, inputs)?;
+  let shape: Array1<i64> = into_rank(&node.proto, get_input_tensor(1, node, inputs)?.to_i64(), "shape")?;
+
+  let output_layer = reshape(data, &shape.to_vec(), allowzero.unwrap_or(0) != 0)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!("Reshape: {:?}", output_layer.clone());
+  println!("Reshape, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the transpose
+  -It takes 2 parameters:
+    ~ node: node on which transpose has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn transpose_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["perm"])?;
+  let input = get_input_tensor(0, node, inputs)?;
+  let perm: Option<Vec<usize>> = match node.attributes.ints("perm") {
+    Some(perm) => Some(perm.iter().map(|&axis| normalize_axis(&node.proto, axis, input.rank())).collect::<Result<Vec<usize>, InferenceError>>()?),
+    None => None
+  };
+
+  let output_layer = transpose(input, perm.as_deref())
+    .map_err(|message| InferenceError::bad_attribute(&node.proto, "perm", message))?;
+
+  //dbg!(output_layer);
+  println!("Transpose, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the flatten
+  -It takes 2 parameters:
+    ~ node: node on which flatten has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn flatten_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["axis"])?;
+  let input = get_input_tensor(0, node, inputs)?;
+  /* axis can be the rank itself: the output is a single row */
+  let axis = normalize_axis(&node.proto, node.attributes.int("axis").unwrap_or(1), input.rank() + 1)?;
+
+  let output_layer = flatten(input, axis);
+
+  //dbg!(output_layer);
+  println!("Flatten, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the squeeze (opset 1-12: axes is an attribute)
+  -It takes 2 parameters:
+    ~ node: node on which squeeze has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn squeeze_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["axes"])?;
+  let axes: Vec<i64> = node.attributes.ints("axes").unwrap_or_default().to_vec();
+  squeeze_node_op(node, inputs, &axes)
+}
+
+/*
+This function do the squeeze (opset 13 on: axes is an optional input)
+  -It takes 2 parameters:
+    ~ node: node on which squeeze has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn squeeze_op_v13(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &[])?;
+  let axes: Vec<i64> = match inputs.get(1) {
+    Some(Some(axes)) => axes.to_i64().iter().copied().collect(),
+    _ => Vec::new()
+  };
+  squeeze_node_op(node, inputs, &axes)
+}
+
+fn squeeze_node_op(node: &PreparedNode, inputs: &[Option<&Tensor>], axes: &[i64]) -> Result<Vec<Tensor>, InferenceError> {
+  let input = get_input_tensor(0, node, inputs)?;
+  let axes = axes.iter().map(|&axis| normalize_axis(&node.proto, axis, input.rank())).collect::<Result<Vec<usize>, InferenceError>>()?;
+
+  let output_layer = squeeze(input, &axes)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!(output_layer);
+  println!("Squeeze, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the unsqueeze (opset 1-12: axes is an attribute)
+  -It takes 2 parameters:
+    ~ node: node on which unsqueeze has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn unsqueeze_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["axes"])?;
+  let axes: Vec<i64> = node.attributes.ints("axes").unwrap_or_default().to_vec();
+  unsqueeze_node_op(node, inputs, &axes)
+}
+
+/*
+This function do the unsqueeze (opset 13 on: axes is an input)
+  -It takes 2 parameters:
+    ~ node: node on which unsqueeze has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn unsqueeze_op_v13(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &[])?;
+  let axes: Vec<i64> = get_input_tensor(1, node, inputs)?.to_i64().iter().copied().collect();
+  unsqueeze_node_op(node, inputs, &axes)
+}
+
+fn unsqueeze_node_op(node: &PreparedNode, inputs: &[Option<&Tensor>], axes: &[i64]) -> Result<Vec<Tensor>, InferenceError> {
+  let input = get_input_tensor(0, node, inputs)?;
+  /* The axes refer to the dimensions of the output */
+  let rank = input.rank() + axes.len();
+  let axes = axes.iter().map(|&axis| normalize_axis(&node.proto, axis, rank)).collect::<Result<Vec<usize>, InferenceError>>()?;
+
+  let output_layer = unsqueeze(input, &axes)
+    .map_err(|message| InferenceError::bad_attribute(&node.proto, "axes", message))?;
+
+  //dbg!(output_layer);
+  println!("Unsqueeze, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the expand
+  -It takes 2 parameters:
+    ~ node: node on which expand has to be executed
+    ~ inputs: values of the node inputs (input and shape)
+*/
+fn expand_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &[])?;
+  let input = get_input_tensor(0, node, inputs)?;
+  let shape: Array1<i64> = into_rank(&node.proto, get_input_tensor(1, node, inputs)?.to_i64(), "shape")?;
+
+  let output_layer = expand(input, &shape.to_vec())
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!(output_layer);
+  println!("Expand, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the tile
+  -It takes 2 parameters:
+    ~ node: node on which tile has to be executed
+    ~ inputs: values of the node inputs (input and repeats)
+*/
+fn tile_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &[])?;
+  let input = get_input_tensor(0, node, inputs)?;
+  let repeats: Array1<i64> = into_rank(&node.proto, get_input_tensor(1, node, inputs)?.to_i64(), "repeats")?;
+
+  let output_layer = tile(input, &repeats.to_vec())
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!(output_layer);
+  println!("Tile, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the identity
+  -It takes 2 parameters:
+    ~ node: node on which identity has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn identity_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &[])?;
+  let output_layer = get_input_tensor(0, node, inputs)?.clone();
+
+  println!("Identity, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the slice (opset 1-9: starts, ends and axes are attributes)
+  -It takes 2 parameters:
+    ~ node: node on which slice has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn slice_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["starts", "ends", "axes"])?;
+  let starts: Vec<i64> = node.attributes.ints("starts").unwrap_or_default().to_vec();
+  let ends: Vec<i64> = node.attributes.ints("ends").unwrap_or_default().to_vec();
+  let axes: Option<Vec<i64>> = node.attributes.ints("axes").map(|axes| axes.to_vec());
+  slice_node_op(node, inputs, &starts, &ends, axes, None)
+}
+
+/*
+This function do the slice (opset 10 on: starts, ends, axes and steps are inputs, the last two optional)
+  -It takes 2 parameters:
+    ~ node: node on which slice has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn slice_op_v10(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &[])?;
+  let starts: Vec<i64> = get_indices_tensor(1, node, inputs)?.iter().copied().collect();
+  let ends: Vec<i64> = get_indices_tensor(2, node, inputs)?.iter().copied().collect();
+  let axes: Option<Vec<i64>> = match inputs.get(3) {
+    Some(Some(_)) => Some(get_indices_tensor(3, node, inputs)?.iter().copied().collect()),
+    _ => None
+  };
+  let steps: Option<Vec<i64>> = match inputs.get(4) {
+    Some(Some(_)) => Some(get_indices_tensor(4, node, inputs)?.iter().copied().collect()),
+    _ => None
+  };
+  slice_node_op(node, inputs, &starts, &ends, axes, steps)
+}
+
+fn slice_node_op(node: &PreparedNode, inputs: &[Option<&Tensor>], starts: &[i64], ends: &[i64], axes: Option<Vec<i64>>, steps: Option<Vec<i64>>) -> Result<Vec<Tensor>, InferenceError> {
+  let input = get_input_tensor(0, node, inputs)?;
+  /* By default the first axes are sliced, with step 1 */
+  let axes: Vec<usize> = match axes {
+    Some(axes) => axes.iter().map(|&axis| normalize_axis(&node.proto, axis, input.rank())).collect::<Result<Vec<usize>, InferenceError>>()?,
+    None => (0..starts.len()).collect()
+  };
+  let steps: Vec<i64> = steps.unwrap_or_else(|| vec![1; starts.len()]);
+
+  let output_layer = slice(input, starts, ends, &axes, &steps)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!(output_layer);
+  println!("Slice, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the gather
+  -It takes 2 parameters:
+    ~ node: node on which gather has to be executed
+    ~ inputs: values of the node inputs (data and indices)
+*/
+fn gather_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["axis"])?;
+  let data = get_input_tensor(0, node, inputs)?;
+  let indices = get_indices_tensor(1, node, inputs)?;
+  let axis = normalize_axis(&node.proto, node.attributes.int("axis").unwrap_or(0), data.rank())?;
+
+  let output_layer = gather(data, &indices, axis)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!(output_layer);
+  println!("Gather, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the gather elements
+  -It takes 2 parameters:
+    ~ node: node on which gather elements has to be executed
+    ~ inputs: values of the node inputs (data and indices)
+*/
+fn gather_elements_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["axis"])?;
+  let data = get_input_tensor(0, node, inputs)?;
+  let indices = get_indices_tensor(1, node, inputs)?;
+  let axis = normalize_axis(&node.proto, node.attributes.int("axis").unwrap_or(0), data.rank())?;
+
+  let output_layer = gather_elements(data, &indices, axis)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!(output_layer);
+  println!("GatherElements, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the gather nd
+  -It takes 2 parameters:
+    ~ node: node on which gather nd has to be executed
+    ~ inputs: values of the node inputs (data and indices)
+*/
+fn gather_nd_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["batch_dims"])?;
+  let data = get_input_tensor(0, node, inputs)?;
+  let indices = get_indices_tensor(1, node, inputs)?;
+  let batch_dims = node.attributes.int("batch_dims").unwrap_or(0);
+  if batch_dims < 0 {
+    return Err(InferenceError::bad_attribute(&node.proto, "batch_dims", format!("batch_dims {} is negative", batch_dims)));
+  }
+
+  let output_layer = gather_nd(data, &indices, batch_dims as usize)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!(output_layer);
+  println!("GatherND, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the scatter nd
+  -It takes 2 parameters:
+    ~ node: node on which scatter nd has to be executed
+    ~ inputs: values of the node inputs (data, indices and updates)
+*/
+fn scatter_nd_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["reduction"])?;
+  let data = get_input_tensor(0, node, inputs)?;
+  let indices = get_indices_tensor(1, node, inputs)?;
+  let updates = get_input_tensor(2, node, inputs)?;
+  let reduction = match node.attributes.string("reduction").unwrap_or("none") {
+    "none" => ScatterReduction::None,
+    "add" => ScatterReduction::Add,
+    "mul" => ScatterReduction::Mul,
+    "max" => ScatterReduction::Max,
+    "min" => ScatterReduction::Min,
+    other => return Err(InferenceError::bad_attribute(&node.proto, "reduction", format!("unknown reduction {}", other)))
+  };
+
+  let output_layer = scatter_nd(data, &indices, updates, reduction)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!(output_layer);
+  println!("ScatterND, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the split (opset 2-12: the sizes of the parts are an attribute)
+  -It takes 2 parameters:
+    ~ node: node on which split has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn split_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["axis", "split"])?;
+  let sizes: Option<Vec<i64>> = node.attributes.ints("split").map(|split| split.to_vec());
+  split_node_op(node, inputs, sizes, node.proto.output.len())
+}
+
+/*
+This function do the split (opset 13 on: the sizes of the parts are an optional input, from opset 18 num_outputs
+gives the number of parts when they are missing)
+  -It takes 2 parameters:
+    ~ node: node on which split has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn split_op_v13(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["axis", "num_outputs"])?;
+  let sizes: Option<Vec<i64>> = match inputs.get(1) {
+    Some(Some(_)) => Some(get_indices_tensor(1, node, inputs)?.iter().copied().collect()),
+    _ => None
+  };
+  let parts = match node.attributes.int("num_outputs") {
+    Some(parts) if parts < 1 => return Err(InferenceError::bad_attribute(&node.proto, "num_outputs", format!("num_outputs {} is not positive", parts))),
+    Some(parts) => parts as usize,
+    None => node.proto.output.len()
+  };
+  split_node_op(node, inputs, sizes, parts)
+}
+
+fn split_node_op(node: &PreparedNode, inputs: &[Option<&Tensor>], sizes: Option<Vec<i64>>, parts: usize) -> Result<Vec<Tensor>, InferenceError> {
+  let input = get_input_tensor(0, node, inputs)?;
+  let axis = normalize_axis(&node.proto, node.attributes.int("axis").unwrap_or(0), input.rank())?;
+  let sizes: Vec<usize> = match sizes {
+    Some(sizes) if sizes.iter().any(|&size| size < 0) => return Err(InferenceError::shape_mismatch(&node.proto, format!("negative part in {:?}", sizes))),
+    Some(sizes) => sizes.iter().map(|&size| size as usize).collect(),
+    None => split_sizes(input.shape()[axis], parts)
+  };
+
+  let outputs = split(input, axis, &sizes)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!(outputs);
+  println!("Split, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(outputs)
+}
+
+/*
+This function do the pad (opset 2-10: pads and the constant value are attributes)
+  -It takes 2 parameters:
+    ~ node: node on which pad has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn pad_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["mode", "pads", "value"])?;
+  let pads: Vec<i64> = node.attributes.ints("pads").unwrap_or_default().to_vec();
+  let value = Tensor::F32(ArrayD::from_elem(IxDyn(&[]), node.attributes.float("value").unwrap_or(0.)));
+  pad_node_op(node, inputs, &pads, Some(&value))
+}
+
+/*
+This function do the pad (opset 11 on: pads and the constant value are inputs, from opset 18 the optional input axes
+selects the padded dimensions)
+  -It takes 2 parameters:
+    ~ node: node on which pad has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn pad_op_v11(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["mode"])?;
+  let rank = get_input_tensor(0, node, inputs)?.rank();
+  let pads: Vec<i64> = get_indices_tensor(1, node, inputs)?.iter().copied().collect();
+  let pads: Vec<i64> = match inputs.get(3) {
+    Some(Some(_)) => {
+      let axes = get_indices_tensor(3, node, inputs)?.iter().map(|&axis| normalize_axis(&node.proto, axis, rank)).collect::<Result<Vec<usize>, InferenceError>>()?;
+      if pads.len() != 2 * axes.len() {
+        return Err(InferenceError::shape_mismatch(&node.proto, format!("pads {:?} must have 2 values for each axis of {:?}", pads, axes)));
+      }
+      /* The dimensions not in axes aren't padded */
+      let mut full = vec![0; 2 * rank];
+      for (i, &axis) in axes.iter().enumerate() {
+        full[axis] = pads[i];
+        full[rank + axis] = pads[axes.len() + i];
       }
+      full
     }
+    _ => pads
+  };
+  pad_node_op(node, inputs, &pads, inputs.get(2).copied().flatten())
+}
 
-    if is_contained {
-      pos_to_skip.push(position);
-      indipendent_nodes.push(node.clone());
-    }
+fn pad_node_op(node: &PreparedNode, inputs: &[Option<&Tensor>], pads: &[i64], constant_value: Option<&Tensor>) -> Result<Vec<Tensor>, InferenceError> {
+  let input = get_input_tensor(0, node, inputs)?;
+  let mode = match node.attributes.string("mode").unwrap_or("constant") {
+    "constant" => PadMode::Constant,
+    "reflect" => PadMode::Reflect,
+    "edge" => PadMode::Edge,
+    "wrap" => PadMode::Wrap,
+    other => return Err(InferenceError::bad_attribute(&node.proto, "mode", format!("unknown mode {}", other)))
+  };
+
+  let output_layer = pad(input, pads, mode, constant_value)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!(output_layer);
+  println!("Pad, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the add
+  -It takes 2 parameters:
+    ~ node: node on which add has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn add_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  binary_node_op(node, inputs, BinaryOp::Add, "Add")
+}
+
+/*
+This function do the sub
+  -It takes 2 parameters:
+    ~ node: node on which sub has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn sub_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  binary_node_op(node, inputs, BinaryOp::Sub, "Sub")
+}
+
+/*
+This function do the element-wise mul
+  -It takes 2 parameters:
+    ~ node: node on which mul has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn multiply_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  binary_node_op(node, inputs, BinaryOp::Mul, "Mul")
+}
+
+/*
+This function do the div
+  -It takes 2 parameters:
+    ~ node: node on which div has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn div_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  binary_node_op(node, inputs, BinaryOp::Div, "Div")
+}
+
+/*
+This function do the pow
+  -It takes 2 parameters:
+    ~ node: node on which pow has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn pow_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  binary_node_op(node, inputs, BinaryOp::Pow, "Pow")
+}
 
-    position += 1;
+/*
+This function do the mod
+  -It takes 2 parameters:
+    ~ node: node on which mod has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn mod_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let fmod = node.attributes.int("fmod").unwrap_or(0) != 0;
+  binary_node_op(node, inputs, BinaryOp::Mod { fmod }, "Mod")
+}
+
+/*
+This function do the max (of any number of inputs)
+  -It takes 2 parameters:
+    ~ node: node on which max has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn max_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  binary_node_op(node, inputs, BinaryOp::Max, "Max")
+}
+
+/*
+This function do the min (of any number of inputs)
+  -It takes 2 parameters:
+    ~ node: node on which min has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn min_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  binary_node_op(node, inputs, BinaryOp::Min, "Min")
+}
+
+/*
+This function executes an element-wise operation with broadcasting, folding the inputs from the first one
+(Max and Min accept any number of inputs, the other operations exactly 2)
+  -It takes 4 parameters:
+    ~ node: considered node
+    ~ inputs: values of the node inputs, computed at run time or taken from the initializers
+    ~ op: operation
+    ~ op_name: name printed when the operation is done
+*/
+fn binary_node_op(node: &PreparedNode, inputs: &[Option<&Tensor>], op: BinaryOp, op_name: &str) -> Result<Vec<Tensor>, InferenceError> {
+  let known: &[&str] = if matches!(op, BinaryOp::Mod { .. }) { &["fmod"] } else { &[] };
+  check_attributes(&node.proto, &node.attributes, known)?;
+  let variadic = matches!(op, BinaryOp::Max | BinaryOp::Min);
+  let count = if variadic { inputs.len().max(1) } else { 2 };
+
+  let mut output_layer: Tensor = get_input_tensor(0, node, inputs)?.clone();
+  for i in 1..count {
+    output_layer = binary(op, &output_layer, get_input_tensor(i, node, inputs)?)
+      .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
   }
 
-  if pos_to_skip.len() < 2 {
-    None
-  } else {
-    Some((indipendent_nodes, pos_to_skip))
+  //dbg!("{}: {:?}", op_name, output_layer.clone());
+  println!("{}, done! by {}", op_name, thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the mul (matrix product with the numpy semantics, on operands of any rank)
+  -It takes 2 parameters:
+    ~ node: node on which mul has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn mul_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  let input_1 = get_input_tensor(0, node, inputs)?;
+  let input_2 = get_input_tensor(1, node, inputs)?;
+
+  let output_layer = matmul(input_1, input_2)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!("MatMul: {:?}", output_layer.clone());
+  println!("MatMul, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function do the gemm (opset 7-10: C is required)
+  -It takes 2 parameters:
+    ~ node: node on which gemm has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn gemm_op(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  get_input_tensor(2, node, inputs)?;
+  gemm_op_v11(node, inputs)
+}
+
+/*
+This function do the gemm (opset 11 on: C is optional)
+  -It takes 2 parameters:
+    ~ node: node on which gemm has to be executed
+    ~ inputs: values of the node inputs
+*/
+fn gemm_op_v11(node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+  check_attributes(&node.proto, &node.attributes, &["alpha", "beta", "transA", "transB"])?;
+  let input_1 = get_input_tensor(0, node, inputs)?;
+  let input_2 = get_input_tensor(1, node, inputs)?;
+  let input_3 = inputs.get(2).copied().flatten();
+
+  let alpha = node.attributes.float("alpha").unwrap_or(1.0);
+  let beta = node.attributes.float("beta").unwrap_or(1.0);
+  let trans_a = node.attributes.int("transA").unwrap_or(0) != 0;
+  let trans_b = node.attributes.int("transB").unwrap_or(0) != 0;
+
+  let output_layer = gemm(input_1, input_2, input_3, alpha, beta, trans_a, trans_b)
+    .map_err(|message| InferenceError::shape_mismatch(&node.proto, message))?;
+
+  //dbg!("Gemm: {:?}", output_layer.clone());
+  println!("Gemm, done! by {}", thread::current().name().unwrap_or("PROCESSO PRINCIPALE"));
+
+  Ok(vec![output_layer])
+}
+
+/*
+This function get an input tensor of the node
+  -It takes 3 parameters:
+    ~ i: position of the input in the node
+    ~ node: considered node
+    ~ inputs: values of the node inputs
+  -It returns the input tensor, or MissingInput if it isn't provided
+*/
+fn get_input_tensor<'a>(i: usize, node: &PreparedNode, inputs: &[Option<&'a Tensor>]) -> Result<&'a Tensor, InferenceError> {
+  match inputs.get(i) {
+    Some(Some(tensor)) => Ok(tensor),
+    _ => Err(InferenceError::missing_input(&node.proto, node.proto.input.get(i).map(|s| s.as_str()).unwrap_or(&format!("#{}", i))))
+  }
+}
+
+/*
+This function get an input tensor of the node that holds indices (or other integer parameters like pads and sizes)
+  -It takes 3 parameters:
+    ~ i: position of the input in the node
+    ~ node: considered node
+    ~ inputs: values of the node inputs
+  -It returns the values as int64, MissingInput if the input isn't provided or ShapeMismatch if it isn't int32 or int64
+*/
+fn get_indices_tensor(i: usize, node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<ArrayD<i64>, InferenceError> {
+  match get_input_tensor(i, node, inputs)? {
+    Tensor::I64(indices) => Ok(indices.clone()),
+    Tensor::I32(indices) => Ok(indices.mapv(|v| v as i64)),
+    other => Err(InferenceError::shape_mismatch(&node.proto, format!("{} must be int32 or int64, got {:?}", node.proto.input.get(i).map(|s| s.as_str()).unwrap_or_default(), other.data_type())))
   }
-}
\ No newline at end of file
+}
+
+/*
+This function converts an input of the node to the rank expected by the operation
+  -It takes 3 parameters:
+    ~ node: considered node
+    ~ array: input array
+    ~ input_description: which input is converted (used in the error message)
+  -It returns the array with a static dimension, or ShapeMismatch if the rank is different
+*/
+fn into_rank<A, D: Dimension>(node: &NodeProto, array: ArrayD<A>, input_description: &str) -> Result<Array<A, D>, InferenceError> {
+  let shape = array.shape().to_vec();
+  array.into_dimensionality::<D>()
+    .map_err(|_| InferenceError::shape_mismatch(node, format!("{} has shape {:?}, expected rank {}", input_description, shape, D::NDIM.unwrap_or(0))))
+}
+
+/*
+This function converts a (possibly negative) axis attribute into the position of the dimension
+  -It takes 3 parameters:
+    ~ node: considered node
+    ~ axis: value of the attribute, in [-rank, rank - 1]
+    ~ rank: rank of the input
+  -It returns the axis in [0, rank - 1], or BadAttribute if it is out of range
+*/
+fn normalize_axis(node: &NodeProto, axis: i64, rank: usize) -> Result<usize, InferenceError> {
+  let normalized = if axis < 0 { axis + rank as i64 } else { axis };
+  if normalized < 0 || normalized >= rank as i64 {
+    return Err(InferenceError::bad_attribute(node, "axis", format!("axis {} out of range for rank {}", axis, rank)));
+  }
+  Ok(normalized as usize)
+}
+
+/*
+This function checks that the node has only attributes known by its operation
+  -It takes 3 parameters:
+    ~ node: considered node
+    ~ attributes: attributes of the node
+    ~ known: names of the attributes of the operation
+  -It returns BadAttribute for the first unknown attribute
+*/
+fn check_attributes(node: &NodeProto, attributes: &NodeAttributes, known: &[&str]) -> Result<(), InferenceError> {
+  match attributes.names().find(|name| !known.contains(&name.as_str())) {
+    Some(name) => Err(InferenceError::bad_attribute(node, name, format!("unknown attribute for {}", node.op_type.as_deref().unwrap_or_default()))),
+    None => Ok(())
+  }
+}
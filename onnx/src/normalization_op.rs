@@ -0,0 +1,83 @@
+use ndarray::{Array, Array1, ArrayD, Axis, IxDyn};
+use num_traits::Float;
+
+//OPSET VERSION = 15
+//Inference mode: y = scale * (x - mean) / sqrt(var + epsilon) + bias, with the statistics estimated during the training.
+//The input is (N, C, D1, ..., Dn) (the NCHW layout of the convolutions) and every parameter has one value per channel C
+pub fn batch_normalization<F: Float>(x: &ArrayD<F>, scale: &Array1<F>, bias: &Array1<F>, mean: &Array1<F>, var: &Array1<F>, epsilon: f32) -> Result<ArrayD<F>, String> {
+  let epsilon = F::from(epsilon).unwrap();
+  if x.ndim() < 2 {
+    return Err(format!("input has shape {:?}, expected (N, C, ...)", x.shape()));
+  }
+  let channels = x.shape()[1];
+  for (name, parameter) in [("scale", scale), ("B", bias), ("mean", mean), ("var", var)] {
+    if parameter.len() != channels {
+      return Err(format!("{} has {} values, expected one for each of the {} channels", name, parameter.len(), channels));
+    }
+  }
+
+  /* The normalization of each channel is a multiplication and an addition */
+  let factor: Array1<F> = scale / &var.mapv(|v| (v + epsilon).sqrt());
+  let offset: Array1<F> = bias - &(mean * &factor);
+
+  let mut y = x.clone();
+  for mut image in y.axis_iter_mut(Axis(0)) {
+    for (c, mut channel) in image.axis_iter_mut(Axis(0)).enumerate() {
+      channel.mapv_inplace(|v| v * factor[c] + offset[c]);
+    }
+  }
+  Ok(y)
+}
+
+//OPSET VERSION = 6
+//y = scale * (x - mean) / sqrt(var + epsilon) + bias, where mean and var are computed over the spatial dimensions
+//of each channel of each image
+pub fn instance_normalization<F: Float>(x: &ArrayD<F>, scale: &Array1<F>, bias: &Array1<F>, epsilon: f32) -> Result<ArrayD<F>, String> {
+  let epsilon = F::from(epsilon).unwrap();
+  if x.ndim() < 3 {
+    return Err(format!("input has shape {:?}, expected (N, C, D1, ...)", x.shape()));
+  }
+  let channels = x.shape()[1];
+  for (name, parameter) in [("scale", scale), ("B", bias)] {
+    if parameter.len() != channels {
+      return Err(format!("{} has {} values, expected one for each of the {} channels", name, parameter.len(), channels));
+    }
+  }
+
+  let mut y = x.clone();
+  for mut image in y.axis_iter_mut(Axis(0)) {
+    for (c, mut channel) in image.axis_iter_mut(Axis(0)).enumerate() {
+      let size = F::from(channel.len()).unwrap();
+      let mean = channel.sum() / size;
+      let var = channel.fold(F::zero(), |acc, &v| acc + (v - mean) * (v - mean)) / size;
+      let factor = scale[c] / (var + epsilon).sqrt();
+      channel.mapv_inplace(|v| (v - mean) * factor + bias[c]);
+    }
+  }
+  Ok(y)
+}
+
+#[allow(dead_code)]
+pub fn test_normalization() {
+  // Input (1, 2, 2, 2): channel 0 = [1, 2, 3, 4], channel 1 = [10, 20, 30, 40]
+  let x = Array::from_shape_vec((1, 2, 2, 2), vec![1., 2., 3., 4., 10., 20., 30., 40.]).unwrap().into_dyn();
+
+  // Channel 0: (x - 2) / sqrt(1) * 2 + 1, channel 1: (x - 20) / sqrt(100) * 1 + 0 (epsilon = 0)
+  let scale = Array1::from(vec![2., 1.]);
+  let bias = Array1::from(vec![1., 0.]);
+  let mean = Array1::from(vec![2., 20.]);
+  let var = Array1::from(vec![1., 100.]);
+  println!("batch normalization: {:?}", batch_normalization(&x, &scale, &bias, &mean, &var, 0.));
+  println!("expected: [[[[-1, 1], [3, 5]], [[-1, 0], [1, 2]]]]");
+
+  // Channel 0 has mean 2.5 and variance 1.25, channel 1 mean 25 and variance 125: both become
+  // [-1.3416, -0.4472, 0.4472, 1.3416], then scaled and shifted
+  let scale = Array1::from(vec![1., 2.]);
+  let bias = Array1::from(vec![0., 1.]);
+  println!("instance normalization: {:?}", instance_normalization(&x, &scale, &bias, 0.));
+  println!("expected: [[[[-1.3416, -0.4472], [0.4472, 1.3416]], [[-1.6833, 0.1056], [1.8944, 3.6833]]]]");
+
+  // One parameter for each channel is required
+  let wrong = ArrayD::<f32>::zeros(IxDyn(&[1, 3, 2, 2]));
+  println!("batch normalization wrong: {:?}", batch_normalization(&wrong, &scale, &bias, &mean, &var, 1e-5));
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use crate::inference_error::InferenceError;
+use crate::inference_session::{NodeAttributes, PreparedNode};
+use crate::model_inference::register_default_operators;
+use crate::onnx_structure::{ModelProto, NodeProto, TensorProto};
+use crate::onnx_structure::tensor_proto::DataType;
+use crate::shape_inference::infer_node;
+use crate::tensor::{Tensor, TensorType};
+
+/* Implementation of an operation: it takes the node and the values of its inputs and returns the values of its outputs */
+pub type OpFunction = fn(&PreparedNode, &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError>;
+
+/*
+Operator that can be added to an OperatorRegistry, also for a custom domain (i.e. "com.ourteam"), so that models
+with proprietary operations can be executed without changing the engine.
+A kernel is shared by every session using the registry and by their workers, so it must be Send + Sync.
+*/
+pub trait OpKernel: Send + Sync {
+  /*
+  This function computes the types of the outputs of the node, used by the shape inference
+    -It takes 4 parameters:
+      ~ node: considered node
+      ~ attributes: attributes of the node
+      ~ inputs: types of the node inputs (None for the optional inputs not provided)
+      ~ constants: initializers feeding the node inputs, if any
+    -It returns the types of the node outputs. By default they are unknown (undefined element type and no shape)
+  */
+  fn infer_shape(&self, node: &NodeProto, attributes: &NodeAttributes, inputs: &[Option<&TensorType>], constants: &[Option<&TensorProto>]) -> Result<Vec<TensorType>, InferenceError> {
+    let _ = (attributes, inputs, constants);
+    Ok(node.output.iter().map(|_| TensorType { elem_type: DataType::UNDEFINED, shape: None }).collect())
+  }
+
+  /*
+  This function executes the node
+    -It takes 2 parameters:
+      ~ node: node to execute, with its attributes already resolved (see PreparedNode::attributes)
+      ~ inputs: values of the node inputs, in the same order of node.input (None for the optional inputs not provided)
+    -It returns the values of the node outputs, in the same order of node.output
+  */
+  fn compute(&self, node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError>;
+}
+
+/*
+Kernel made of a plain function: the types of the outputs are inferred by the engine for the standard operators only,
+following the semantics of the opset version where the function is registered
+*/
+struct FunctionKernel {
+  function: OpFunction,
+  since_version: i64,
+}
+
+impl OpKernel for FunctionKernel {
+  fn infer_shape(&self, node: &NodeProto, attributes: &NodeAttributes, inputs: &[Option<&TensorType>], constants: &[Option<&TensorProto>]) -> Result<Vec<TensorType>, InferenceError> {
+    match normalize_domain(node.domain.as_deref().unwrap_or_default()) {
+      DEFAULT_DOMAIN => infer_node(node, self.since_version, attributes, inputs, constants),
+      _ => Ok(node.output.iter().map(|_| TensorType { elem_type: DataType::UNDEFINED, shape: None }).collect())
+    }
+  }
+
+  fn compute(&self, node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+    (self.function)(node, inputs)
+  }
+}
+
+/* Domain of the standard ONNX operators (also written as "ai.onnx") */
+pub const DEFAULT_DOMAIN: &str = "";
+
+/*
+Operators known by the engine, indexed by (domain, op_type). Every operator has one implementation for each opset
+version that changed its semantics: the implementation used for a model is the one with the greatest since_version
+not greater than the version imported by the model for that domain.
+*/
+pub struct OperatorRegistry {
+  /* For each (domain, op_type), the implementations sorted by since_version */
+  operators: HashMap<(String, String), KernelVersions>,
+}
+
+/* Implementations of an operator with their since_version */
+type KernelVersions = Vec<(i64, Arc<dyn OpKernel>)>;
+
+impl OperatorRegistry {
+  /* Empty registry */
+  pub fn new() -> OperatorRegistry {
+    OperatorRegistry { operators: HashMap::new() }
+  }
+
+  /*
+  This function adds the implementation of an operator
+    -It takes 4 parameters:
+      ~ domain: domain of the operator ("" or "ai.onnx" for the standard ones)
+      ~ op_type: name of the operator
+      ~ since_version: first opset version of the domain where the implementation is valid
+      ~ function: the implementation. It replaces the one registered with the same since_version, if any
+  */
+  pub fn register(&mut self, domain: &str, op_type: &str, since_version: i64, function: OpFunction) {
+    self.register_kernel(domain, op_type, since_version, FunctionKernel { function, since_version });
+  }
+
+  /*
+  This function adds the kernel of an operator, i.e. a custom operator of the model
+    -It takes 4 parameters:
+      ~ domain: domain of the operator (the model must import an opset for it)
+      ~ op_type: name of the operator
+      ~ since_version: first opset version of the domain where the kernel is valid
+      ~ kernel: the implementation. It replaces the one registered with the same since_version, if any
+  */
+  pub fn register_kernel(&mut self, domain: &str, op_type: &str, since_version: i64, kernel: impl OpKernel + 'static) {
+    let versions = self.operators.entry((normalize_domain(domain).to_string(), op_type.to_string())).or_default();
+    versions.retain(|(version, _)| *version != since_version);
+    versions.push((since_version, Arc::new(kernel)));
+    versions.sort_by_key(|(version, _)| *version);
+  }
+
+  /*
+  This function picks the implementation of the node
+    -It takes 2 parameters:
+      ~ node: considered node
+      ~ opset_imports: opset version imported by the model for each domain (see opset_imports)
+    -It returns the implementation, UnsupportedOp if the operator isn't known or UnsupportedVersion if the
+    imported version is older than every implementation
+  */
+  pub fn resolve(&self, node: &NodeProto, opset_imports: &HashMap<String, i64>) -> Result<Arc<dyn OpKernel>, InferenceError> {
+    let domain = normalize_domain(node.domain.as_deref().unwrap_or_default());
+    let op_type = node.op_type.as_deref().unwrap_or_default();
+
+    let version = match opset_imports.get(domain) {
+      Some(&version) => version,
+      None => return Err(InferenceError::MalformedModel(format!("domain '{}' of node '{}' is not imported by the model", domain, crate::inference_error::node_name(node))))
+    };
+    let versions = match self.operators.get(&(domain.to_string(), op_type.to_string())) {
+      Some(versions) => versions,
+      None => return Err(InferenceError::unsupported_op(node))
+    };
+
+    match versions.iter().rev().find(|(since_version, _)| *since_version <= version) {
+      Some((_, kernel)) => Ok(kernel.clone()),
+      None => Err(InferenceError::unsupported_version(node, domain, version))
+    }
+  }
+}
+
+impl Default for OperatorRegistry {
+  /* Registry with the operators implemented by the engine */
+  fn default() -> Self {
+    let mut registry = OperatorRegistry::new();
+    register_default_operators(&mut registry);
+    registry
+  }
+}
+
+impl fmt::Debug for OperatorRegistry {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut operators: Vec<(&(String, String), Vec<i64>)> = self.operators.iter()
+      .map(|(key, versions)| (key, versions.iter().map(|(version, _)| *version).collect()))
+      .collect();
+    operators.sort();
+    f.debug_map().entries(operators).finish()
+  }
+}
+
+/*
+This function reads the opset version imported by the model for each domain
+  -It takes 1 parameter:
+    ~ model: the onnx model
+  -It returns the versions indexed by domain, or an error if the model doesn't import any opset
+*/
+pub fn opset_imports(model: &ModelProto) -> Result<HashMap<String, i64>, InferenceError> {
+  if model.opset_import.is_empty() {
+    return Err(InferenceError::MalformedModel("the model has no opset import".to_string()));
+  }
+  Ok(model.opset_import.iter()
+    .map(|opset| (normalize_domain(opset.domain.as_deref().unwrap_or_default()).to_string(), opset.version.unwrap_or(0)))
+    .collect())
+}
+
+/* "ai.onnx" and "" are both the default domain */
+pub(crate) fn normalize_domain(domain: &str) -> &str {
+  if domain == "ai.onnx" { DEFAULT_DOMAIN } else { domain }
+}
+
+#[allow(dead_code)]
+pub fn test_custom_operator() {
+  use crate::inference_session::{InferenceSession, SessionOptions};
+  use crate::onnx_structure::{AttributeProto, GraphProto, OperatorSetIdProto};
+  use crate::onnx_structure::attribute_proto::AttributeType;
+  use crate::shape_inference::{infer_value_types_with, to_value_info};
+  use crate::tensor::Dim;
+  use ndarray::{ArrayD, IxDyn};
+  use protobuf::EnumOrUnknown;
+
+  /* y = x * factor, in the domain of the team */
+  struct Scale;
+
+  impl OpKernel for Scale {
+    fn infer_shape(&self, _node: &NodeProto, _attributes: &NodeAttributes, inputs: &[Option<&TensorType>], _constants: &[Option<&TensorProto>]) -> Result<Vec<TensorType>, InferenceError> {
+      Ok(vec![inputs[0].cloned().unwrap()])
+    }
+
+    fn compute(&self, node: &PreparedNode, inputs: &[Option<&Tensor>]) -> Result<Vec<Tensor>, InferenceError> {
+      let x = inputs[0].ok_or_else(|| InferenceError::missing_input(node.proto(), &node.proto().input[0]))?;
+      let factor = node.attributes().float("factor").unwrap_or(1.0);
+      Ok(vec![Tensor::F32(x.to_f32().mapv(|v| v * factor))])
+    }
+  }
+
+  let value_type = TensorType { elem_type: DataType::FLOAT, shape: Some(vec![Dim::Value(2), Dim::Value(2)]) };
+  let mut factor = AttributeProto::new();
+  factor.name = Some("factor".to_string());
+  factor.type_ = Some(EnumOrUnknown::new(AttributeType::FLOAT));
+  factor.f = Some(3.0);
+  let mut node = NodeProto::new();
+  node.op_type = Some("Scale".to_string());
+  node.domain = Some("com.ourteam".to_string());
+  node.input = vec!["x".to_string()];
+  node.output = vec!["y".to_string()];
+  node.attribute = vec![factor];
+
+  let mut graph = GraphProto::new();
+  graph.node = vec![node];
+  graph.input = vec![to_value_info("x", &value_type)];
+  graph.output = vec![to_value_info("y", &value_type)];
+  let mut model = ModelProto::new();
+  model.graph = Some(graph).into();
+  for (domain, version) in [("", 13), ("com.ourteam", 1)] {
+    let mut opset = OperatorSetIdProto::new();
+    opset.domain = Some(domain.to_string());
+    opset.version = Some(version);
+    model.opset_import.push(opset);
+  }
+
+  let mut registry = OperatorRegistry::default();
+  registry.register_kernel("com.ourteam", "Scale", 1, Scale);
+  println!("{:?}", infer_value_types_with(&model, &registry).unwrap().get("y"));
+
+  let options = SessionOptions { operators: Arc::new(registry), ..SessionOptions::single_threaded() };
+  let session = InferenceSession::with_options(&model, options).unwrap();
+  let x = Tensor::F32(ArrayD::from_shape_vec(IxDyn(&[2, 2]), vec![1.0, 2.0, 3.0, 4.0]).unwrap());
+  let outputs = session.run(HashMap::from([("x".to_string(), x)])).unwrap();
+  println!("{:?}", outputs["y"]);
+}
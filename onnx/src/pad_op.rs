@@ -0,0 +1,130 @@
+use half::f16;
+use ndarray::{Array, ArrayD, IxDyn};
+use crate::tensor::Tensor;
+
+//OPSET VERSION = 19
+//Pads every dimension of the input with pads[d] values at the beginning and pads[rank + d] at the end
+//(negative pads remove values instead)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PadMode {
+  /* The new values are constant_value */
+  Constant,
+  /* The values are mirrored around the first and the last one, which aren't repeated */
+  Reflect,
+  /* The first and the last value are repeated */
+  Edge,
+  /* The values are repeated from the other side of the dimension */
+  Wrap,
+}
+
+/*
+This function executes the Pad
+  -It takes 4 parameters:
+    ~ x: input
+    ~ pads: padding at the beginning of every dimension, followed by the padding at the end of every dimension
+    ~ mode: how the new values are computed
+    ~ constant_value: value used by the constant mode, converted to the element type of the input (0 if None)
+  -It returns the padded tensor, or an error if pads hasn't 2 values for each dimension or a dimension can't be padded
+*/
+pub fn pad(x: &Tensor, pads: &[i64], mode: PadMode, constant_value: Option<&Tensor>) -> Result<Tensor, String> {
+  let rank = x.rank();
+  if pads.len() != 2 * rank {
+    return Err(format!("pads {:?} must have 2 values for each dimension of {:?}", pads, x.shape()));
+  }
+  let mut output_shape = Vec::with_capacity(rank);
+  for (d, &size) in x.shape().iter().enumerate() {
+    let padded = size as i64 + pads[d] + pads[rank + d];
+    if padded < 0 {
+      return Err(format!("pads {:?} remove more than dimension {} of {:?}", pads, d, x.shape()));
+    }
+    /* Only the constant mode can create values from an empty dimension, reflect needs at least 2 values */
+    let outside = pads[d] > 0 || pads[rank + d] > 0;
+    if outside && ((mode != PadMode::Constant && size == 0) || (mode == PadMode::Reflect && size < 2)) {
+      return Err(format!("dimension {} of {:?} cannot be padded in {:?} mode", d, x.shape(), mode));
+    }
+    output_shape.push(padded as usize);
+  }
+
+  Ok(match (x, scalar_like(x, constant_value)) {
+    (Tensor::F32(a), Tensor::F32(v)) => Tensor::F32(pad_with(a, pads, mode, &output_shape, v[[]])),
+    (Tensor::F64(a), Tensor::F64(v)) => Tensor::F64(pad_with(a, pads, mode, &output_shape, v[[]])),
+    (Tensor::I64(a), Tensor::I64(v)) => Tensor::I64(pad_with(a, pads, mode, &output_shape, v[[]])),
+    (Tensor::I32(a), Tensor::I32(v)) => Tensor::I32(pad_with(a, pads, mode, &output_shape, v[[]])),
+    (Tensor::U8(a), Tensor::U8(v)) => Tensor::U8(pad_with(a, pads, mode, &output_shape, v[[]])),
+    (Tensor::I8(a), Tensor::I8(v)) => Tensor::I8(pad_with(a, pads, mode, &output_shape, v[[]])),
+    (Tensor::Bool(a), Tensor::Bool(v)) => Tensor::Bool(pad_with(a, pads, mode, &output_shape, v[[]])),
+    (Tensor::F16(a), Tensor::F16(v)) => Tensor::F16(pad_with(a, pads, mode, &output_shape, v[[]])),
+    _ => unreachable!("scalar_like returns the element type of the input")
+  })
+}
+
+fn pad_with<T: Copy>(x: &ArrayD<T>, pads: &[i64], mode: PadMode, output_shape: &[usize], constant_value: T) -> ArrayD<T> {
+  let rank = x.ndim();
+  let mut source = vec![0; rank];
+  ArrayD::from_shape_fn(IxDyn(output_shape), |index| {
+    for d in 0..rank {
+      let size = x.shape()[d] as i64;
+      let position = index[d] as i64 - pads[d];
+      source[d] = if (0..size).contains(&position) {
+        position
+      } else {
+        match mode {
+          PadMode::Constant => return constant_value,
+          PadMode::Edge => position.clamp(0, size - 1),
+          PadMode::Wrap => position.rem_euclid(size),
+          PadMode::Reflect => {
+            let period = 2 * (size - 1);
+            let folded = position.rem_euclid(period);
+            if folded < size { folded } else { period - folded }
+          }
+        }
+      } as usize;
+    }
+    x[IxDyn(&source)]
+  })
+}
+
+/* Converts the constant value (the first element of the tensor, 0 if None) to a scalar with the element type of the input */
+fn scalar_like(x: &Tensor, value: Option<&Tensor>) -> Tensor {
+  let zero = Tensor::I64(ArrayD::zeros(IxDyn(&[])));
+  let value = value.filter(|v| !v.is_empty()).unwrap_or(&zero);
+  let first_f64 = match value {
+    Tensor::F64(v) => v.iter().next().copied().unwrap(),
+    other => other.to_f32().iter().next().copied().unwrap() as f64
+  };
+  let first_i64 = value.to_i64().iter().next().copied().unwrap();
+  match x {
+    Tensor::F32(_) => Tensor::F32(scalar(first_f64 as f32)),
+    Tensor::F64(_) => Tensor::F64(scalar(first_f64)),
+    Tensor::F16(_) => Tensor::F16(scalar(f16::from_f64(first_f64))),
+    Tensor::I64(_) => Tensor::I64(scalar(first_i64)),
+    Tensor::I32(_) => Tensor::I32(scalar(first_i64 as i32)),
+    Tensor::U8(_) => Tensor::U8(scalar(first_i64 as u8)),
+    Tensor::I8(_) => Tensor::I8(scalar(first_i64 as i8)),
+    Tensor::Bool(_) => Tensor::Bool(scalar(first_i64 != 0)),
+  }
+}
+
+fn scalar<T: Clone>(value: T) -> ArrayD<T> {
+  ArrayD::from_elem(IxDyn(&[]), value)
+}
+
+#[allow(dead_code)]
+pub fn test_pad() {
+  let x = Tensor::F32(Array::from_shape_vec((2, 3), vec![1., 2., 3., 4., 5., 6.]).unwrap().into_dyn());
+  let value = Tensor::F32(ArrayD::from_elem(IxDyn(&[]), 9.));
+  println!("pad constant: {:?}", pad(&x, &[0, 1, 1, 0], PadMode::Constant, Some(&value)));
+  println!("expected: [[9, 1, 2, 3], [9, 4, 5, 6], [9, 9, 9, 9]]");
+  println!("pad reflect: {:?}", pad(&x, &[0, 2, 0, 2], PadMode::Reflect, None));
+  println!("expected: [[3, 2, 1, 2, 3, 2, 1], [6, 5, 4, 5, 6, 5, 4]]");
+  println!("pad edge: {:?}", pad(&x, &[1, 1, 0, 0], PadMode::Edge, None));
+  println!("expected: [[1, 1, 2, 3], [1, 1, 2, 3], [4, 4, 5, 6]]");
+  println!("pad wrap: {:?}", pad(&x, &[0, 1, 0, 1], PadMode::Wrap, None));
+  println!("expected: [[3, 1, 2, 3, 1], [6, 4, 5, 6, 4]]");
+
+  // Negative pads crop, the default constant is 0 of the element type of the input
+  let integers = Tensor::I32(Array::from_shape_vec((2, 3), vec![1, 2, 3, 4, 5, 6]).unwrap().into_dyn());
+  println!("pad crop: {:?}", pad(&integers, &[0, -1, 1, 0], PadMode::Constant, None));
+  println!("expected: [[2, 3], [5, 6], [0, 0]]");
+  println!("pad wrong: {:?}", pad(&integers, &[0, 1], PadMode::Constant, None));
+}
@@ -0,0 +1,240 @@
+use half::f16;
+use ndarray::{Array, ArrayD, ArrayView1, Axis, IxDyn};
+use crate::tensor::Tensor;
+
+//OPSET VERSION = 18
+//Reductions of the elements along the given axes (all the axes if none is given). With keepdims the reduced axes are
+//kept with size 1. The output has the element type of the input (integer means are truncated)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReduceOp {
+  Sum,
+  Mean,
+  Max,
+  Min,
+  Prod,
+  /* Sum of the absolute values */
+  L1,
+  /* Square root of the sum of the squares */
+  L2,
+  /* Natural logarithm of the sum */
+  LogSum,
+  /* Natural logarithm of the sum of the exponentials */
+  LogSumExp,
+  SumSquare,
+}
+
+/* Element type on which the reductions are defined */
+trait Element: Copy {
+  fn reduce(op: ReduceOp, values: &[Self]) -> Self;
+}
+
+macro_rules! impl_float_element {
+  ($($elem:ty),*) => {
+    $(
+      impl Element for $elem {
+        fn reduce(op: ReduceOp, values: &[Self]) -> Self {
+          let sum = |f: fn($elem) -> $elem| values.iter().fold(0., |acc, &v| acc + f(v));
+          match op {
+            ReduceOp::Sum => sum(|v| v),
+            ReduceOp::Mean => sum(|v| v) / values.len() as $elem,
+            ReduceOp::Max => values.iter().fold(<$elem>::NEG_INFINITY, |acc, &v| acc.max(v)),
+            ReduceOp::Min => values.iter().fold(<$elem>::INFINITY, |acc, &v| acc.min(v)),
+            ReduceOp::Prod => values.iter().product(),
+            ReduceOp::L1 => sum(|v| v.abs()),
+            ReduceOp::L2 => sum(|v| v * v).sqrt(),
+            ReduceOp::LogSum => sum(|v| v).ln(),
+            /* The maximum is taken out so that the exponentials never overflow */
+            ReduceOp::LogSumExp => {
+              let max = Self::reduce(ReduceOp::Max, values);
+              if max.is_infinite() { return max; }
+              max + values.iter().fold(0., |acc, &v| acc + (v - max).exp()).ln()
+            }
+            ReduceOp::SumSquare => sum(|v| v * v),
+          }
+        }
+      }
+    )*
+  };
+}
+
+macro_rules! impl_int_element {
+  ($($elem:ty),*) => {
+    $(
+      impl Element for $elem {
+        #[allow(unused_comparisons)]
+        fn reduce(op: ReduceOp, values: &[Self]) -> Self {
+          let sum = |f: fn($elem) -> $elem| values.iter().fold(0 as $elem, |acc, &v| acc.wrapping_add(f(v)));
+          /* The logarithms and the square root are computed in floating point and truncated */
+          let float = |v: $elem| v as f64;
+          match op {
+            ReduceOp::Sum => sum(|v| v),
+            /* The mean is computed on a wider sum, so that neither the sum nor the number of values overflow the type */
+            ReduceOp::Mean => {
+              let wide_sum: i128 = values.iter().map(|&v| v as i128).sum();
+              wide_sum.checked_div(values.len() as i128).unwrap_or(0).clamp(<$elem>::MIN as i128, <$elem>::MAX as i128) as $elem
+            }
+            ReduceOp::Max => values.iter().copied().fold(<$elem>::MIN, <$elem>::max),
+            ReduceOp::Min => values.iter().copied().fold(<$elem>::MAX, <$elem>::min),
+            ReduceOp::Prod => values.iter().fold(1 as $elem, |acc, &v| acc.wrapping_mul(v)),
+            ReduceOp::L1 => sum(|v| if v < 0 { v.wrapping_neg() } else { v }),
+            ReduceOp::L2 => float(sum(|v| v.wrapping_mul(v))).sqrt() as $elem,
+            ReduceOp::LogSum => float(sum(|v| v)).ln() as $elem,
+            ReduceOp::LogSumExp => {
+              let values: Vec<f64> = values.iter().map(|&v| float(v)).collect();
+              f64::reduce(ReduceOp::LogSumExp, &values) as $elem
+            }
+            ReduceOp::SumSquare => sum(|v| v.wrapping_mul(v)),
+          }
+        }
+      }
+    )*
+  };
+}
+
+impl_float_element!(f32, f64);
+impl_int_element!(i64, i32, u8, i8);
+
+impl Element for f16 {
+  fn reduce(op: ReduceOp, values: &[Self]) -> Self {
+    let values: Vec<f32> = values.iter().map(|v| v.to_f32()).collect();
+    f16::from_f32(f32::reduce(op, &values))
+  }
+}
+
+/*
+This function executes a reduction
+  -It takes 4 parameters:
+    ~ op: reduction
+    ~ x: input
+    ~ axes: reduced axes, in [0, rank - 1] (all of them if empty)
+    ~ keepdims: whether the reduced axes are kept with size 1
+  -It returns the result, with the element type of the input, or an error if it isn't numeric
+*/
+pub fn reduce(op: ReduceOp, x: &Tensor, axes: &[usize], keepdims: bool) -> Result<Tensor, String> {
+  match x {
+    Tensor::F32(x) => Ok(Tensor::F32(reduce_with(op, x, axes, keepdims))),
+    Tensor::F64(x) => Ok(Tensor::F64(reduce_with(op, x, axes, keepdims))),
+    Tensor::F16(x) => Ok(Tensor::F16(reduce_with(op, x, axes, keepdims))),
+    Tensor::I64(x) => Ok(Tensor::I64(reduce_with(op, x, axes, keepdims))),
+    Tensor::I32(x) => Ok(Tensor::I32(reduce_with(op, x, axes, keepdims))),
+    Tensor::U8(x) => Ok(Tensor::U8(reduce_with(op, x, axes, keepdims))),
+    Tensor::I8(x) => Ok(Tensor::I8(reduce_with(op, x, axes, keepdims))),
+    Tensor::Bool(_) => Err(format!("Reduce{:?} is not defined for {:?}", op, x.data_type())),
+  }
+}
+
+fn reduce_with<T: Element>(op: ReduceOp, x: &ArrayD<T>, axes: &[usize], keepdims: bool) -> ArrayD<T> {
+  let reduced: Vec<bool> = (0..x.ndim()).map(|d| axes.is_empty() || axes.contains(&d)).collect();
+
+  /* The reduced axes are moved to the end, so that every group of values reduced together is contiguous */
+  let order: Vec<usize> = (0..x.ndim()).filter(|&d| !reduced[d]).chain((0..x.ndim()).filter(|&d| reduced[d])).collect();
+  let group_size: usize = (0..x.ndim()).filter(|&d| reduced[d]).map(|d| x.shape()[d]).product();
+  let values: Vec<T> = x.view().permuted_axes(IxDyn(&order)).iter().copied().collect();
+  let output: Vec<T> = if group_size == 0 {
+    let groups = (0..x.ndim()).filter(|&d| !reduced[d]).map(|d| x.shape()[d]).product();
+    vec![T::reduce(op, &[]); groups]
+  } else {
+    values.chunks(group_size).map(|group| T::reduce(op, group)).collect()
+  };
+
+  let output_shape: Vec<usize> = x.shape().iter().zip(&reduced).filter_map(|(&size, &r)| match (r, keepdims) {
+    (false, _) => Some(size),
+    (true, true) => Some(1),
+    (true, false) => None
+  }).collect();
+  ArrayD::from_shape_vec(IxDyn(&output_shape), output).unwrap()
+}
+
+//OPSET VERSION = 13
+//Index of the maximum (or minimum) along an axis. With select_last_index the last one is taken when it is repeated
+/*
+This function executes the ArgMax or the ArgMin
+  -It takes 5 parameters:
+    ~ x: input
+    ~ axis: axis along which the index is found, in [0, rank - 1]
+    ~ keepdims: whether the axis is kept with size 1
+    ~ select_last_index: whether the last occurrence of the extreme is taken instead of the first one
+    ~ max: ArgMax if true, ArgMin otherwise
+  -It returns the indices (int64), or an error if the axis is empty or the input isn't numeric
+*/
+pub fn arg_reduce(x: &Tensor, axis: usize, keepdims: bool, select_last_index: bool, max: bool) -> Result<ArrayD<i64>, String> {
+  if x.shape()[axis] == 0 {
+    return Err(format!("axis {} of {:?} is empty", axis, x.shape()));
+  }
+  let output = match x {
+    Tensor::F32(x) => arg_reduce_with(x, axis, select_last_index, max),
+    Tensor::F64(x) => arg_reduce_with(x, axis, select_last_index, max),
+    Tensor::F16(x) => arg_reduce_with(x, axis, select_last_index, max),
+    Tensor::I64(x) => arg_reduce_with(x, axis, select_last_index, max),
+    Tensor::I32(x) => arg_reduce_with(x, axis, select_last_index, max),
+    Tensor::U8(x) => arg_reduce_with(x, axis, select_last_index, max),
+    Tensor::I8(x) => arg_reduce_with(x, axis, select_last_index, max),
+    Tensor::Bool(_) => return Err(format!("{} is not defined for {:?}", if max { "ArgMax" } else { "ArgMin" }, x.data_type())),
+  };
+  Ok(if keepdims { output.insert_axis(Axis(axis)) } else { output })
+}
+
+fn arg_reduce_with<T: Copy + PartialOrd>(x: &ArrayD<T>, axis: usize, select_last_index: bool, max: bool) -> ArrayD<i64> {
+  x.map_axis(Axis(axis), |lane: ArrayView1<T>| {
+    let mut best = 0;
+    for (i, &v) in lane.iter().enumerate().skip(1) {
+      let better = if max { v > lane[best] } else { v < lane[best] };
+      if better || (select_last_index && v == lane[best]) {
+        best = i;
+      }
+    }
+    best as i64
+  })
+}
+
+#[allow(dead_code)]
+pub fn test_reduce() {
+  let x = Tensor::F32(Array::from_shape_vec((2, 3), vec![1., 2., 3., 4., 5., 6.]).unwrap().into_dyn());
+  println!("reduce mean axis 1: {:?}", reduce(ReduceOp::Mean, &x, &[1], true));
+  println!("expected: [[2], [5]]");
+  println!("reduce mean axis 0: {:?}", reduce(ReduceOp::Mean, &x, &[0], false));
+  println!("expected: [2.5, 3.5, 4.5]");
+  println!("reduce mean all: {:?}", reduce(ReduceOp::Mean, &x, &[], false));
+  println!("expected: 3.5");
+
+  let cases = [
+    (ReduceOp::Sum, "[6, 15]"),
+    (ReduceOp::Max, "[3, 6]"),
+    (ReduceOp::Min, "[1, 4]"),
+    (ReduceOp::Prod, "[6, 120]"),
+    (ReduceOp::L1, "[6, 15]"),
+    (ReduceOp::L2, "[3.7417, 8.7750]"),
+    (ReduceOp::LogSum, "[1.7918, 2.7081]"),
+    (ReduceOp::LogSumExp, "[3.4076, 6.4076]"),
+    (ReduceOp::SumSquare, "[14, 77]"),
+  ];
+  for (op, expected) in cases {
+    println!("reduce {:?} axis 1: {:?}", op, reduce(op, &x, &[1], false));
+    println!("expected: {}", expected);
+  }
+
+  // Several axes of a 3D integer tensor: the mean is truncated
+  let integers = Tensor::I64(Array::from_shape_vec((2, 2, 2), vec![1, -2, 3, 4, 5, 6, 7, 9]).unwrap().into_dyn());
+  println!("reduce sum int axes 0, 2: {:?}", reduce(ReduceOp::Sum, &integers, &[0, 2], true));
+  println!("expected: [[[10], [23]]]");
+  println!("reduce mean int axes 0, 2: {:?}", reduce(ReduceOp::Mean, &integers, &[0, 2], false));
+  println!("expected: [2, 5]");
+  println!("reduce l1 int all: {:?}", reduce(ReduceOp::L1, &integers, &[], false));
+  println!("expected: 37");
+  // More values than the maximum of the type, whose sum doesn't fit the type
+  let bytes = Tensor::U8(ArrayD::from_elem(IxDyn(&[300]), 200));
+  println!("reduce mean uint8: {:?}", reduce(ReduceOp::Mean, &bytes, &[], false));
+  println!("expected: 200");
+  let signed_bytes = Tensor::I8(ArrayD::from_shape_fn(IxDyn(&[200]), |i| if i[0] % 2 == 0 { -100 } else { -50 }));
+  println!("reduce mean int8: {:?}", reduce(ReduceOp::Mean, &signed_bytes, &[], false));
+  println!("expected: -75");
+
+  // ArgMax and ArgMin, with the repeated extremes
+  let x = Tensor::F32(Array::from_shape_vec((2, 3), vec![2., 7., 7., 1., 1., 0.]).unwrap().into_dyn());
+  println!("argmax axis 1: {:?}", arg_reduce(&x, 1, true, false, true));
+  println!("expected: [[1], [0]]");
+  println!("argmax axis 1 last index: {:?}", arg_reduce(&x, 1, false, true, true));
+  println!("expected: [2, 1]");
+  println!("argmin axis 0: {:?}", arg_reduce(&x, 0, false, false, false));
+  println!("expected: [1, 1, 1]");
+}
@@ -1,7 +1,25 @@
-use ndarray::{Array, Array4};
+use half::f16;
+use ndarray::{Array, ArrayD};
+use crate::tensor::Tensor;
 
-pub fn relu(x: &Array4<f32>) -> Array4<f32> {
-  x.map(|&val| val.max(0.0))
+//OPSET VERSION = 14
+//y = max(0, x), with the shape and the element type of the input: floating point, or signed integer (since opset 14)
+pub fn relu(x: &Tensor) -> Result<Tensor, String> {
+  match x {
+    Tensor::F32(x) => Ok(Tensor::F32(relu_with(x))),
+    Tensor::F64(x) => Ok(Tensor::F64(relu_with(x))),
+    Tensor::F16(x) => Ok(Tensor::F16(relu_with(x))),
+    Tensor::I64(x) => Ok(Tensor::I64(relu_with(x))),
+    Tensor::I32(x) => Ok(Tensor::I32(relu_with(x))),
+    Tensor::I8(x) => Ok(Tensor::I8(relu_with(x))),
+    other => Err(format!("Relu is not defined for {:?}", other.data_type()))
+  }
+}
+
+/* The default value of the numeric types is their zero (NaN becomes 0, as with f32::max) */
+fn relu_with<T: Copy + PartialOrd + Default>(x: &ArrayD<T>) -> ArrayD<T> {
+  let zero = T::default();
+  x.mapv(|val| if val > zero { val } else { zero })
 }
 
 #[allow(dead_code)]
@@ -18,6 +36,16 @@ pub fn test_relu(){
   )
     .unwrap();
 
-  println!("{:?}", relu(&input));
+  println!("{:?}", relu(&Tensor::F32(input.into_dyn())));
   println!("{:?}", output);
-}
\ No newline at end of file
+
+  // The element type of the input is kept
+  println!("relu double: {:?}", relu(&Tensor::F64(Array::from_vec(vec![-1.5, 2.5]).into_dyn())));
+  println!("expected: F64 [0, 2.5]");
+  println!("relu int64: {:?}", relu(&Tensor::I64(Array::from_vec(vec![-3, 0, 7]).into_dyn())));
+  println!("expected: I64 [0, 0, 7]");
+  println!("relu float16: {:?}", relu(&Tensor::F16(Array::from_vec(vec![f16::from_f32(-1.), f16::from_f32(0.5)]).into_dyn())));
+  println!("expected: F16 [0, 0.5]");
+  println!("relu bool: {:?}", relu(&Tensor::Bool(Array::from_vec(vec![true]).into_dyn())));
+  println!("expected: error");
+}
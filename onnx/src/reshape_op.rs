@@ -1,55 +1,143 @@
-use ndarray::Array;
-use ndarray::prelude::*;
-use rand::Rng;
-
-//OPSET VERSION = 8
-pub fn reshape(data: Array4<f32>, shape: Array1<i64>, allowzero: Option<usize>) -> Array2<f32> {
-  return if allowzero.is_none() {
-    reshape_implementation(data, shape, 0)   /* Default value of allowzero = 0  */
-  } else {
-    reshape_implementation(data, shape, allowzero.unwrap())
+use ndarray::{Array, ArrayD, IxDyn};
+use crate::tensor::{map_each_variant, Tensor};
+
+//OPSET VERSION = 14
+//Operations that change only the shape of a tensor: the elements keep their (row-major) order and their element type
+
+/*
+This function computes the shape of a Reshape
+  -It takes 3 parameters:
+    ~ input: shape of the data
+    ~ shape: requested shape. -1 is inferred from the remaining dimensions (at most one), 0 copies the dimension of
+    the input at the same position (if allowzero is false) or is an empty dimension (if allowzero is true)
+    ~ allowzero: how 0 is interpreted
+  -It returns the output shape, or an error if the number of elements changes or the requested shape is invalid
+*/
+pub fn reshape_shape(input: &[usize], shape: &[i64], allowzero: bool) -> Result<Vec<usize>, String> {
+  if allowzero && shape.contains(&0) && shape.contains(&-1) {
+    return Err(format!("shape {:?} has both 0 and -1 with allowzero", shape));
   }
+  let mut output: Vec<usize> = Vec::with_capacity(shape.len());
+  let mut inferred_position = None;
+  for (i, &dim) in shape.iter().enumerate() {
+    match dim {
+      0 if !allowzero => match input.get(i) {
+        Some(&size) => output.push(size),
+        None => return Err(format!("shape {:?} copies dimension {} that the input {:?} doesn't have", shape, i, input))
+      },
+      -1 if inferred_position.is_none() => { inferred_position = Some(i); output.push(1); }
+      dim if dim >= 0 => output.push(dim as usize),
+      _ => return Err(format!("invalid shape {:?}", shape))
+    }
+  }
+
+  let total: usize = input.iter().product();
+  let known: usize = output.iter().product();
+  match inferred_position {
+    Some(position) if known != 0 && total.is_multiple_of(known) => output[position] = total / known,
+    None if known == total => {}
+    _ => return Err(format!("cannot reshape {:?} into {:?}", input, shape))
+  }
+  Ok(output)
 }
 
-pub fn reshape_implementation(data: Array4<f32>, shape: Array1<i64>, allowzero: usize) -> Array2<f32> {
-  let mut new_shape = shape.clone().to_vec();
-
-  if allowzero == 0 {
-    /* When any value in the ‘shape’ input is equal to zero the corresponding dimension value is copied
-    from the input tensor dynamically. */
-    let zeros_index: Vec<usize> = shape
-      .iter()
-      .enumerate()
-      .filter(|&(_, &value)| value == 0)
-      .map(|(index, _)| index)
-      .collect();
-
-    let data_shape = data.shape().to_vec();
-    for &index in &zeros_index {
-      new_shape[index] = data_shape[index] as i64;
-    }
+/*
+This function executes the Reshape
+  -It takes 3 parameters:
+    ~ data: input
+    ~ shape: requested shape (see reshape_shape)
+    ~ allowzero: how 0 is interpreted
+  -It returns the reshaped tensor, or an error if the shape isn't compatible with the input
+*/
+pub fn reshape(data: &Tensor, shape: &[i64], allowzero: bool) -> Result<Tensor, String> {
+  let output_shape = reshape_shape(data.shape(), shape, allowzero)?;
+  Ok(reshape_to(data, &output_shape))
+}
+
+/*
+This function executes the Flatten: the dimensions before axis become the rows of a matrix, the others its columns
+  -It takes 2 parameters:
+    ~ x: input
+    ~ axis: first dimension of the columns, in [0, rank]
+  -It returns the matrix
+*/
+pub fn flatten(x: &Tensor, axis: usize) -> Tensor {
+  let rows: usize = x.shape()[..axis].iter().product();
+  let columns: usize = x.shape()[axis..].iter().product();
+  reshape_to(x, &[rows, columns])
+}
+
+/*
+This function executes the Squeeze: removes dimensions of size 1
+  -It takes 2 parameters:
+    ~ x: input
+    ~ axes: removed dimensions, in [0, rank - 1] (all the dimensions of size 1 if empty)
+  -It returns the result, or an error if one of the axes hasn't size 1
+*/
+pub fn squeeze(x: &Tensor, axes: &[usize]) -> Result<Tensor, String> {
+  if let Some(&axis) = axes.iter().find(|&&axis| x.shape()[axis] != 1) {
+    return Err(format!("cannot squeeze axis {} of {:?}", axis, x.shape()));
   }
-  /* allowzero=1 indicates that if any value in the ‘shape’ input is set to zero,
-  the zero value is honored */
+  let output_shape: Vec<usize> = x.shape().iter().enumerate()
+    .filter(|&(d, &size)| if axes.is_empty() { size != 1 } else { !axes.contains(&d) })
+    .map(|(_, &size)| size)
+    .collect();
+  Ok(reshape_to(x, &output_shape))
+}
 
-  let (d1, d2) = (new_shape[0] as usize, new_shape[1] as usize);
+/*
+This function executes the Unsqueeze: inserts dimensions of size 1
+  -It takes 2 parameters:
+    ~ x: input
+    ~ axes: positions of the new dimensions in the output, in [0, output rank - 1]
+  -It returns the result, or an error if an axis is repeated
+*/
+pub fn unsqueeze(x: &Tensor, axes: &[usize]) -> Result<Tensor, String> {
+  let rank = x.rank() + axes.len();
+  let mut input_dims = x.shape().iter();
+  let mut output_shape = Vec::with_capacity(rank);
+  for d in 0..rank {
+    match axes.iter().filter(|&&axis| axis == d).count() {
+      0 => output_shape.push(*input_dims.next().unwrap()),
+      1 => output_shape.push(1),
+      _ => return Err(format!("axis {} is repeated in {:?}", d, axes))
+    }
+  }
+  Ok(reshape_to(x, &output_shape))
+}
 
-  let aus = data.as_slice_memory_order().unwrap();
-  let arr: Array2<f32> = Array2::from_shape_vec((d1, d2), aus.to_vec()).unwrap();
-  arr
+/* Reshapes the tensor to a shape with the same number of elements */
+fn reshape_to(x: &Tensor, shape: &[usize]) -> Tensor {
+  map_each_variant!(x, arr => arr.to_shape(IxDyn(shape)).unwrap().into_owned())
 }
 
 #[allow(dead_code)]
 pub fn test_reshape() {
-  // Esempio di utilizzo
-  let mut rng = rand::thread_rng();
-
-  let original_shape: Vec<i64> = vec![16, 3];
-  let orig: Array1<i64> = original_shape.into();
-
-  let data = Array::from_shape_vec((4, 2, 2, 3), (0..48).map(|_| rng.gen::<f32>()).collect()).unwrap();
+  let data = Tensor::F32(Array::from_shape_vec((2, 3, 4), (0..24).map(|v| v as f32).collect()).unwrap().into_dyn());
+  println!("reshape [4, -1]: {:?}", reshape(&data, &[4, -1], false).map(|t| t.shape().to_vec()));
+  println!("expected: [4, 6]");
+  println!("reshape [0, -1]: {:?}", reshape(&data, &[0, -1], false).map(|t| t.shape().to_vec()));
+  println!("expected: [2, 12]");
+  println!("reshape [-1, 0, 2] allowzero: {:?}", reshape(&data, &[-1, 0, 2], true));
+  println!("expected: error");
+  let empty = Tensor::I64(ArrayD::zeros(IxDyn(&[0, 3])));
+  println!("reshape [3, 0] allowzero: {:?}", reshape(&empty, &[3, 0], true).map(|t| t.shape().to_vec()));
+  println!("expected: [3, 0]");
+  println!("reshape [5, -1]: {:?}", reshape(&data, &[5, -1], false));
+  println!("expected: error");
 
-  let reshaped = reshape(data.into(), orig, Some(0));
+  println!("flatten axis 2: {:?}", flatten(&data, 2).shape());
+  println!("expected: [6, 4]");
+  println!("flatten axis 0: {:?}", flatten(&data, 0).shape());
+  println!("expected: [1, 24]");
 
-  println!("reshaped: \n{:?}", reshaped);
+  let x = Tensor::I32(Array::from_shape_vec((1, 3, 1), vec![1, 2, 3]).unwrap().into_dyn());
+  println!("squeeze all: {:?}", squeeze(&x, &[]));
+  println!("expected: [1, 2, 3]");
+  println!("squeeze axis 2: {:?}", squeeze(&x, &[2]).map(|t| t.shape().to_vec()));
+  println!("expected: [1, 3]");
+  println!("squeeze axis 1: {:?}", squeeze(&x, &[1]));
+  println!("expected: error");
+  println!("unsqueeze axes 0, 3: {:?}", unsqueeze(&x, &[0, 3]).map(|t| t.shape().to_vec()));
+  println!("expected: [1, 1, 3, 1, 1]");
 }
@@ -0,0 +1,147 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use crate::inference_session::PreparedNode;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/*
+Dependencies among the nodes of the graph, computed once when the session is created
+  ~ pending_inputs: for each node, how many other nodes produce its inputs (the node is ready when all of them are done)
+  ~ consumers: for each node, the nodes using at least one of its outputs
+*/
+pub(crate) struct DependencyGraph {
+  pub(crate) pending_inputs: Vec<usize>,
+  pub(crate) consumers: Vec<Vec<usize>>,
+}
+
+impl DependencyGraph {
+  /*
+  This function computes the dependencies of the nodes
+    -It takes 1 parameter:
+      ~ nodes: nodes of the graph, in topological order
+    -Inputs that aren't produced by any node (graph inputs and initializers) don't count as dependencies
+  */
+  pub(crate) fn new(nodes: &[PreparedNode]) -> DependencyGraph {
+    let mut produced_by: HashMap<&str, usize> = HashMap::new();
+    for (position, node) in nodes.iter().enumerate() {
+      for output in &node.proto.output {
+        if !output.is_empty() {
+          produced_by.insert(output.as_str(), position);
+        }
+      }
+    }
+
+    let mut pending_inputs = vec![0; nodes.len()];
+    let mut consumers: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    for (position, node) in nodes.iter().enumerate() {
+      let mut producers: Vec<usize> = node.proto.input.iter()
+        .filter_map(|input| produced_by.get(input.as_str()).copied())
+        .collect();
+      /* A node that reads two outputs of the same producer depends on it only once */
+      producers.sort_unstable();
+      producers.dedup();
+
+      pending_inputs[position] = producers.len();
+      for producer in producers {
+        consumers[producer].push(position);
+      }
+    }
+
+    DependencyGraph { pending_inputs, consumers }
+  }
+}
+
+/*
+Fixed set of worker threads executing the jobs submitted by the inference runs.
+The threads are started once with the session and stopped when the pool is dropped.
+*/
+pub(crate) struct ThreadPool {
+  shared: Arc<PoolShared>,
+  workers: Vec<JoinHandle<()>>,
+}
+
+/* Handle used to submit jobs to the pool, also from inside a running job */
+#[derive(Clone)]
+pub(crate) struct PoolHandle {
+  shared: Arc<PoolShared>,
+}
+
+struct PoolShared {
+  queue: Mutex<PoolQueue>,
+  job_available: Condvar,
+}
+
+struct PoolQueue {
+  jobs: VecDeque<Job>,
+  shutdown: bool,
+}
+
+impl ThreadPool {
+  /*
+  This function starts the worker threads
+    -It takes 1 parameter:
+      ~ num_threads: number of workers
+  */
+  pub(crate) fn new(num_threads: usize) -> ThreadPool {
+    let shared = Arc::new(PoolShared {
+      queue: Mutex::new(PoolQueue { jobs: VecDeque::new(), shutdown: false }),
+      job_available: Condvar::new(),
+    });
+
+    let workers = (0..num_threads).map(|n_t| {
+      let t_shared = shared.clone();
+      thread::Builder::new()
+        .name(format!("{}{}", "Worker", n_t))
+        .spawn(move || worker_loop(&t_shared))
+        .expect("PROBLEM STARTING WORKER THREAD")
+    }).collect();
+
+    ThreadPool { shared, workers }
+  }
+
+  pub(crate) fn handle(&self) -> PoolHandle {
+    PoolHandle { shared: self.shared.clone() }
+  }
+
+  pub(crate) fn num_threads(&self) -> usize {
+    self.workers.len()
+  }
+}
+
+impl PoolHandle {
+  /* Queues a job: the first idle worker will execute it */
+  pub(crate) fn execute(&self, job: impl FnOnce() + Send + 'static) {
+    let mut queue = self.shared.queue.lock().unwrap();
+    queue.jobs.push_back(Box::new(job));
+    self.shared.job_available.notify_one();
+  }
+}
+
+impl Drop for ThreadPool {
+  fn drop(&mut self) {
+    self.shared.queue.lock().unwrap().shutdown = true;
+    self.shared.job_available.notify_all();
+    for worker in self.workers.drain(..) {
+      worker.join().expect("PROBLEM JOINING");
+    }
+  }
+}
+
+/* Body of the worker threads: waits for jobs until the pool is dropped (pending jobs are completed first) */
+fn worker_loop(shared: &PoolShared) {
+  loop {
+    let mut queue = shared.queue.lock().unwrap();
+    while queue.jobs.is_empty() && !queue.shutdown {
+      queue = shared.job_available.wait(queue).unwrap();
+    }
+    match queue.jobs.pop_front() {
+      Some(job) => {
+        drop(queue); /* The lock is released while the job runs */
+        job();
+      }
+      None => return
+    }
+  }
+}
@@ -0,0 +1,991 @@
+use std::collections::HashMap;
+use protobuf::{Enum, MessageField};
+use crate::indexing_op::{slice_range, split_sizes};
+use crate::inference_error::InferenceError;
+use crate::inference_session::NodeAttributes;
+use crate::onnx_structure::{ModelProto, NodeProto, TensorProto, TensorShapeProto, TypeProto, ValueInfoProto};
+use crate::onnx_structure::tensor_proto::DataType;
+use crate::onnx_structure::tensor_shape_proto::Dimension;
+use crate::onnx_structure::tensor_shape_proto::dimension::Value::{DimParam, DimValue};
+use crate::onnx_structure::type_proto::{Tensor as TypeProtoTensor, Value};
+use crate::op_registry::{opset_imports, OperatorRegistry};
+use crate::tensor::{Dim, Tensor, TensorType};
+
+/*
+This function infers the type of every value of the graph and stores it into graph.value_info
+(the declarations of graph inputs and outputs are left as they are)
+  -It takes 1 parameter:
+    ~ model: the onnx model, updated in place
+  -It returns the first inconsistency found (i.e. incompatible shapes, output declared with a different shape)
+*/
+pub fn infer_shapes(model: &mut ModelProto) -> Result<(), InferenceError> {
+  infer_shapes_with(model, &OperatorRegistry::default())
+}
+
+/*
+This function is infer_shapes for models with custom operators: the types of their outputs are computed by the
+kernels added to the registry (see OpKernel::infer_shape)
+  -It takes 2 parameters:
+    ~ model: the onnx model, updated in place
+    ~ operators: kernels of the operators used by the model
+*/
+pub fn infer_shapes_with(model: &mut ModelProto, operators: &OperatorRegistry) -> Result<(), InferenceError> {
+  let types = infer_value_types_with(model, operators)?;
+
+  let graph = match model.graph.as_mut() {
+    Some(graph) => graph,
+    None => return Err(InferenceError::MalformedModel("model without graph".to_string()))
+  };
+  let declared_elsewhere: Vec<String> = graph.input.iter().chain(graph.output.iter()).filter_map(|v| v.name.clone()).collect();
+
+  let mut value_info = Vec::new();
+  for node in &graph.node {
+    for output in &node.output {
+      if output.is_empty() || declared_elsewhere.contains(output) {
+        continue;
+      }
+      if let Some(value_type) = types.get(output) {
+        value_info.push(to_value_info(output, value_type));
+      }
+    }
+  }
+  graph.value_info = value_info;
+  Ok(())
+}
+
+/*
+This function infers the type of every value of the graph, walking the nodes in order
+  -It takes 1 parameter:
+    ~ model: the onnx model
+  -It returns the types indexed by value name (graph inputs, initializers and node outputs), or the first inconsistency found
+*/
+pub fn infer_value_types(model: &ModelProto) -> Result<HashMap<String, TensorType>, InferenceError> {
+  infer_value_types_with(model, &OperatorRegistry::default())
+}
+
+/*
+This function is infer_value_types for models with custom operators
+  -It takes 2 parameters:
+    ~ model: the onnx model
+    ~ operators: kernels of the operators used by the model
+*/
+pub fn infer_value_types_with(model: &ModelProto, operators: &OperatorRegistry) -> Result<HashMap<String, TensorType>, InferenceError> {
+  let opsets = opset_imports(model)?;
+  let graph = match model.graph.as_ref() {
+    Some(graph) => graph,
+    None => return Err(InferenceError::MalformedModel("model without graph".to_string()))
+  };
+
+  let initializers: HashMap<&str, &TensorProto> = graph.initializer.iter().map(|init| (init.name.as_deref().unwrap_or_default(), init)).collect();
+  let mut types: HashMap<String, TensorType> = HashMap::new();
+  for init in &graph.initializer {
+    types.insert(init.name.clone().unwrap_or_default(), TensorType {
+      elem_type: DataType::from_i32(init.data_type.unwrap_or(0)).unwrap_or(DataType::UNDEFINED),
+      shape: Some(init.dims.iter().map(|&d| Dim::Value(d as usize)).collect()),
+    });
+  }
+  for inp in &graph.input {
+    types.insert(inp.name.clone().unwrap_or_default(), TensorType::from_value_info(inp)?);
+  }
+  /* Declarations of the values produced by the nodes, to be compared with the inferred types */
+  let declared: HashMap<String, &ValueInfoProto> = graph.output.iter().chain(graph.value_info.iter())
+    .map(|v| (v.name.clone().unwrap_or_default(), v)).collect();
+
+  for node in &graph.node {
+    let attributes = NodeAttributes::new(&node.attribute);
+    let mut inputs: Vec<Option<&TensorType>> = Vec::with_capacity(node.input.len());
+    for input in &node.input {
+      if input.is_empty() {
+        inputs.push(None);
+      } else {
+        match types.get(input) {
+          Some(t) => inputs.push(Some(t)),
+          None => return Err(InferenceError::missing_input(node, input))
+        }
+      }
+    }
+    let constants: Vec<Option<&TensorProto>> = node.input.iter().map(|name| initializers.get(name.as_str()).copied()).collect();
+
+    let outputs = operators.resolve(node, &opsets)?.infer_shape(node, &attributes, &inputs, &constants)?;
+
+    for (name, inferred) in node.output.iter().zip(outputs) {
+      if name.is_empty() {
+        continue;
+      }
+      let value_type = match declared.get(name) {
+        Some(declaration) => merge_types(node, name, inferred, &TensorType::from_value_info(declaration)?)?,
+        None => inferred
+      };
+      types.insert(name.clone(), value_type);
+    }
+  }
+
+  Ok(types)
+}
+
+/*
+This function computes the types of the outputs of a node
+  -It takes 5 parameters:
+    ~ node: considered node
+    ~ since_version: opset version of the implementation chosen for the node (see OperatorRegistry::resolve)
+    ~ attributes: attributes of the node
+    ~ inputs: types of the node inputs (None for the optional inputs not provided)
+    ~ constants: initializers feeding the node inputs, if any (i.e. the shape of a Reshape)
+  -It returns the types of the node outputs, or ShapeMismatch/BadAttribute if the inputs aren't valid for the operation
+*/
+pub(crate) fn infer_node(node: &NodeProto, since_version: i64, attributes: &NodeAttributes, inputs: &[Option<&TensorType>], constants: &[Option<&TensorProto>]) -> Result<Vec<TensorType>, InferenceError> {
+  let input = |i: usize| -> Result<&TensorType, InferenceError> {
+    match inputs.get(i) {
+      Some(Some(t)) => Ok(*t),
+      _ => Err(InferenceError::missing_input(node, node.input.get(i).map(|s| s.as_str()).unwrap_or_default()))
+    }
+  };
+
+  match node.op_type.as_deref().unwrap_or_default() {
+    "Relu" | "LeakyRelu" | "PRelu" | "Elu" | "Selu" | "Sigmoid" | "HardSigmoid" | "HardSwish" | "Tanh" | "Softplus"
+    | "Softsign" | "Gelu" | "Mish" | "Clip" | "ThresholdedRelu" | "Softmax" | "BatchNormalization" | "InstanceNormalization" | "GroupNormalization" | "RMSNormalization"
+    | "Exp" | "Log" | "Sqrt" | "Reciprocal" | "Abs" | "Neg" | "Floor" | "Ceil" | "Round" | "Sign" | "Erf" | "Sin" | "Cos" | "Tan"
+    | "Asin" | "Acos" | "Atan" | "Sinh" | "Cosh" | "Asinh" | "Acosh" | "Atanh" | "Identity" => Ok(vec![input(0)?.clone()]),
+    "LayerNormalization" => {
+      let x = input(0)?;
+      /* Mean and InvStdDev keep the dimensions before axis, the others become 1 */
+      let statistics_shape = match &x.shape {
+        Some(shape) => {
+          let rank = shape.len() as i64;
+          let axis = attributes.int("axis").unwrap_or(-1);
+          let axis = if axis < 0 { axis + rank } else { axis };
+          if axis < 0 || axis >= rank {
+            return Err(InferenceError::bad_attribute(node, "axis", format!("axis {} out of range for rank {}", axis, rank)));
+          }
+          Some(shape.iter().enumerate().map(|(d, dim)| if (d as i64) < axis { dim.clone() } else { Dim::Value(1) }).collect())
+        }
+        None => None
+      };
+      let statistics = TensorType { elem_type: DataType::FLOAT, shape: statistics_shape };
+      Ok(vec![x.clone(), statistics.clone(), statistics])
+    }
+    "ReduceSum" | "ReduceMean" | "ReduceMax" | "ReduceMin" | "ReduceProd" | "ReduceL1" | "ReduceL2" | "ReduceLogSum"
+    | "ReduceLogSumExp" | "ReduceSumSquare" => {
+      let x = input(0)?;
+      let axes: Option<Vec<i64>> = match (attributes.ints("axes"), constants.get(1).copied().flatten()) {
+        (Some(axes), _) => Some(axes.to_vec()),
+        (None, Some(proto)) => Some(Tensor::from_proto(proto)?.to_i64().iter().copied().collect()),
+        /* Axes computed at run time */
+        (None, None) if inputs.get(1).map(|i| i.is_some()).unwrap_or(false) => None,
+        (None, None) => Some(Vec::new())
+      };
+      let keepdims = attributes.int("keepdims").unwrap_or(1) != 0;
+      let shape = match (&x.shape, axes) {
+        (Some(shape), Some(axes)) => {
+          if axes.is_empty() && attributes.int("noop_with_empty_axes").unwrap_or(0) != 0 {
+            Some(shape.clone())
+          } else {
+            let rank = shape.len() as i64;
+            let mut reduced = vec![axes.is_empty(); shape.len()];
+            for axis in axes {
+              let normalized = if axis < 0 { axis + rank } else { axis };
+              if normalized < 0 || normalized >= rank {
+                return Err(InferenceError::bad_attribute(node, "axes", format!("axis {} out of range for rank {}", axis, rank)));
+              }
+              reduced[normalized as usize] = true;
+            }
+            Some(shape.iter().zip(reduced).filter_map(|(dim, r)| match (r, keepdims) {
+              (false, _) => Some(dim.clone()),
+              (true, true) => Some(Dim::Value(1)),
+              (true, false) => None
+            }).collect())
+          }
+        }
+        _ => None
+      };
+      Ok(vec![TensorType { elem_type: x.elem_type, shape }])
+    }
+    "ArgMax" | "ArgMin" => {
+      let x = input(0)?;
+      let keepdims = attributes.int("keepdims").unwrap_or(1) != 0;
+      let shape = match &x.shape {
+        Some(shape) => {
+          let rank = shape.len() as i64;
+          let axis = attributes.int("axis").unwrap_or(0);
+          let normalized = if axis < 0 { axis + rank } else { axis };
+          if normalized < 0 || normalized >= rank {
+            return Err(InferenceError::bad_attribute(node, "axis", format!("axis {} out of range for rank {}", axis, rank)));
+          }
+          let mut shape = shape.clone();
+          if keepdims { shape[normalized as usize] = Dim::Value(1); } else { shape.remove(normalized as usize); }
+          Some(shape)
+        }
+        None => None
+      };
+      Ok(vec![TensorType { elem_type: DataType::INT64, shape }])
+    }
+    "Dropout" => {
+      let x = input(0)?;
+      Ok(vec![x.clone(), TensorType { elem_type: DataType::BOOL, shape: x.shape.clone() }])
+    }
+    "Conv" => infer_conv(node, attributes, input(0)?, input(1)?),
+    "ConvTranspose" => infer_conv_transpose(node, attributes, input(0)?, input(1)?),
+    "MaxPool" | "AveragePool" | "LpPool" => {
+      let x = input(0)?;
+      let kernel: Vec<usize> = match attributes.ints("kernel_shape") {
+        Some(k) => k.iter().map(|&v| v as usize).collect(),
+        None => return Err(InferenceError::bad_attribute(node, "kernel_shape", "required attribute not set"))
+      };
+      let shape = match &x.shape {
+        Some(shape) => {
+          if shape.len() != kernel.len() + 2 {
+            return Err(InferenceError::shape_mismatch(node, format!("input {:?} and kernel_shape {:?} have incompatible ranks", shape, kernel)));
+          }
+          let mut out = vec![shape[0].clone(), shape[1].clone()];
+          out.extend(spatial_output_dims(node, attributes, &shape[2..], &kernel)?);
+          Some(out)
+        }
+        None => None
+      };
+      Ok(vec![TensorType { elem_type: x.elem_type, shape }])
+    }
+    "GlobalAveragePool" | "GlobalMaxPool" => {
+      let x = input(0)?;
+      let shape = x.shape.as_ref().map(|shape| {
+        let mut out = shape[..2.min(shape.len())].to_vec();
+        out.extend(std::iter::repeat_n(Dim::Value(1), shape.len().saturating_sub(2)));
+        out
+      });
+      Ok(vec![TensorType { elem_type: x.elem_type, shape }])
+    }
+    "Concat" => {
+      /* Before opset 4 axis is optional (default 1) */
+      let axis = match attributes.int("axis") {
+        Some(axis) => axis,
+        None if since_version < 4 => 1,
+        None => return Err(InferenceError::bad_attribute(node, "axis", "required attribute not set"))
+      };
+      let first = input(0)?;
+      let mut elem_type = first.elem_type;
+      let mut shape = first.shape.clone();
+      for i in 1..inputs.len() {
+        let other = input(i)?;
+        /* An undefined element type (i.e. the output of a custom operator) is unknown, not a different type */
+        elem_type = match (elem_type, other.elem_type) {
+          (DataType::UNDEFINED, t) => t,
+          (t, DataType::UNDEFINED) => t,
+          (t, o) if t == o => t,
+          (t, o) => return Err(InferenceError::shape_mismatch(node, format!("cannot concatenate element types {:?} and {:?}", t, o)))
+        };
+        shape = match (shape, &other.shape) {
+          (Some(acc), Some(other_shape)) => {
+            if acc.len() != other_shape.len() {
+              return Err(InferenceError::shape_mismatch(node, format!("cannot concatenate {:?} and {:?}", acc, other_shape)));
+            }
+            let axis = normalize_dim(node, "axis", axis, acc.len())?;
+            let mut out = Vec::with_capacity(acc.len());
+            for (d, (a, b)) in acc.iter().zip(other_shape).enumerate() {
+              out.push(if d == axis {
+                match (a, b) {
+                  (Dim::Value(x), Dim::Value(y)) => Dim::Value(x + y),
+                  _ => Dim::Unknown
+                }
+              } else {
+                merge_dim(a, b).ok_or_else(|| InferenceError::shape_mismatch(node, format!("cannot concatenate {:?} and {:?} on axis {}", acc, other_shape, axis)))?
+              });
+            }
+            Some(out)
+          }
+          _ => None
+        };
+      }
+      Ok(vec![TensorType { elem_type, shape }])
+    }
+    "Reshape" => {
+      let data = input(0)?;
+      let target = input(1)?;
+      let shape = match constants.get(1).copied().flatten() {
+        Some(proto) => Some(reshape_dims(node, data, &Tensor::from_proto(proto)?.to_i64().iter().copied().collect::<Vec<i64>>(), attributes.int("allowzero").unwrap_or(0) != 0)?),
+        /* Shape computed at run time: only the rank is known */
+        None => match &target.shape {
+          Some(s) if s.len() == 1 => match s[0] {
+            Dim::Value(rank) => Some(vec![Dim::Unknown; rank]),
+            _ => None
+          },
+          _ => None
+        }
+      };
+      Ok(vec![TensorType { elem_type: data.elem_type, shape }])
+    }
+    "Transpose" => {
+      let x = input(0)?;
+      let shape = match &x.shape {
+        Some(shape) => {
+          let perm: Vec<usize> = match attributes.ints("perm") {
+            Some(perm) => perm.iter().map(|&axis| normalize_dim(node, "perm", axis, shape.len())).collect::<Result<Vec<usize>, InferenceError>>()?,
+            None => (0..shape.len()).rev().collect()
+          };
+          if perm.len() != shape.len() {
+            return Err(InferenceError::bad_attribute(node, "perm", format!("perm {:?} is not a permutation of the dimensions of {:?}", perm, shape)));
+          }
+          Some(perm.iter().map(|&d| shape[d].clone()).collect())
+        }
+        None => None
+      };
+      Ok(vec![TensorType { elem_type: x.elem_type, shape }])
+    }
+    "Flatten" => {
+      let x = input(0)?;
+      let shape = match &x.shape {
+        Some(shape) => {
+          let axis = normalize_dim(node, "axis", attributes.int("axis").unwrap_or(1), shape.len() + 1)?;
+          let product = |dims: &[Dim]| fixed_size(dims).map(Dim::Value).unwrap_or(Dim::Unknown);
+          Some(vec![product(&shape[..axis]), product(&shape[axis..])])
+        }
+        None => Some(vec![Dim::Unknown, Dim::Unknown])
+      };
+      Ok(vec![TensorType { elem_type: x.elem_type, shape }])
+    }
+    "Squeeze" | "Unsqueeze" => {
+      let x = input(0)?;
+      let axes: Option<Vec<i64>> = match (attributes.ints("axes"), constant_ints(constants, 1)?) {
+        (Some(axes), _) => Some(axes.to_vec()),
+        (None, Some(axes)) => Some(axes),
+        /* Axes computed at run time */
+        (None, None) if inputs.get(1).map(|i| i.is_some()).unwrap_or(false) => None,
+        (None, None) => Some(Vec::new())
+      };
+      let shape = match (&x.shape, axes) {
+        (Some(shape), Some(axes)) if node.op_type.as_deref() == Some("Squeeze") => {
+          let axes = axes.iter().map(|&axis| normalize_dim(node, "axes", axis, shape.len())).collect::<Result<Vec<usize>, InferenceError>>()?;
+          if let Some(&axis) = axes.iter().find(|&&axis| matches!(shape[axis], Dim::Value(size) if size != 1)) {
+            return Err(InferenceError::shape_mismatch(node, format!("cannot squeeze axis {} of {:?}", axis, shape)));
+          }
+          /* Without axes the dimensions of size 1 are known only if every dimension is */
+          if axes.is_empty() && fixed_size(shape).is_none() {
+            None
+          } else {
+            Some(shape.iter().enumerate()
+              .filter(|&(d, dim)| if axes.is_empty() { *dim != Dim::Value(1) } else { !axes.contains(&d) })
+              .map(|(_, dim)| dim.clone())
+              .collect())
+          }
+        }
+        (Some(shape), Some(axes)) => {
+          let rank = shape.len() + axes.len();
+          let axes = axes.iter().map(|&axis| normalize_dim(node, "axes", axis, rank)).collect::<Result<Vec<usize>, InferenceError>>()?;
+          let mut input_dims = shape.iter();
+          Some((0..rank).map(|d| if axes.contains(&d) { Dim::Value(1) } else { input_dims.next().cloned().unwrap_or(Dim::Unknown) }).collect())
+        }
+        _ => None
+      };
+      Ok(vec![TensorType { elem_type: x.elem_type, shape }])
+    }
+    "Expand" => {
+      let x = input(0)?;
+      let shape = match (&x.shape, constant_ints(constants, 1)?) {
+        (Some(shape), Some(target)) => {
+          let target: Vec<Dim> = target.iter().map(|&dim| Dim::Value(dim.max(0) as usize)).collect();
+          Some(broadcast_dims(shape, &target).ok_or_else(|| InferenceError::shape_mismatch(node, format!("cannot broadcast {:?} with {:?}", shape, target)))?)
+        }
+        _ => None
+      };
+      Ok(vec![TensorType { elem_type: x.elem_type, shape }])
+    }
+    "Tile" => {
+      let x = input(0)?;
+      let shape = match (&x.shape, constant_ints(constants, 1)?) {
+        (Some(shape), Some(repeats)) => {
+          if repeats.len() != shape.len() {
+            return Err(InferenceError::shape_mismatch(node, format!("repeats {:?} is not valid for {:?}", repeats, shape)));
+          }
+          Some(shape.iter().zip(repeats).map(|(dim, r)| match (dim, r) {
+            (dim, 1) => dim.clone(),
+            (Dim::Value(size), r) => Dim::Value(size * r.max(0) as usize),
+            _ => Dim::Unknown
+          }).collect())
+        }
+        (shape, _) => shape.as_ref().map(|s| vec![Dim::Unknown; s.len()])
+      };
+      Ok(vec![TensorType { elem_type: x.elem_type, shape }])
+    }
+    "Slice" => {
+      let x = input(0)?;
+      /* Up to opset 9 starts, ends and axes are attributes */
+      let parameter = |name: &str, i: usize| -> Result<Option<Vec<i64>>, InferenceError> {
+        match attributes.ints(name) {
+          Some(values) => Ok(Some(values.to_vec())),
+          None => constant_ints(constants, i)
+        }
+      };
+      let (starts, ends) = (parameter("starts", 1)?, parameter("ends", 2)?);
+      let axes = match parameter("axes", 3)? {
+        Some(axes) => Some(axes),
+        None if inputs.get(3).map(|i| i.is_some()).unwrap_or(false) => None,
+        None => starts.as_ref().map(|starts| (0..starts.len() as i64).collect())
+      };
+      let steps = match constant_ints(constants, 4)? {
+        Some(steps) => Some(steps),
+        None if inputs.get(4).map(|i| i.is_some()).unwrap_or(false) => None,
+        None => starts.as_ref().map(|starts| vec![1; starts.len()])
+      };
+      let shape = match (&x.shape, starts, ends, axes, steps) {
+        (Some(shape), Some(starts), Some(ends), Some(axes), Some(steps)) => {
+          if starts.len() != axes.len() || ends.len() != axes.len() || steps.len() != axes.len() || steps.contains(&0) {
+            return Err(InferenceError::shape_mismatch(node, format!("invalid slice: starts {:?}, ends {:?}, axes {:?}, steps {:?}", starts, ends, axes, steps)));
+          }
+          let mut out = shape.clone();
+          for i in 0..axes.len() {
+            let axis = normalize_dim(node, "axes", axes[i], shape.len())?;
+            out[axis] = match &shape[axis] {
+              Dim::Value(size) => Dim::Value(slice_range(*size, starts[i], ends[i], steps[i]).1),
+              _ => Dim::Unknown
+            };
+          }
+          Some(out)
+        }
+        (Some(shape), ..) => Some(vec![Dim::Unknown; shape.len()]),
+        _ => None
+      };
+      Ok(vec![TensorType { elem_type: x.elem_type, shape }])
+    }
+    "Gather" => {
+      let (data, indices) = (input(0)?, input(1)?);
+      let shape = match (&data.shape, &indices.shape) {
+        (Some(data_shape), Some(indices_shape)) => {
+          let axis = normalize_dim(node, "axis", attributes.int("axis").unwrap_or(0), data_shape.len())?;
+          Some(data_shape[..axis].iter().chain(indices_shape).chain(&data_shape[axis + 1..]).cloned().collect())
+        }
+        _ => None
+      };
+      Ok(vec![TensorType { elem_type: data.elem_type, shape }])
+    }
+    "GatherElements" => Ok(vec![TensorType { elem_type: input(0)?.elem_type, shape: input(1)?.shape.clone() }]),
+    "GatherND" => {
+      let (data, indices) = (input(0)?, input(1)?);
+      let batch_dims = attributes.int("batch_dims").unwrap_or(0).max(0) as usize;
+      let shape = match (&data.shape, &indices.shape) {
+        (Some(data_shape), Some(indices_shape)) => match indices_shape.last() {
+          Some(Dim::Value(k)) if batch_dims + k <= data_shape.len() => {
+            Some(indices_shape[..indices_shape.len() - 1].iter().chain(&data_shape[batch_dims + k..]).cloned().collect())
+          }
+          Some(Dim::Value(_)) | None => return Err(InferenceError::shape_mismatch(node, format!("indices {:?} are not compatible with the input {:?}", indices_shape, data_shape))),
+          Some(_) => None
+        },
+        _ => None
+      };
+      Ok(vec![TensorType { elem_type: data.elem_type, shape }])
+    }
+    "ScatterND" => Ok(vec![input(0)?.clone()]),
+    "Split" => {
+      let x = input(0)?;
+      let count = node.output.len();
+      let sizes = match attributes.ints("split") {
+        Some(split) => Some(split.to_vec()),
+        None => constant_ints(constants, 1)?
+      };
+      let shapes: Vec<Option<Vec<Dim>>> = match &x.shape {
+        Some(shape) => {
+          let axis = normalize_dim(node, "axis", attributes.int("axis").unwrap_or(0), shape.len())?;
+          let sizes: Option<Vec<usize>> = match (sizes, &shape[axis]) {
+            (Some(sizes), _) => Some(sizes.iter().map(|&size| size.max(0) as usize).collect()),
+            /* Sizes computed at run time */
+            (None, _) if inputs.get(1).map(|i| i.is_some()).unwrap_or(false) => None,
+            (None, Dim::Value(size)) => Some(split_sizes(*size, attributes.int("num_outputs").map(|n| n.max(1) as usize).unwrap_or(count))),
+            (None, _) => None
+          };
+          (0..count).map(|i| {
+            let mut part = shape.clone();
+            part[axis] = match &sizes {
+              Some(sizes) => sizes.get(i).map(|&size| Dim::Value(size)).unwrap_or(Dim::Unknown),
+              None => Dim::Unknown
+            };
+            Some(part)
+          }).collect()
+        }
+        None => vec![None; count]
+      };
+      Ok(shapes.into_iter().map(|shape| TensorType { elem_type: x.elem_type, shape }).collect())
+    }
+    "Pad" => {
+      let x = input(0)?;
+      let pads = match attributes.ints("pads") {
+        Some(pads) => Some(pads.to_vec()),
+        None => constant_ints(constants, 1)?
+      };
+      let shape = match (&x.shape, pads) {
+        /* With the axes input the padded dimensions are known only if it is constant */
+        (Some(shape), Some(pads)) if inputs.get(3).map(|i| i.is_none()).unwrap_or(true) => {
+          let rank = shape.len();
+          if pads.len() != 2 * rank {
+            return Err(InferenceError::shape_mismatch(node, format!("pads {:?} must have 2 values for each dimension of {:?}", pads, shape)));
+          }
+          Some(shape.iter().enumerate().map(|(d, dim)| match dim {
+            Dim::Value(size) => Dim::Value((*size as i64 + pads[d] + pads[rank + d]).max(0) as usize),
+            _ if pads[d] == 0 && pads[rank + d] == 0 => dim.clone(),
+            _ => Dim::Unknown
+          }).collect())
+        }
+        (Some(shape), _) => Some(vec![Dim::Unknown; shape.len()]),
+        _ => None
+      };
+      Ok(vec![TensorType { elem_type: x.elem_type, shape }])
+    }
+    "Add" | "Sub" | "Mul" | "Div" | "Pow" | "Mod" | "Max" | "Min" => {
+      let first = input(0)?;
+      let count = if matches!(node.op_type.as_deref(), Some("Max") | Some("Min")) { inputs.len().max(1) } else { 2 };
+      let mut shape = first.shape.clone();
+      for i in 1..count {
+        let other = input(i)?;
+        shape = match (shape, &other.shape) {
+          (Some(sa), Some(sb)) => Some(broadcast_dims(&sa, sb).ok_or_else(|| InferenceError::shape_mismatch(node, format!("cannot broadcast {:?} with {:?}", sa, sb)))?),
+          _ => None
+        };
+      }
+      Ok(vec![TensorType { elem_type: first.elem_type, shape }])
+    }
+    "MatMul" => {
+      let a = input(0)?;
+      let b = input(1)?;
+      let shape = match (&a.shape, &b.shape) {
+        (Some(sa), Some(sb)) => Some(matmul_dims(node, sa, sb)?),
+        _ => None
+      };
+      Ok(vec![TensorType { elem_type: a.elem_type, shape }])
+    }
+    "Gemm" => {
+      let a = input(0)?;
+      let b = input(1)?;
+      let shape = match (&a.shape, &b.shape) {
+        (Some(sa), Some(sb)) => {
+          if sa.len() != 2 || sb.len() != 2 {
+            return Err(InferenceError::shape_mismatch(node, format!("cannot multiply {:?} by {:?}, expected matrices", sa, sb)));
+          }
+          let (m, k_a) = if attributes.int("transA").unwrap_or(0) != 0 { (&sa[1], &sa[0]) } else { (&sa[0], &sa[1]) };
+          let (k_b, n) = if attributes.int("transB").unwrap_or(0) != 0 { (&sb[1], &sb[0]) } else { (&sb[0], &sb[1]) };
+          if merge_dim(k_a, k_b).is_none() {
+            return Err(InferenceError::shape_mismatch(node, format!("cannot multiply {:?} by {:?}", sa, sb)));
+          }
+          let out = vec![m.clone(), n.clone()];
+          /* C must be unidirectionally broadcastable to (M, N) */
+          if let Some(Some(TensorType { shape: Some(sc), .. })) = inputs.get(2) {
+            let compatible = sc.len() <= 2 && sc.iter().rev().zip(out.iter().rev()).all(|(c, o)| matches!(c, Dim::Value(1)) || merge_dim(c, o).is_some());
+            if !compatible {
+              return Err(InferenceError::shape_mismatch(node, format!("cannot broadcast C {:?} to {:?}", sc, out)));
+            }
+          }
+          Some(out)
+        }
+        _ => None
+      };
+      Ok(vec![TensorType { elem_type: a.elem_type, shape }])
+    }
+    _ => Err(InferenceError::unsupported_op(node))
+  }
+}
+
+/*
+This function infers the output type of a convolution
+  -It takes 4 parameters:
+    ~ node: considered node
+    ~ attributes: attributes of the node
+    ~ x: type of the input image (N, C, spatial dims...)
+    ~ w: type of the weights (M, C/group, kernel dims...)
+*/
+fn infer_conv(node: &NodeProto, attributes: &NodeAttributes, x: &TensorType, w: &TensorType) -> Result<Vec<TensorType>, InferenceError> {
+  let (x_shape, w_shape) = match (&x.shape, &w.shape) {
+    (Some(xs), Some(ws)) => (xs, ws),
+    _ => return Ok(vec![TensorType { elem_type: x.elem_type, shape: None }])
+  };
+  if x_shape.len() != w_shape.len() || x_shape.len() < 3 {
+    return Err(InferenceError::shape_mismatch(node, format!("input {:?} and weights {:?} have incompatible ranks", x_shape, w_shape)));
+  }
+  let group = attributes.int("group").unwrap_or(1);
+  if let (Dim::Value(c), Dim::Value(cg)) = (&x_shape[1], &w_shape[1]) {
+    if *c as i64 != *cg as i64 * group {
+      return Err(InferenceError::shape_mismatch(node, format!("input {:?} and weights {:?} are not compatible with group {}", x_shape, w_shape, group)));
+    }
+  }
+
+  let kernel: Vec<usize> = match attributes.ints("kernel_shape") {
+    Some(k) => k.iter().map(|&v| v as usize).collect(),
+    None => {
+      let mut k = Vec::new();
+      for d in &w_shape[2..] {
+        match d {
+          Dim::Value(v) => k.push(*v),
+          _ => return Ok(vec![TensorType { elem_type: x.elem_type, shape: None }])
+        }
+      }
+      k
+    }
+  };
+
+  let mut shape = vec![x_shape[0].clone(), w_shape[0].clone()];
+  shape.extend(spatial_output_dims(node, attributes, &x_shape[2..], &kernel)?);
+  Ok(vec![TensorType { elem_type: x.elem_type, shape: Some(shape) }])
+}
+
+/*
+This function computes the output of a transposed convolution: (N, M, D1', D2', ...) from X (N, C, D1, D2, ...)
+and W (C, M/group, k1, k2, ...)
+  -It takes 4 parameters:
+    ~ node: considered node
+    ~ attributes: attributes of the node
+    ~ x: type of the input
+    ~ w: type of the weights
+*/
+fn infer_conv_transpose(node: &NodeProto, attributes: &NodeAttributes, x: &TensorType, w: &TensorType) -> Result<Vec<TensorType>, InferenceError> {
+  let (x_shape, w_shape) = match (&x.shape, &w.shape) {
+    (Some(xs), Some(ws)) => (xs, ws),
+    _ => return Ok(vec![TensorType { elem_type: x.elem_type, shape: None }])
+  };
+  if x_shape.len() != w_shape.len() || x_shape.len() < 3 {
+    return Err(InferenceError::shape_mismatch(node, format!("input {:?} and weights {:?} have incompatible ranks", x_shape, w_shape)));
+  }
+  if let (Dim::Value(c), Dim::Value(wc)) = (&x_shape[1], &w_shape[0]) {
+    if c != wc {
+      return Err(InferenceError::shape_mismatch(node, format!("input {:?} and weights {:?} have different channels", x_shape, w_shape)));
+    }
+  }
+  let group = attributes.int("group").unwrap_or(1).max(1) as usize;
+  let maps = match &w_shape[1] {
+    Dim::Value(m) => Dim::Value(m * group),
+    _ => Dim::Unknown
+  };
+
+  let n = x_shape.len() - 2;
+  let mut shape = vec![x_shape[0].clone(), maps];
+  if let Some(output_shape) = attributes.ints("output_shape") {
+    if output_shape.len() < n {
+      return Err(InferenceError::bad_attribute(node, "output_shape", format!("expected {} values", n)));
+    }
+    shape.extend(output_shape[output_shape.len() - n..].iter().map(|&d| Dim::Value(d as usize)));
+    return Ok(vec![TensorType { elem_type: x.elem_type, shape: Some(shape) }]);
+  }
+
+  let strides = attributes.ints("strides").map(|s| s.to_vec()).unwrap_or_else(|| vec![1; n]);
+  let dilations = attributes.ints("dilations").map(|s| s.to_vec()).unwrap_or_else(|| vec![1; n]);
+  let output_padding = attributes.ints("output_padding").map(|s| s.to_vec()).unwrap_or_else(|| vec![0; n]);
+  let pads = attributes.ints("pads").map(|s| s.to_vec()).unwrap_or_else(|| vec![0; 2 * n]);
+  if strides.len() != n || dilations.len() != n || output_padding.len() != n || pads.len() != 2 * n {
+    return Err(InferenceError::bad_attribute(node, "strides", format!("strides, dilations, output_padding and pads must have {}, {}, {} and {} values", n, n, n, 2 * n)));
+  }
+  let auto_pad = attributes.string("auto_pad").unwrap_or("NOTSET");
+  for i in 0..n {
+    let (size, kernel) = match (&x_shape[i + 2], &w_shape[i + 2]) {
+      (Dim::Value(size), Dim::Value(kernel)) => (*size as i64, *kernel as i64),
+      _ => { shape.push(Dim::Unknown); continue; }
+    };
+    let out = match auto_pad {
+      "SAME_UPPER" | "SAME_LOWER" => size * strides[i],
+      "VALID" => strides[i] * (size - 1) + output_padding[i] + (kernel - 1) * dilations[i] + 1,
+      "NOTSET" | "NOT_SET" => strides[i] * (size - 1) + output_padding[i] + (kernel - 1) * dilations[i] + 1 - pads[i] - pads[i + n],
+      other => return Err(InferenceError::bad_attribute(node, "auto_pad", format!("unknown padding {}", other)))
+    };
+    if out <= 0 {
+      return Err(InferenceError::shape_mismatch(node, format!("pads {:?} remove the whole output of input {:?}", pads, x_shape)));
+    }
+    shape.push(Dim::Value(out as usize));
+  }
+  Ok(vec![TensorType { elem_type: x.elem_type, shape: Some(shape) }])
+}
+
+/*
+This function computes the spatial dimensions produced by a convolution or a pooling
+  -It takes 4 parameters:
+    ~ node: considered node
+    ~ attributes: attributes of the node (auto_pad, pads, strides, dilations)
+    ~ input: spatial dimensions of the input
+    ~ kernel: spatial dimensions of the kernel
+  -It returns the output dimensions (Unknown where the input dimension isn't a fixed size)
+*/
+fn spatial_output_dims(node: &NodeProto, attributes: &NodeAttributes, input: &[Dim], kernel: &[usize]) -> Result<Vec<Dim>, InferenceError> {
+  let n = input.len();
+  if kernel.len() != n {
+    return Err(InferenceError::bad_attribute(node, "kernel_shape", format!("expected {} values", n)));
+  }
+  let strides = attributes.ints("strides").map(|s| s.to_vec()).unwrap_or_else(|| vec![1; n]);
+  let dilations = attributes.ints("dilations").map(|s| s.to_vec()).unwrap_or_else(|| vec![1; n]);
+  let pads = attributes.ints("pads").map(|s| s.to_vec()).unwrap_or_else(|| vec![0; 2 * n]);
+  if strides.len() != n || dilations.len() != n || pads.len() != 2 * n {
+    return Err(InferenceError::bad_attribute(node, "strides", format!("strides, dilations and pads must have {}, {} and {} values", n, n, 2 * n)));
+  }
+  let auto_pad = attributes.string("auto_pad").unwrap_or("NOTSET");
+  let ceil_mode = attributes.int("ceil_mode").unwrap_or(0) != 0;
+  /* Number of windows over a span (padded input minus the window), rounded down or up. With ceil_mode the last
+  window must start before the end of the input (limit) */
+  let pooled_size = |span: i64, stride: i64, ceil_mode: bool, limit: i64| -> i64 {
+    if span < 0 {
+      return 0;
+    }
+    if !ceil_mode {
+      return span / stride + 1;
+    }
+    let out = (span + stride - 1) / stride + 1;
+    if (out - 1) * stride >= limit { out - 1 } else { out }
+  };
+
+  let mut output = Vec::with_capacity(n);
+  for i in 0..n {
+    let size = match input[i] {
+      Dim::Value(v) => v as i64,
+      _ => { output.push(Dim::Unknown); continue; }
+    };
+    let stride = strides[i].max(1);
+    let effective_kernel = (kernel[i] as i64 - 1) * dilations[i] + 1;
+    let out = match auto_pad {
+      "SAME_UPPER" | "SAME_LOWER" => (size + stride - 1) / stride,
+      "VALID" => pooled_size(size - effective_kernel, stride, ceil_mode, size),
+      "NOTSET" | "NOT_SET" => pooled_size(size + pads[i] + pads[i + n] - effective_kernel, stride, ceil_mode, size + pads[i]),
+      other => return Err(InferenceError::bad_attribute(node, "auto_pad", format!("unknown padding {}", other)))
+    };
+    if out <= 0 {
+      return Err(InferenceError::shape_mismatch(node, format!("kernel {:?} is bigger than input {:?}", kernel, input)));
+    }
+    output.push(Dim::Value(out as usize));
+  }
+  Ok(output)
+}
+
+/*
+This function computes the dimensions produced by a Reshape with a constant shape
+  -It takes 4 parameters:
+    ~ node: considered node
+    ~ data: type of the reshaped tensor
+    ~ target: requested shape (0 copies the input dimension unless allowzero is set, -1 is inferred)
+    ~ allowzero: if true a 0 in target is an actual 0 dimension
+*/
+fn reshape_dims(node: &NodeProto, data: &TensorType, target: &[i64], allowzero: bool) -> Result<Vec<Dim>, InferenceError> {
+  let mut out: Vec<Dim> = Vec::with_capacity(target.len());
+  let mut inferred_position = None;
+  for (i, &t) in target.iter().enumerate() {
+    match t {
+      /* With an unknown input shape the copied dimension is unknown as well */
+      0 if !allowzero => out.push(match &data.shape {
+        None => Dim::Unknown,
+        Some(shape) => match shape.get(i) {
+          Some(d) => d.clone(),
+          None => return Err(InferenceError::shape_mismatch(node, format!("shape {:?} copies dimension {} that the input doesn't have", target, i)))
+        }
+      }),
+      -1 if inferred_position.is_none() => { inferred_position = Some(i); out.push(Dim::Unknown); }
+      t if t >= 0 => out.push(Dim::Value(t as usize)),
+      _ => return Err(InferenceError::shape_mismatch(node, format!("invalid shape {:?}", target)))
+    }
+  }
+
+  let total = data.shape.as_ref().and_then(|s| fixed_size(s));
+  let known = fixed_size(&out.iter().enumerate().filter(|(i, _)| Some(*i) != inferred_position).map(|(_, d)| d.clone()).collect::<Vec<Dim>>());
+  match (inferred_position, total, known) {
+    (Some(position), Some(total), Some(known)) => {
+      if known == 0 || total % known != 0 {
+        return Err(InferenceError::shape_mismatch(node, format!("cannot reshape {:?} into {:?}", data.shape, target)));
+      }
+      out[position] = Dim::Value(total / known);
+    }
+    (None, Some(total), Some(known)) if total != known => {
+      return Err(InferenceError::shape_mismatch(node, format!("cannot reshape {:?} into {:?}", data.shape, target)));
+    }
+    _ => {}
+  }
+  Ok(out)
+}
+
+/* Values of a constant integer input (i.e. axes, shape), None if it is computed at run time */
+fn constant_ints(constants: &[Option<&TensorProto>], i: usize) -> Result<Option<Vec<i64>>, InferenceError> {
+  match constants.get(i).copied().flatten() {
+    Some(proto) => Ok(Some(Tensor::from_proto(proto)?.to_i64().iter().copied().collect())),
+    None => Ok(None)
+  }
+}
+
+/* Position of a (possibly negative) axis attribute, BadAttribute if it is out of range */
+fn normalize_dim(node: &NodeProto, attribute: &str, axis: i64, rank: usize) -> Result<usize, InferenceError> {
+  let normalized = if axis < 0 { axis + rank as i64 } else { axis };
+  if normalized < 0 || normalized >= rank as i64 {
+    return Err(InferenceError::bad_attribute(node, attribute, format!("axis {} out of range for rank {}", axis, rank)));
+  }
+  Ok(normalized as usize)
+}
+
+/*
+This function computes the dimensions of a matrix product with numpy semantics
+(1-D operands are promoted to matrices, the batch dimensions are broadcast)
+*/
+fn matmul_dims(node: &NodeProto, a: &[Dim], b: &[Dim]) -> Result<Vec<Dim>, InferenceError> {
+  if a.is_empty() || b.is_empty() {
+    return Err(InferenceError::shape_mismatch(node, "matrix product of a scalar"));
+  }
+  let a_matrix: Vec<Dim> = if a.len() == 1 { vec![Dim::Value(1), a[0].clone()] } else { a.to_vec() };
+  let b_matrix: Vec<Dim> = if b.len() == 1 { vec![b[0].clone(), Dim::Value(1)] } else { b.to_vec() };
+  let (a_batch, a_mat) = a_matrix.split_at(a_matrix.len() - 2);
+  let (b_batch, b_mat) = b_matrix.split_at(b_matrix.len() - 2);
+
+  if merge_dim(&a_mat[1], &b_mat[0]).is_none() {
+    return Err(InferenceError::shape_mismatch(node, format!("cannot multiply {:?} by {:?}", a, b)));
+  }
+  let mut out = broadcast_dims(a_batch, b_batch).ok_or_else(|| InferenceError::shape_mismatch(node, format!("cannot broadcast the batch dimensions of {:?} and {:?}", a, b)))?;
+  if a.len() > 1 {
+    out.push(a_mat[0].clone());
+  }
+  if b.len() > 1 {
+    out.push(b_mat[1].clone());
+  }
+  Ok(out)
+}
+
+/*
+This function broadcasts two declared shapes (numpy multidirectional broadcasting)
+  -It returns None if two fixed dimensions are different and none of them is 1
+*/
+pub(crate) fn broadcast_dims(a: &[Dim], b: &[Dim]) -> Option<Vec<Dim>> {
+  let rank = a.len().max(b.len());
+  let mut out = Vec::with_capacity(rank);
+  for i in 0..rank {
+    let da = if i < rank - a.len() { Dim::Value(1) } else { a[i - (rank - a.len())].clone() };
+    let db = if i < rank - b.len() { Dim::Value(1) } else { b[i - (rank - b.len())].clone() };
+    out.push(match (&da, &db) {
+      (Dim::Value(1), _) => db,
+      (_, Dim::Value(1)) => da,
+      _ => merge_dim(&da, &db)?
+    });
+  }
+  Some(out)
+}
+
+/*
+This function merges two descriptions of the same dimension, keeping the most precise one
+  -It returns None if they are different fixed sizes
+*/
+fn merge_dim(a: &Dim, b: &Dim) -> Option<Dim> {
+  match (a, b) {
+    (Dim::Value(x), Dim::Value(y)) => if x == y { Some(a.clone()) } else { None },
+    (Dim::Value(_), _) => Some(a.clone()),
+    (_, Dim::Value(_)) => Some(b.clone()),
+    (Dim::Param(_), _) => Some(a.clone()),
+    _ => Some(b.clone())
+  }
+}
+
+/*
+This function merges the inferred type of an output with its declaration
+  -It returns ShapeMismatch if they are incompatible
+*/
+fn merge_types(node: &NodeProto, name: &str, inferred: TensorType, declared: &TensorType) -> Result<TensorType, InferenceError> {
+  let mismatch = || InferenceError::shape_mismatch(node, format!("output {} is declared as {:?} but inferred as {:?}", name, declared, inferred));
+
+  let elem_type = match (inferred.elem_type, declared.elem_type) {
+    (DataType::UNDEFINED, d) => d,
+    (i, DataType::UNDEFINED) => i,
+    (i, d) if i == d => i,
+    _ => return Err(mismatch())
+  };
+  let shape = match (&inferred.shape, &declared.shape) {
+    (Some(i), Some(d)) => {
+      if i.len() != d.len() {
+        return Err(mismatch());
+      }
+      let mut merged = Vec::with_capacity(i.len());
+      for (a, b) in i.iter().zip(d) {
+        merged.push(merge_dim(a, b).ok_or_else(mismatch)?);
+      }
+      Some(merged)
+    }
+    (Some(i), None) => Some(i.clone()),
+    (None, d) => d.clone()
+  };
+  Ok(TensorType { elem_type, shape })
+}
+
+/* Number of elements of a shape, if all its dimensions are fixed */
+fn fixed_size(shape: &[Dim]) -> Option<usize> {
+  shape.iter().map(|d| match d { Dim::Value(v) => Some(*v), _ => None }).product()
+}
+
+/*
+This function builds the declaration of a value of the graph
+  -It takes 2 parameters:
+    ~ name: name of the value
+    ~ value_type: element type and shape of the value
+*/
+pub(crate) fn to_value_info(name: &str, value_type: &TensorType) -> ValueInfoProto {
+  let mut tensor_type = TypeProtoTensor::new();
+  tensor_type.elem_type = Some(value_type.elem_type.value());
+  if let Some(shape) = &value_type.shape {
+    let mut shape_proto = TensorShapeProto::new();
+    shape_proto.dim = shape.iter().map(|d| {
+      let mut dim = Dimension::new();
+      dim.value = match d {
+        Dim::Value(v) => Some(DimValue(*v as i64)),
+        Dim::Param(p) => Some(DimParam(p.clone())),
+        Dim::Unknown => None
+      };
+      dim
+    }).collect();
+    tensor_type.shape = MessageField::some(shape_proto);
+  }
+
+  let mut type_proto = TypeProto::new();
+  type_proto.value = Some(Value::TensorType(tensor_type));
+
+  let mut value_info = ValueInfoProto::new();
+  value_info.name = Some(name.to_string());
+  value_info.type_ = MessageField::some(type_proto);
+  value_info
+}
+
+#[allow(dead_code)]
+pub fn test_shape_inference() {
+  use crate::test_graph::{int64_initializer, ints_attribute, model, node, value_info};
+
+  let n = || Dim::Param("N".to_string());
+  let dims = |values: &[usize]| Some(values.iter().map(|&v| Dim::Value(v)).collect::<Vec<Dim>>());
+
+  // Broadcast of a symbolic batch with a fixed shape
+  let broadcast = model(
+    vec![node("Add", &["a", "b"], &["c"], vec![])],
+    vec![value_info("a", DataType::FLOAT, Some(vec![n(), Dim::Value(1), Dim::Value(4)])), value_info("b", DataType::FLOAT, dims(&[3, 1]))],
+    vec![],
+    vec![],
+    13,
+  );
+  println!("broadcast: {:?}", infer_value_types(&broadcast).map(|types| types["c"].clone()));
+  println!("expected: FLOAT [N, 3, 4]");
+
+  // Conv with pads 1 keeps the spatial size, MaxPool 2x2 with strides 2 halves it
+  let conv_pool = model(
+    vec![
+      node("Conv", &["x", "w"], &["conv"], vec![ints_attribute("kernel_shape", &[3, 3]), ints_attribute("pads", &[1, 1, 1, 1])]),
+      node("MaxPool", &["conv"], &["pool"], vec![ints_attribute("kernel_shape", &[2, 2]), ints_attribute("strides", &[2, 2])]),
+    ],
+    vec![value_info("x", DataType::FLOAT, Some(vec![n(), Dim::Value(1), Dim::Value(6), Dim::Value(6)])), value_info("w", DataType::FLOAT, dims(&[2, 1, 3, 3]))],
+    vec![],
+    vec![],
+    13,
+  );
+  let types = infer_value_types(&conv_pool);
+  println!("conv: {:?}", types.as_ref().map(|types| types["conv"].clone()));
+  println!("expected: FLOAT [N, 2, 6, 6]");
+  println!("pool: {:?}", types.map(|types| types["pool"].clone()));
+  println!("expected: FLOAT [N, 2, 3, 3]");
+
+  // Reshape with 0 (copy the input dimension) and -1 (inferred from the number of elements)
+  for (name, shape) in [("fixed", dims(&[2, 3, 4])), ("symbolic", Some(vec![n(), Dim::Value(3), Dim::Value(4)])), ("unknown", None)] {
+    let reshape = model(
+      vec![node("Reshape", &["data", "shape"], &["reshaped"], vec![])],
+      vec![value_info("data", DataType::FLOAT, shape)],
+      vec![],
+      vec![int64_initializer("shape", &[2], &[0, -1])],
+      13,
+    );
+    println!("reshape {}: {:?}", name, infer_value_types(&reshape).map(|types| types["reshaped"].clone()));
+  }
+  println!("expected: fixed [2, 12], symbolic [N, Unknown], unknown [Unknown, Unknown]");
+
+  // An output declared with a shape different from the inferred one
+  let mismatch = model(
+    vec![node("Relu", &["x"], &["y"], vec![])],
+    vec![value_info("x", DataType::FLOAT, Some(vec![n(), Dim::Value(3)]))],
+    vec![value_info("y", DataType::FLOAT, Some(vec![n(), Dim::Value(5)]))],
+    vec![],
+    13,
+  );
+  println!("mismatch: {:?}", infer_value_types(&mismatch).map(|types| types["y"].clone()));
+  println!("expected: ShapeMismatch (y declared as [N, 5] but inferred as [N, 3])");
+}
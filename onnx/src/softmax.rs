@@ -1,17 +1,31 @@
-use ndarray::{Array, Axis};
+use ndarray::{Array, ArrayD, Axis};
 use ndarray::prelude::*;
+use num_traits::Float;
 
 //OPSET VERSION = 8
-pub fn softmax(input: Array4<f32>, axis: Option<usize>) -> Array2<f32> {
-  let batch_size = input.len_of(Axis(0));
-  let other_size = input.len_of(Axis(1))*input.len_of(Axis(2))*input.len_of(Axis(3));
-  let mut x: Array2<f32> = input.into_shape((batch_size, other_size)).unwrap();
+//The input is coerced into a 2D matrix (dimensions before axis, dimensions from axis on), the output has the input shape
+//and element type
+pub fn softmax<F: Float>(input: ArrayD<F>, axis: Option<usize>) -> ArrayD<F> {
+  let input_shape = input.shape().to_vec();
+  let axis = axis.unwrap_or(1);
+  let batch_size: usize = input_shape[..axis].iter().product();
+  let other_size: usize = input_shape[axis..].iter().product();
+  /* into_shape follows the memory order of the array: a column-major input (i.e. produced by Concat or Tile) is first
+  copied in row-major order, so that the rows of the matrix are the ones of the onnx semantics */
+  let x: Array2<F> = input.as_standard_layout().into_owned().into_shape((batch_size, other_size)).unwrap();
   //let mut x_64 = x.map(|x| *x as f64);
 
-  let axis = axis.unwrap_or(1);
-  let max_val = x.fold_axis(Axis(axis), f32::NEG_INFINITY, |&max, &el| el.max(max));
-  x -= &max_val.insert_axis(Axis(axis));
-  let exp_x = x.mapv(f32::exp);
+  let max_val = x.fold_axis(Axis(1), F::neg_infinity(), |&max, &el| el.max(max));
+  let exp_x = (&x - &max_val.insert_axis(Axis(1))).mapv(F::exp);
+  let sum_exp_x = exp_x.sum_axis(Axis(1));
+  (exp_x / &sum_exp_x.insert_axis(Axis(1))).as_standard_layout().into_owned().into_shape(input_shape).unwrap()
+}
+
+//OPSET VERSION = 13
+//The softmax is computed along the single axis, the other dimensions are independent
+pub fn softmax_axis<F: Float>(input: ArrayD<F>, axis: usize) -> ArrayD<F> {
+  let max_val = input.fold_axis(Axis(axis), F::neg_infinity(), |&max, &el| el.max(max));
+  let exp_x = (&input - &max_val.insert_axis(Axis(axis))).mapv(F::exp);
   let sum_exp_x = exp_x.sum_axis(Axis(axis));
   exp_x / &sum_exp_x.insert_axis(Axis(axis))
 }
@@ -22,6 +36,18 @@ pub fn test_softmax() {
   let x = Array::from_shape_vec((1, 1, 2,4), vec![118.85734,5640.1426,2.,3.,1000.,1001.,1002.,1003.]).unwrap();
   let _x_2 = Array::from_shape_vec((1, 1, 1,3), vec![-1.,0.,1.]).unwrap();
   println!("input: \n{:?}", x);
-  let result = softmax(x, None);
+  let result = softmax(x.clone().into_dyn(), None);
   println!("output: \n{:?}", result);
+  let result_13 = softmax_axis(x.into_dyn(), 3);
+  println!("output (opset 13, axis -1): \n{:?}", result_13);
+
+  // Column-major input, as the ones produced by Concat and Tile: the result must not depend on the memory order
+  let column_major = Array::from_shape_vec((2, 2).f(), vec![1., 3., 2., 4.]).unwrap();
+  println!("column-major input: {:?}", softmax(column_major.into_dyn(), Some(1)));
+  println!("expected: [[0.2689, 0.7311], [0.2689, 0.7311]]");
+
+  // Double input: the result keeps the precision of the input
+  let double = Array::from_shape_vec((1, 2), vec![0f64, 1e-12]).unwrap();
+  println!("double input: {:?}", softmax_axis(double.into_dyn(), 1));
+  println!("expected: [[0.49999999999975, 0.50000000000025]]");
 }
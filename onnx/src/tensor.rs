@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use half::f16;
+use num_traits::Float;
+use protobuf::Enum;
+use ndarray::{ArrayD, IxDyn};
+use crate::inference_error::InferenceError;
+use crate::onnx_structure::{TensorProto, ValueInfoProto};
+use crate::onnx_structure::tensor_proto::DataType;
+use crate::onnx_structure::tensor_shape_proto::dimension::Value::{DimParam, DimValue};
+use crate::onnx_structure::type_proto::Value;
+
+/*
+Value flowing through the graph: an n-dimensional array of any rank, tagged with its element type.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tensor {
+  F32(ArrayD<f32>),
+  F64(ArrayD<f64>),
+  I64(ArrayD<i64>),
+  I32(ArrayD<i32>),
+  U8(ArrayD<u8>),
+  I8(ArrayD<i8>),
+  Bool(ArrayD<bool>),
+  F16(ArrayD<f16>),
+}
+
+/*
+Dimension of a declared shape: a fixed size, a symbolic name (i.e. batch_size) or nothing at all
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Dim {
+  Value(usize),
+  Param(String),
+  Unknown,
+}
+
+/*
+Element type and shape declared for a value of the graph (graph inputs, outputs and value_info).
+The shape is None when the rank itself isn't declared.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct TensorType {
+  pub elem_type: DataType,
+  pub shape: Option<Vec<Dim>>,
+}
+
+/* Runs the same expression over the array held by any variant of the tensor */
+macro_rules! for_each_variant {
+  ($tensor:expr, $arr:ident => $body:expr) => {
+    match $tensor {
+      Tensor::F32($arr) => $body,
+      Tensor::F64($arr) => $body,
+      Tensor::I64($arr) => $body,
+      Tensor::I32($arr) => $body,
+      Tensor::U8($arr) => $body,
+      Tensor::I8($arr) => $body,
+      Tensor::Bool($arr) => $body,
+      Tensor::F16($arr) => $body,
+    }
+  };
+}
+
+/* Runs the same expression over the array held by any variant of the tensor and wraps the resulting array into the same variant */
+macro_rules! map_each_variant {
+  ($tensor:expr, $arr:ident => $body:expr) => {
+    match $tensor {
+      Tensor::F32($arr) => Tensor::F32($body),
+      Tensor::F64($arr) => Tensor::F64($body),
+      Tensor::I64($arr) => Tensor::I64($body),
+      Tensor::I32($arr) => Tensor::I32($body),
+      Tensor::U8($arr) => Tensor::U8($body),
+      Tensor::I8($arr) => Tensor::I8($body),
+      Tensor::Bool($arr) => Tensor::Bool($body),
+      Tensor::F16($arr) => Tensor::F16($body),
+    }
+  };
+}
+pub(crate) use map_each_variant;
+
+macro_rules! impl_from_array {
+  ($($elem:ty => $variant:ident),*) => {
+    $(
+      impl From<ArrayD<$elem>> for Tensor {
+        fn from(arr: ArrayD<$elem>) -> Self {
+          Tensor::$variant(arr)
+        }
+      }
+    )*
+  };
+}
+
+impl_from_array!(f32 => F32, f64 => F64, i64 => I64, i32 => I32, u8 => U8, i8 => I8, bool => Bool, f16 => F16);
+
+impl Tensor {
+  /* Dimensions of the tensor */
+  pub fn shape(&self) -> &[usize] {
+    for_each_variant!(self, arr => arr.shape())
+  }
+
+  /* Number of dimensions of the tensor */
+  pub fn rank(&self) -> usize {
+    self.shape().len()
+  }
+
+  /* Number of elements of the tensor */
+  pub fn len(&self) -> usize {
+    for_each_variant!(self, arr => arr.len())
+  }
+
+  /* True if the tensor has no elements */
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /* ONNX element type of the tensor */
+  pub fn data_type(&self) -> DataType {
+    match self {
+      Tensor::F32(_) => DataType::FLOAT,
+      Tensor::F64(_) => DataType::DOUBLE,
+      Tensor::I64(_) => DataType::INT64,
+      Tensor::I32(_) => DataType::INT32,
+      Tensor::U8(_) => DataType::UINT8,
+      Tensor::I8(_) => DataType::INT8,
+      Tensor::Bool(_) => DataType::BOOL,
+      Tensor::F16(_) => DataType::FLOAT16,
+    }
+  }
+
+  /*
+  This function converts the tensor into an f32 array (the element type used by the convolutional kernels)
+  */
+  pub fn to_f32(&self) -> ArrayD<f32> {
+    match self {
+      Tensor::F32(arr) => arr.clone(),
+      Tensor::F64(arr) => arr.mapv(|x| x as f32),
+      Tensor::I64(arr) => arr.mapv(|x| x as f32),
+      Tensor::I32(arr) => arr.mapv(|x| x as f32),
+      Tensor::U8(arr) => arr.mapv(|x| x as f32),
+      Tensor::I8(arr) => arr.mapv(|x| x as f32),
+      Tensor::Bool(arr) => arr.mapv(|x| if x { 1.0 } else { 0.0 }),
+      Tensor::F16(arr) => arr.mapv(|x| x.to_f32()),
+    }
+  }
+
+  /*
+  This function converts the tensor into an array of a floating point type (i.e. the parameters of an operation to the
+  element type of its input)
+  */
+  pub fn to_float<F: Float>(&self) -> ArrayD<F> {
+    self.to_f64().mapv(|x| F::from(x).unwrap())
+  }
+
+  /*
+  This function converts the tensor into an f64 array
+  */
+  pub fn to_f64(&self) -> ArrayD<f64> {
+    match self {
+      Tensor::F32(arr) => arr.mapv(|x| x as f64),
+      Tensor::F64(arr) => arr.clone(),
+      Tensor::I64(arr) => arr.mapv(|x| x as f64),
+      Tensor::I32(arr) => arr.mapv(|x| x as f64),
+      Tensor::U8(arr) => arr.mapv(|x| x as f64),
+      Tensor::I8(arr) => arr.mapv(|x| x as f64),
+      Tensor::Bool(arr) => arr.mapv(|x| if x { 1.0 } else { 0.0 }),
+      Tensor::F16(arr) => arr.mapv(|x| x.to_f64()),
+    }
+  }
+
+  /*
+  This function converts the tensor into an i64 array (the element type used by shapes and indices)
+  */
+  pub fn to_i64(&self) -> ArrayD<i64> {
+    match self {
+      Tensor::F32(arr) => arr.mapv(|x| x as i64),
+      Tensor::F64(arr) => arr.mapv(|x| x as i64),
+      Tensor::I64(arr) => arr.clone(),
+      Tensor::I32(arr) => arr.mapv(|x| x as i64),
+      Tensor::U8(arr) => arr.mapv(|x| x as i64),
+      Tensor::I8(arr) => arr.mapv(|x| x as i64),
+      Tensor::Bool(arr) => arr.mapv(|x| x as i64),
+      Tensor::F16(arr) => arr.mapv(|x| x.to_f32() as i64),
+    }
+  }
+
+  /*
+  This function decodes an initializer (or any serialized tensor) of the onnx model
+    -It takes 1 parameter:
+      ~ proto: serialized tensor, with its data either in raw_data or in the typed fields
+    -It returns the decoded tensor, or an error if its data type isn't supported or its data doesn't match its dims
+  */
+  pub fn from_proto(proto: &TensorProto) -> Result<Tensor, InferenceError> {
+    let name = proto.name.clone().unwrap_or_default();
+    let shape: Vec<usize> = proto.dims.iter().map(|&d| d as usize).collect();
+    let data_type = DataType::from_i32(proto.data_type.unwrap_or(DataType::UNDEFINED as i32)).unwrap_or(DataType::UNDEFINED);
+    let raw_data = proto.raw_data.as_deref();
+
+    match data_type {
+      DataType::FLOAT => Ok(Tensor::F32(build_array(&name, &shape, match raw_data {
+        Some(bytes) => bytes.chunks_exact(4).map(|c| f32::from_le_bytes(c.try_into().unwrap())).collect(),
+        None => proto.float_data.clone(),
+      })?)),
+      DataType::DOUBLE => Ok(Tensor::F64(build_array(&name, &shape, match raw_data {
+        Some(bytes) => bytes.chunks_exact(8).map(|c| f64::from_le_bytes(c.try_into().unwrap())).collect(),
+        None => proto.double_data.clone(),
+      })?)),
+      DataType::INT64 => Ok(Tensor::I64(build_array(&name, &shape, match raw_data {
+        Some(bytes) => bytes.chunks_exact(8).map(|c| i64::from_le_bytes(c.try_into().unwrap())).collect(),
+        None => proto.int64_data.clone(),
+      })?)),
+      DataType::INT32 => Ok(Tensor::I32(build_array(&name, &shape, match raw_data {
+        Some(bytes) => bytes.chunks_exact(4).map(|c| i32::from_le_bytes(c.try_into().unwrap())).collect(),
+        None => proto.int32_data.clone(),
+      })?)),
+      DataType::UINT8 => Ok(Tensor::U8(build_array(&name, &shape, match raw_data {
+        Some(bytes) => bytes.to_vec(),
+        None => proto.int32_data.iter().map(|&x| x as u8).collect(),
+      })?)),
+      DataType::INT8 => Ok(Tensor::I8(build_array(&name, &shape, match raw_data {
+        Some(bytes) => bytes.iter().map(|&x| x as i8).collect(),
+        None => proto.int32_data.iter().map(|&x| x as i8).collect(),
+      })?)),
+      DataType::BOOL => Ok(Tensor::Bool(build_array(&name, &shape, match raw_data {
+        Some(bytes) => bytes.iter().map(|&x| x != 0).collect(),
+        None => proto.int32_data.iter().map(|&x| x != 0).collect(),
+      })?)),
+      DataType::FLOAT16 => Ok(Tensor::F16(build_array(&name, &shape, match raw_data {
+        Some(bytes) => bytes.chunks_exact(2).map(|c| f16::from_le_bytes(c.try_into().unwrap())).collect(),
+        None => proto.int32_data.iter().map(|&x| f16::from_bits(x as u16)).collect(),
+      })?)),
+      _ => Err(InferenceError::MalformedModel(format!("data type {:?} of tensor {} is not supported", data_type, name)))
+    }
+  }
+}
+
+impl TensorType {
+  /*
+  This function reads the declared type of a value of the graph
+    -It takes 1 parameter:
+      ~ value_info: declaration of the value
+    -It returns the element type and the shape, or an error if the value isn't a tensor or its type isn't declared
+  */
+  pub fn from_value_info(value_info: &ValueInfoProto) -> Result<TensorType, InferenceError> {
+    let name = value_info.name.clone().unwrap_or_default();
+    match value_info.type_.value.as_ref() {
+      Some(Value::TensorType(t)) => {
+        let elem_type = DataType::from_i32(t.elem_type.unwrap_or(DataType::UNDEFINED as i32)).unwrap_or(DataType::UNDEFINED);
+        let shape = t.shape.as_ref().map(|shape| shape.dim.iter().map(|el| match &el.value {
+          Some(DimValue(v)) if *v >= 0 => Dim::Value(*v as usize),
+          Some(DimParam(p)) if !p.is_empty() => Dim::Param(p.clone()),
+          _ => Dim::Unknown,
+        }).collect());
+        Ok(TensorType { elem_type, shape })
+      }
+      Some(_) => Err(InferenceError::InvalidInput { input: name, message: "only tensor values are supported".to_string() }),
+      None => Err(InferenceError::MalformedModel(format!("type of value {} not declared", name)))
+    }
+  }
+
+  /*
+  This function checks that a tensor matches the declared type, binding the symbolic dimensions to the actual sizes
+    -It takes 2 parameters:
+      ~ value: tensor to check
+      ~ bindings: sizes of the symbolic dimensions met so far (i.e. batch_size -> 4), updated with the new ones
+    -It returns a description of the difference if the element type, the rank or a dimension doesn't match
+  */
+  pub fn check(&self, value: &Tensor, bindings: &mut HashMap<String, usize>) -> Result<(), String> {
+    if self.elem_type != DataType::UNDEFINED && self.elem_type != value.data_type() {
+      return Err(format!("expected element type {:?}, got {:?}", self.elem_type, value.data_type()));
+    }
+    if let Some(shape) = &self.shape {
+      if shape.len() != value.rank() {
+        return Err(format!("expected shape {:?}, got {:?}", shape, value.shape()));
+      }
+      for (dim, &size) in shape.iter().zip(value.shape()) {
+        match dim {
+          Dim::Value(v) if *v != size => return Err(format!("expected shape {:?}, got {:?}", shape, value.shape())),
+          Dim::Param(p) => match bindings.get(p) {
+            Some(&bound) if bound != size => return Err(format!("dimension {} is {} here but {} elsewhere (shape {:?})", p, size, bound, value.shape())),
+            Some(_) => {}
+            None => { bindings.insert(p.clone(), size); }
+          },
+          _ => {}
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+/*
+This function builds the array of a decoded tensor
+  -It takes 3 parameters:
+    ~ name: name of the tensor (used for error reporting)
+    ~ shape: dimensions of the tensor (an empty shape is a scalar)
+    ~ data: elements of the tensor in row major order
+  -It returns the array, or an error if the number of elements doesn't match the shape
+*/
+fn build_array<T>(name: &str, shape: &[usize], data: Vec<T>) -> Result<ArrayD<T>, InferenceError> {
+  ArrayD::from_shape_vec(IxDyn(shape), data)
+    .map_err(|e| InferenceError::MalformedModel(format!("cannot decode tensor {} with shape {:?}: {}", name, shape, e)))
+}
+
+/*
+This function computes the shape obtained by broadcasting two shapes (numpy multidirectional broadcasting)
+  -It takes 2 parameters:
+    ~ shape_1: shape of the first operand
+    ~ shape_2: shape of the second operand
+  -It returns the broadcast shape, None if the shapes aren't compatible
+*/
+pub fn broadcast_shapes(shape_1: &[usize], shape_2: &[usize]) -> Option<Vec<usize>> {
+  let rank = shape_1.len().max(shape_2.len());
+  let mut shape = vec![0; rank];
+
+  for i in 0..rank {
+    let d1 = if i < rank - shape_1.len() { 1 } else { shape_1[i - (rank - shape_1.len())] };
+    let d2 = if i < rank - shape_2.len() { 1 } else { shape_2[i - (rank - shape_2.len())] };
+    shape[i] = match (d1, d2) {
+      (a, b) if a == b => a,
+      (1, b) => b,
+      (a, 1) => a,
+      _ => return None,
+    };
+  }
+
+  Some(shape)
+}
@@ -0,0 +1,91 @@
+use protobuf::EnumOrUnknown;
+use crate::onnx_structure::{AttributeProto, GraphProto, ModelProto, NodeProto, OperatorSetIdProto, TensorProto, ValueInfoProto};
+use crate::onnx_structure::attribute_proto::AttributeType;
+use crate::onnx_structure::tensor_proto::DataType;
+use crate::shape_inference::to_value_info;
+use crate::tensor::{Dim, TensorType};
+
+/*
+Builders of small models for the test functions that work on whole graphs (session, checker, shape inference).
+The nodes are named after their first output.
+*/
+
+#[allow(dead_code)]
+pub(crate) fn node(op_type: &str, inputs: &[&str], outputs: &[&str], attributes: Vec<AttributeProto>) -> NodeProto {
+  let mut node = NodeProto::new();
+  node.op_type = Some(op_type.to_string());
+  node.name = outputs.first().map(|output| output.to_string());
+  node.input = inputs.iter().map(|input| input.to_string()).collect();
+  node.output = outputs.iter().map(|output| output.to_string()).collect();
+  node.attribute = attributes;
+  node
+}
+
+#[allow(dead_code)]
+pub(crate) fn int_attribute(name: &str, value: i64) -> AttributeProto {
+  let mut attribute = attribute(name, AttributeType::INT);
+  attribute.i = Some(value);
+  attribute
+}
+
+#[allow(dead_code)]
+pub(crate) fn ints_attribute(name: &str, values: &[i64]) -> AttributeProto {
+  let mut attribute = attribute(name, AttributeType::INTS);
+  attribute.ints = values.to_vec();
+  attribute
+}
+
+#[allow(dead_code)]
+pub(crate) fn string_attribute(name: &str, value: &str) -> AttributeProto {
+  let mut attribute = attribute(name, AttributeType::STRING);
+  attribute.s = Some(value.as_bytes().to_vec());
+  attribute
+}
+
+fn attribute(name: &str, attribute_type: AttributeType) -> AttributeProto {
+  let mut attribute = AttributeProto::new();
+  attribute.name = Some(name.to_string());
+  attribute.type_ = Some(EnumOrUnknown::new(attribute_type));
+  attribute
+}
+
+/* Declaration of a value: a shape of None means unknown rank */
+#[allow(dead_code)]
+pub(crate) fn value_info(name: &str, elem_type: DataType, shape: Option<Vec<Dim>>) -> ValueInfoProto {
+  to_value_info(name, &TensorType { elem_type, shape })
+}
+
+#[allow(dead_code)]
+pub(crate) fn int64_initializer(name: &str, dims: &[i64], values: &[i64]) -> TensorProto {
+  let mut tensor = TensorProto::new();
+  tensor.name = Some(name.to_string());
+  tensor.data_type = Some(DataType::INT64 as i32);
+  tensor.dims = dims.to_vec();
+  tensor.int64_data = values.to_vec();
+  tensor
+}
+
+/*
+This function puts the nodes in a model importing the given opset version of the default domain
+  -It takes 5 parameters:
+    ~ nodes: nodes of the graph
+    ~ inputs, outputs: declarations of the graph inputs and outputs
+    ~ initializers: constant values of the graph
+    ~ opset_version: version of the default domain
+  -It returns the model
+*/
+#[allow(dead_code)]
+pub(crate) fn model(nodes: Vec<NodeProto>, inputs: Vec<ValueInfoProto>, outputs: Vec<ValueInfoProto>, initializers: Vec<TensorProto>, opset_version: i64) -> ModelProto {
+  let mut graph = GraphProto::new();
+  graph.node = nodes;
+  graph.input = inputs;
+  graph.output = outputs;
+  graph.initializer = initializers;
+  let mut opset = OperatorSetIdProto::new();
+  opset.domain = Some(String::new());
+  opset.version = Some(opset_version);
+  let mut model = ModelProto::new();
+  model.graph = Some(graph).into();
+  model.opset_import = vec![opset];
+  model
+}
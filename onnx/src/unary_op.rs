@@ -0,0 +1,176 @@
+use half::f16;
+use ndarray::{Array, ArrayD};
+use crate::activation_op::erf;
+use crate::tensor::Tensor;
+
+//OPSET VERSION = 13
+//Element-wise math functions: the output has the shape and the element type of the input.
+//Abs, Neg and Sign are defined for integers too, the others only for floating point inputs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+  Exp,
+  /* Natural logarithm */
+  Log,
+  Sqrt,
+  Reciprocal,
+  Abs,
+  Neg,
+  Floor,
+  Ceil,
+  /* Halves are rounded to the nearest even integer */
+  Round,
+  /* -1, 0 or 1 */
+  Sign,
+  Erf,
+  Sin,
+  Cos,
+  Tan,
+  Asin,
+  Acos,
+  Atan,
+  Sinh,
+  Cosh,
+  Asinh,
+  Acosh,
+  Atanh,
+}
+
+impl UnaryOp {
+  fn defined_for_integers(self) -> bool {
+    matches!(self, UnaryOp::Abs | UnaryOp::Neg | UnaryOp::Sign)
+  }
+}
+
+/* Element type on which the unary operations are defined */
+trait Element: Copy {
+  fn apply(op: UnaryOp, x: Self) -> Self;
+}
+
+macro_rules! impl_float_element {
+  ($($elem:ty),*) => {
+    $(
+      impl Element for $elem {
+        fn apply(op: UnaryOp, x: Self) -> Self {
+          match op {
+            UnaryOp::Exp => x.exp(),
+            UnaryOp::Log => x.ln(),
+            UnaryOp::Sqrt => x.sqrt(),
+            UnaryOp::Reciprocal => 1. / x,
+            UnaryOp::Abs => x.abs(),
+            UnaryOp::Neg => -x,
+            UnaryOp::Floor => x.floor(),
+            UnaryOp::Ceil => x.ceil(),
+            UnaryOp::Round => x.round_ties_even(),
+            /* signum would give 1 for 0 */
+            UnaryOp::Sign => if x == 0. { 0. } else { x.signum() },
+            UnaryOp::Erf => erf(x as f64) as $elem,
+            UnaryOp::Sin => x.sin(),
+            UnaryOp::Cos => x.cos(),
+            UnaryOp::Tan => x.tan(),
+            UnaryOp::Asin => x.asin(),
+            UnaryOp::Acos => x.acos(),
+            UnaryOp::Atan => x.atan(),
+            UnaryOp::Sinh => x.sinh(),
+            UnaryOp::Cosh => x.cosh(),
+            UnaryOp::Asinh => x.asinh(),
+            UnaryOp::Acosh => x.acosh(),
+            UnaryOp::Atanh => x.atanh(),
+          }
+        }
+      }
+    )*
+  };
+}
+
+macro_rules! impl_int_element {
+  ($($elem:ty),*) => {
+    $(
+      impl Element for $elem {
+        #[allow(unused_comparisons)]
+        fn apply(op: UnaryOp, x: Self) -> Self {
+          match op {
+            UnaryOp::Abs => if x < 0 { x.wrapping_neg() } else { x },
+            UnaryOp::Neg => x.wrapping_neg(),
+            UnaryOp::Sign => ((x > 0) as $elem).wrapping_sub((x < 0) as $elem),
+            /* The other operations are rejected by unary before reaching the elements */
+            _ => x,
+          }
+        }
+      }
+    )*
+  };
+}
+
+impl_float_element!(f32, f64);
+impl_int_element!(i64, i32, u8, i8);
+
+impl Element for f16 {
+  fn apply(op: UnaryOp, x: Self) -> Self {
+    f16::from_f32(f32::apply(op, x.to_f32()))
+  }
+}
+
+/*
+This function executes a unary operation
+  -It takes 2 parameters:
+    ~ op: operation
+    ~ x: operand
+  -It returns the result, with the shape and the element type of the operand, or an error if the operation isn't
+  defined for the element type
+*/
+pub fn unary(op: UnaryOp, x: &Tensor) -> Result<Tensor, String> {
+  let integer = matches!(x, Tensor::I64(_) | Tensor::I32(_) | Tensor::U8(_) | Tensor::I8(_));
+  if integer && !op.defined_for_integers() {
+    return Err(format!("{:?} is not defined for {:?}", op, x.data_type()));
+  }
+
+  match x {
+    Tensor::F32(x) => Ok(Tensor::F32(apply(op, x))),
+    Tensor::F64(x) => Ok(Tensor::F64(apply(op, x))),
+    Tensor::F16(x) => Ok(Tensor::F16(apply(op, x))),
+    Tensor::I64(x) => Ok(Tensor::I64(apply(op, x))),
+    Tensor::I32(x) => Ok(Tensor::I32(apply(op, x))),
+    Tensor::U8(x) => Ok(Tensor::U8(apply(op, x))),
+    Tensor::I8(x) => Ok(Tensor::I8(apply(op, x))),
+    Tensor::Bool(_) => Err(format!("{:?} is not defined for {:?}", op, x.data_type())),
+  }
+}
+
+fn apply<T: Element>(op: UnaryOp, x: &ArrayD<T>) -> ArrayD<T> {
+  x.mapv(|v| T::apply(op, v))
+}
+
+#[allow(dead_code)]
+pub fn test_unary() {
+  let x = Tensor::F32(Array::from_shape_vec(6, vec![-2.5, -0.5, 0., 0.5, 1.5, 4.]).unwrap().into_dyn());
+  let cases = [
+    (UnaryOp::Abs, "[2.5, 0.5, 0, 0.5, 1.5, 4]"),
+    (UnaryOp::Neg, "[2.5, 0.5, -0, -0.5, -1.5, -4]"),
+    (UnaryOp::Floor, "[-3, -1, 0, 0, 1, 4]"),
+    (UnaryOp::Ceil, "[-2, -0, 0, 1, 2, 4]"),
+    (UnaryOp::Round, "[-2, -0, 0, 0, 2, 4]"),
+    (UnaryOp::Sign, "[-1, -1, 0, 1, 1, 1]"),
+    (UnaryOp::Reciprocal, "[-0.4, -2, inf, 2, 0.6667, 0.25]"),
+    (UnaryOp::Sqrt, "[NaN, NaN, 0, 0.7071, 1.2247, 2]"),
+    (UnaryOp::Exp, "[0.0821, 0.6065, 1, 1.6487, 4.4817, 54.5982]"),
+    (UnaryOp::Log, "[NaN, NaN, -inf, -0.6931, 0.4055, 1.3863]"),
+    (UnaryOp::Erf, "[-0.9996, -0.5205, 0, 0.5205, 0.9661, 1]"),
+    (UnaryOp::Sin, "[-0.5985, -0.4794, 0, 0.4794, 0.9975, -0.7568]"),
+    (UnaryOp::Atan, "[-1.1903, -0.4636, 0, 0.4636, 0.9828, 1.3258]"),
+    (UnaryOp::Cosh, "[6.1323, 1.1276, 1, 1.1276, 2.3524, 27.3082]"),
+  ];
+  for (op, expected) in cases {
+    println!("{:?}: {:?}", op, unary(op, &x));
+    println!("expected: {}", expected);
+  }
+
+  // Integers keep their element type
+  let integers = Tensor::I32(Array::from_shape_vec(3, vec![-3, 0, 5]).unwrap().into_dyn());
+  println!("abs int: {:?}", unary(UnaryOp::Abs, &integers));
+  println!("expected: [3, 0, 5]");
+  println!("sign int: {:?}", unary(UnaryOp::Sign, &integers));
+  println!("expected: [-1, 0, 1]");
+
+  // Error: the other operations need floating point inputs
+  println!("sqrt int: {:?}", unary(UnaryOp::Sqrt, &integers));
+}